@@ -0,0 +1,6 @@
+-- Add migration script here
+-- 予約配信された号は配信されるまで published_at が NULL となる
+ALTER TABLE newsletter_issues ALTER COLUMN published_at DROP NOT NULL;
+-- scheduled, published, cancelled のいずれか
+ALTER TABLE newsletter_issues ADD COLUMN status TEXT NOT NULL DEFAULT 'published';
+ALTER TABLE newsletter_issues ADD COLUMN send_at timestamptz NULL;
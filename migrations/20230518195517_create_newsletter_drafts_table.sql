@@ -0,0 +1,11 @@
+-- Add migration script here
+CREATE TABLE newsletter_drafts(
+    draft_id uuid NOT NULL,
+    author_id uuid NOT NULL REFERENCES users(user_id),
+    title TEXT NOT NULL,
+    text_content TEXT NOT NULL,
+    html_content TEXT NOT NULL,
+    created_at timestamptz NOT NULL,
+    updated_at timestamptz NOT NULL,
+    PRIMARY KEY (draft_id)
+);
@@ -0,0 +1,37 @@
+-- Add migration script here
+ALTER TABLE issue_delivery_queue ADD COLUMN subscriber_id uuid NULL
+    REFERENCES subscriptions (id);
+UPDATE issue_delivery_queue q
+    SET subscriber_id = s.id
+    FROM subscriptions s
+    WHERE s.email = q.subscriber_email;
+-- 購読者が存在しないタスクは配信状況を記録できないため削除する
+DELETE FROM issue_delivery_queue WHERE subscriber_id IS NULL;
+ALTER TABLE issue_delivery_queue ALTER COLUMN subscriber_id SET NOT NULL;
+
+CREATE TABLE issue_deliveries(
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id),
+    subscriber_email TEXT NOT NULL,
+    -- queued, sent, failed, skipped のいずれか
+    status TEXT NOT NULL,
+    n_attempts SMALLINT NOT NULL DEFAULT 0,
+    last_error TEXT NULL,
+    provider_message_id TEXT NULL,
+    updated_at timestamptz NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, subscriber_id)
+);
+
+-- 配信待ちのタスクも配信状況を記録できるように登録する
+INSERT INTO issue_deliveries (
+    newsletter_issue_id,
+    subscriber_id,
+    subscriber_email,
+    status,
+    n_attempts,
+    updated_at
+)
+SELECT newsletter_issue_id, subscriber_id, subscriber_email, 'queued', n_retries, now()
+FROM issue_delivery_queue;
@@ -0,0 +1,11 @@
+-- Add migration script here
+-- 公開アーカイブのURLに使用する (タイトルとIDの先頭8文字から作成する)
+ALTER TABLE newsletter_issues ADD COLUMN slug TEXT NULL;
+UPDATE newsletter_issues
+    SET slug = concat_ws(
+        '-',
+        NULLIF(trim(BOTH '-' FROM lower(regexp_replace(title, '[^[:alnum:]]+', '-', 'g'))), ''),
+        left(newsletter_issue_id::text, 8)
+    );
+ALTER TABLE newsletter_issues ALTER COLUMN slug SET NOT NULL;
+ALTER TABLE newsletter_issues ADD CONSTRAINT newsletter_issues_slug_key UNIQUE (slug);
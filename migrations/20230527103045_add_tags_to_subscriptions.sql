@@ -0,0 +1,5 @@
+-- Add migration script here
+ALTER TABLE subscriptions ADD COLUMN tags TEXT[] NOT NULL DEFAULT '{}';
+CREATE INDEX subscriptions_tags_idx ON subscriptions USING GIN (tags);
+-- 配信対象を絞り込む条件 (NULL の場合は確認済みの全ての購読者に配信する)
+ALTER TABLE newsletter_issues ADD COLUMN segment TEXT NULL;
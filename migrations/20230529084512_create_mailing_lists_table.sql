@@ -0,0 +1,27 @@
+-- Add migration script here
+CREATE TABLE mailing_lists(
+    id uuid NOT NULL,
+    PRIMARY KEY (id),
+    -- リクエストでリストを指定するための識別子
+    slug TEXT NOT NULL UNIQUE,
+    name TEXT NOT NULL,
+    created_at timestamptz NOT NULL
+);
+
+-- 既存の購読者と号は全てデフォルトのリストに所属させる
+INSERT INTO mailing_lists (id, slug, name, created_at)
+VALUES (gen_random_uuid(), 'default', 'Newsletter', now());
+
+-- 購読はリストごとに作成し、確認状況もリストごとに管理する
+ALTER TABLE subscriptions ADD COLUMN mailing_list_id uuid NULL REFERENCES mailing_lists (id);
+UPDATE subscriptions
+    SET mailing_list_id = (SELECT id FROM mailing_lists WHERE slug = 'default');
+ALTER TABLE subscriptions ALTER COLUMN mailing_list_id SET NOT NULL;
+ALTER TABLE subscriptions DROP CONSTRAINT subscriptions_email_key;
+ALTER TABLE subscriptions
+    ADD CONSTRAINT subscriptions_mailing_list_id_email_key UNIQUE (mailing_list_id, email);
+
+ALTER TABLE newsletter_issues ADD COLUMN mailing_list_id uuid NULL REFERENCES mailing_lists (id);
+UPDATE newsletter_issues
+    SET mailing_list_id = (SELECT id FROM mailing_lists WHERE slug = 'default');
+ALTER TABLE newsletter_issues ALTER COLUMN mailing_list_id SET NOT NULL;
@@ -0,0 +1,10 @@
+-- Add migration script here
+-- 開封の計測は号ごと・購読者ごとに無効化できる
+ALTER TABLE newsletter_issues ADD COLUMN track_opens BOOLEAN NOT NULL DEFAULT TRUE;
+ALTER TABLE subscriptions ADD COLUMN track_opens BOOLEAN NOT NULL DEFAULT TRUE;
+
+-- user_agent には最後に開封されたときの User-Agent を記録する
+ALTER TABLE issue_deliveries
+    ADD COLUMN first_opened_at timestamptz NULL,
+    ADD COLUMN open_count INTEGER NOT NULL DEFAULT 0,
+    ADD COLUMN user_agent TEXT NULL;
@@ -0,0 +1,13 @@
+-- Add migration script here
+-- 購読者ごと・リンクごとのクリック数
+CREATE TABLE issue_link_clicks(
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    subscriber_id uuid NOT NULL
+        REFERENCES subscriptions (id),
+    url TEXT NOT NULL,
+    click_count INTEGER NOT NULL,
+    first_clicked_at timestamptz NOT NULL,
+    last_clicked_at timestamptz NOT NULL,
+    PRIMARY KEY (newsletter_issue_id, subscriber_id, url)
+);
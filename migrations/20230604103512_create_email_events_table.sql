@@ -0,0 +1,20 @@
+-- Add migration script here
+-- メール配信サービスから Webhook で通知されたバウンスと苦情を記録する
+-- Webhook は再送されることがあるため、配信サービスのイベントIDで重複を除く
+CREATE TABLE email_events(
+    record_type TEXT NOT NULL,
+    provider_event_id BIGINT NOT NULL,
+    -- hard_bounce, soft_bounce, spam_complaint, other のいずれか
+    kind TEXT NOT NULL,
+    email TEXT NOT NULL,
+    -- 配信記録から特定できた購読者
+    subscriber_id uuid NULL
+        REFERENCES subscriptions (id),
+    provider_message_id TEXT NULL,
+    description TEXT NULL,
+    occurred_at timestamptz NULL,
+    received_at timestamptz NOT NULL,
+    PRIMARY KEY (record_type, provider_event_id)
+);
+
+CREATE INDEX email_events_email_idx ON email_events (lower(email), kind);
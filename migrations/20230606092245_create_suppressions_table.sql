@@ -0,0 +1,14 @@
+-- Add migration script here
+-- 購読の状態に関係なく送信しないメールアドレスとドメイン
+-- value は小文字で保存し、宛先のメールアドレスも小文字に変換して照合する
+CREATE TABLE suppressions(
+    id uuid NOT NULL PRIMARY KEY,
+    -- address, domain のいずれか
+    kind TEXT NOT NULL,
+    value TEXT NOT NULL,
+    -- admin, legal, bounce, complaint のいずれか
+    reason TEXT NOT NULL,
+    note TEXT NULL,
+    created_at timestamptz NOT NULL,
+    UNIQUE (kind, value)
+);
@@ -0,0 +1,14 @@
+-- Add migration script here
+-- 号の全ての購読者に送信する添付ファイル
+CREATE TABLE newsletter_issue_attachments(
+    newsletter_issue_id uuid NOT NULL
+        REFERENCES newsletter_issues (newsletter_issue_id),
+    -- 投稿された順序
+    position INT NOT NULL,
+    name TEXT NOT NULL,
+    content_type TEXT NOT NULL,
+    -- Base64 でエンコードされた内容
+    content TEXT NOT NULL,
+    content_id TEXT NULL,
+    PRIMARY KEY (newsletter_issue_id, position)
+);
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use secrecy::{ExposeSecret, Secret};
 use serde::Deserialize;
 use serde_aux::field_attributes::deserialize_number_from_string;
@@ -6,7 +8,15 @@ use sqlx::{
     ConnectOptions,
 };
 
-use crate::domain::SubscriberEmail;
+use crate::{
+    domain::SubscriberEmail,
+    email_client::{
+        CircuitBreakerSettings, DevMailbox, EmailClient, EmailProvider, EmailTransport,
+        FailoverTransport, MailboxSettings, PostmarkTransport, RetryPolicy, SmtpSettings,
+        SmtpTransport,
+    },
+    rate_limiter::RateLimit,
+};
 
 #[derive(Deserialize, Clone)]
 pub struct Settings {
@@ -22,6 +32,16 @@ pub struct ApplicationSettings {
     pub host: String,
     pub base_url: String,
     pub hmac_secret: Secret<String>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub idempotency_retention_hours: i64,
+    /// APP_ENVIRONMENT から設定する
+    pub environment: Environment,
+}
+
+impl ApplicationSettings {
+    pub fn idempotency_retention(&self) -> chrono::Duration {
+        chrono::Duration::hours(self.idempotency_retention_hours)
+    }
 }
 
 #[derive(Deserialize, Clone)]
@@ -62,13 +82,105 @@ impl DatabaseSettings {
 
 #[derive(Deserialize, Clone)]
 pub struct EmailClientSettings {
+    pub provider: EmailProvider,
+    /// provider で一時的なエラーが発生した場合に、この順番で切り替える配信サービス
+    #[serde(default)]
+    pub fallback_providers: Vec<EmailProvider>,
+    /// fallback_providers を指定した場合に、配信サービスごとの停止を検知する
+    pub circuit_breaker: CircuitBreakerSettings,
     pub base_url: String,
     pub sender_email: String,
     pub authorization_token: Secret<String>,
     pub timeout_milliseconds: u64,
+    pub retry_policy: RetryPolicy,
+    pub rate_limit: RateLimit,
+    pub webhook: EmailWebhookSettings,
+    /// provider が smtp の場合に利用する
+    pub smtp: SmtpSettings,
+    /// provider が mailbox の場合に利用する
+    #[serde(default)]
+    pub mailbox: MailboxSettings,
+}
+
+#[derive(Deserialize, Clone)]
+pub struct EmailWebhookSettings {
+    pub username: String,
+    pub password: Secret<String>,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub soft_bounce_threshold: u32,
 }
 
 impl EmailClientSettings {
+    /// provider、fallback_providers の順番で重複を除いた配信サービス
+    pub fn providers(&self) -> Vec<EmailProvider> {
+        let mut providers = vec![self.provider];
+        for provider in &self.fallback_providers {
+            if !providers.contains(provider) {
+                providers.push(*provider);
+            }
+        }
+        providers
+    }
+
+    /// 配信サービスに mailbox を含む場合のみ開発用の受信箱を作成する
+    ///
+    /// メールが配信されないまま失われることを防ぐため、ローカル環境以外では起動しない。
+    pub fn dev_mailbox(&self, environment: Environment) -> Option<DevMailbox> {
+        if !self.providers().contains(&EmailProvider::Mailbox) {
+            return None;
+        }
+        if environment != Environment::Local {
+            panic!("The mailbox email provider is only available in the local environment.");
+        }
+
+        Some(
+            DevMailbox::new(self.mailbox.clone()).expect("Failed to open the development mailbox."),
+        )
+    }
+
+    /// 配信サービスに mailbox を含む場合は `dev_mailbox` で作成した受信箱を渡す
+    ///
+    /// fallback_providers を指定した場合は、一時的なエラーで次の配信サービスに切り替える。
+    pub fn client(self, dev_mailbox: Option<DevMailbox>) -> EmailClient {
+        let sender_email = self.sender().expect("Invalid sender email address.");
+
+        let mut transports: Vec<_> = self
+            .providers()
+            .into_iter()
+            .map(|provider| (provider, self.transport(provider, dev_mailbox.clone())))
+            .collect();
+
+        let transport: Arc<dyn EmailTransport> = if transports.len() == 1 {
+            transports.remove(0).1
+        } else {
+            Arc::new(FailoverTransport::new(transports, &self.circuit_breaker))
+        };
+
+        EmailClient::new(transport, sender_email, self.retry_policy, self.rate_limit)
+    }
+
+    fn transport(
+        &self,
+        provider: EmailProvider,
+        dev_mailbox: Option<DevMailbox>,
+    ) -> Arc<dyn EmailTransport> {
+        let timeout = self.timeout();
+
+        match provider {
+            EmailProvider::Postmark => Arc::new(PostmarkTransport::new(
+                self.base_url.clone(),
+                self.authorization_token.clone(),
+                timeout,
+            )),
+            EmailProvider::Smtp => Arc::new(
+                SmtpTransport::new(self.smtp.clone(), timeout).expect("Invalid SMTP settings."),
+            ),
+            EmailProvider::Mailbox => Arc::new(
+                dev_mailbox.expect("The mailbox email provider requires a development mailbox."),
+            ),
+        }
+    }
+
     pub fn sender(&self) -> Result<SubscriberEmail, String> {
         SubscriberEmail::parse(self.sender_email.clone())
     }
@@ -100,11 +212,14 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
                 .prefix_separator("_")
                 .separator("__"),
         )
+        .set_override("application.environment", environment.as_str())?
         .build()?;
 
     settings.try_deserialize::<Settings>()
 }
 
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(try_from = "String")]
 pub enum Environment {
     Local,
     Production,
@@ -0,0 +1,83 @@
+/// メール配信サービスから通知されたバウンスや苦情の種類
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FeedbackKind {
+    /// 宛先が存在しないなど、再送しても届かないバウンス
+    HardBounce,
+    /// メールボックスの容量超過など、一時的なバウンス
+    SoftBounce,
+    /// 受信者が迷惑メールとして報告した
+    SpamComplaint,
+    /// 自動応答など、購読の状態に影響しない通知
+    Other,
+}
+
+impl FeedbackKind {
+    /// Postmark の `RecordType` と `Type` から種類を判定する
+    pub fn classify(record_type: &str, bounce_type: Option<&str>) -> Self {
+        match (record_type, bounce_type) {
+            ("SpamComplaint", _) | ("Bounce", Some("SpamComplaint")) => Self::SpamComplaint,
+            ("Bounce", Some("HardBounce" | "BadEmailAddress" | "ManuallyDeactivated")) => {
+                Self::HardBounce
+            }
+            ("Bounce", Some("SoftBounce" | "Transient" | "DnsError")) => Self::SoftBounce,
+            _ => Self::Other,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::HardBounce => "hard_bounce",
+            Self::SoftBounce => "soft_bounce",
+            Self::SpamComplaint => "spam_complaint",
+            Self::Other => "other",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::FeedbackKind;
+
+    #[test]
+    fn bounce_types_are_classified() {
+        let test_cases = vec![
+            ("Bounce", Some("HardBounce"), FeedbackKind::HardBounce),
+            ("Bounce", Some("BadEmailAddress"), FeedbackKind::HardBounce),
+            ("Bounce", Some("SoftBounce"), FeedbackKind::SoftBounce),
+            ("Bounce", Some("Transient"), FeedbackKind::SoftBounce),
+            ("Bounce", Some("SpamComplaint"), FeedbackKind::SpamComplaint),
+            ("Bounce", Some("AutoResponder"), FeedbackKind::Other),
+            ("Bounce", None, FeedbackKind::Other),
+        ];
+
+        for (record_type, bounce_type, expected) in test_cases {
+            assert_eq!(
+                FeedbackKind::classify(record_type, bounce_type),
+                expected,
+                "{:?} was not classified as {:?}",
+                bounce_type,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn spam_complaints_are_classified_regardless_of_their_type() {
+        assert_eq!(
+            FeedbackKind::classify("SpamComplaint", Some("SpamComplaint")),
+            FeedbackKind::SpamComplaint
+        );
+        assert_eq!(
+            FeedbackKind::classify("SpamComplaint", None),
+            FeedbackKind::SpamComplaint
+        );
+    }
+
+    #[test]
+    fn other_record_types_are_ignored() {
+        assert_eq!(
+            FeedbackKind::classify("Delivery", None),
+            FeedbackKind::Other
+        );
+    }
+}
@@ -0,0 +1,63 @@
+use uuid::Uuid;
+
+/// 公開アーカイブで号を表示するためのURLの一部
+///
+/// タイトルが重複してもURLが一意になるように、号のIDの先頭8文字を末尾に付与する。
+#[derive(Debug)]
+pub struct IssueSlug(String);
+
+impl IssueSlug {
+    pub fn generate(title: &str, newsletter_issue_id: Uuid) -> IssueSlug {
+        let mut slug = String::with_capacity(title.len());
+        for c in title.trim().chars().flat_map(char::to_lowercase) {
+            if c.is_alphanumeric() {
+                slug.push(c);
+            } else if !slug.is_empty() && !slug.ends_with('-') {
+                slug.push('-');
+            }
+        }
+
+        let id = newsletter_issue_id.simple().to_string();
+        if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+        slug.push_str(&id[..8]);
+
+        Self(slug)
+    }
+}
+
+impl AsRef<str> for IssueSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use crate::domain::IssueSlug;
+
+    fn issue_id() -> Uuid {
+        Uuid::parse_str("5d2a3a8e-1f4b-4a51-9c1e-6b1f0f7c2d10").unwrap()
+    }
+
+    #[test]
+    fn title_is_converted_to_lowercase_words_joined_by_hyphens() {
+        let slug = IssueSlug::generate("  Hello, World!  Issue #3 ", issue_id());
+        assert_eq!(slug.as_ref(), "hello-world-issue-3-5d2a3a8e");
+    }
+
+    #[test]
+    fn non_ascii_letters_are_kept() {
+        let slug = IssueSlug::generate("今週のニュース", issue_id());
+        assert_eq!(slug.as_ref(), "今週のニュース-5d2a3a8e");
+    }
+
+    #[test]
+    fn titles_without_letters_use_only_the_issue_id() {
+        let slug = IssueSlug::generate("!!!", issue_id());
+        assert_eq!(slug.as_ref(), "5d2a3a8e");
+    }
+}
@@ -0,0 +1,163 @@
+/// HTML の本文に含まれる `<a>` タグの `href` を書き換える
+///
+/// `rewrite` には `&amp;` などを元に戻したURLを渡し、`None` を返した場合はそのまま残す。
+/// 書き換えたURLは属性値としてエスケープして埋め込む。
+pub fn rewrite_links(html: &str, mut rewrite: impl FnMut(&str) -> Option<String>) -> String {
+    let mut rewritten = String::with_capacity(html.len());
+    let mut rest = html;
+
+    while let Some(start) = find_anchor_tag(rest) {
+        let Some(tag_length) = rest[start..].find('>') else {
+            break;
+        };
+        let tag_end = start + tag_length;
+        rewritten.push_str(&rest[..start]);
+
+        let tag = &rest[start..tag_end];
+        match find_href(tag) {
+            Some((value_start, value_end)) => {
+                let url = unescape(&tag[value_start..value_end]);
+                match rewrite(&url) {
+                    Some(url) => {
+                        // 引用符のない属性値は書き換え後の値を引用符で囲む
+                        let is_quoted = tag[..value_start].ends_with(['"', '\'']);
+                        let url = htmlescape::encode_minimal(&url);
+                        rewritten.push_str(&tag[..value_start]);
+                        if is_quoted {
+                            rewritten.push_str(&url);
+                        } else {
+                            rewritten.push_str(&format!("\"{}\"", url));
+                        }
+                        rewritten.push_str(&tag[value_end..]);
+                    }
+                    None => rewritten.push_str(tag),
+                }
+            }
+            None => rewritten.push_str(tag),
+        }
+
+        rest = &rest[tag_end..];
+    }
+    rewritten.push_str(rest);
+
+    rewritten
+}
+
+/// `<a ...>` の開始位置を探す (`<abbr>` などは対象としない)
+fn find_anchor_tag(html: &str) -> Option<usize> {
+    let bytes = html.as_bytes();
+
+    (0..bytes.len().saturating_sub(2)).find(|&i| {
+        bytes[i] == b'<'
+            && bytes[i + 1].eq_ignore_ascii_case(&b'a')
+            && bytes[i + 2].is_ascii_whitespace()
+    })
+}
+
+/// タグの中から `href` 属性の値の範囲を探す
+fn find_href(tag: &str) -> Option<(usize, usize)> {
+    let lowercase = tag.to_ascii_lowercase();
+    let mut offset = 0;
+
+    while let Some(position) = lowercase[offset..].find("href") {
+        let name_start = offset + position;
+        let name_end = name_start + "href".len();
+        offset = name_end;
+
+        // `data-href` のような別の属性は読み飛ばす
+        if !tag[..name_start].ends_with(|c: char| c.is_ascii_whitespace()) {
+            continue;
+        }
+
+        let after_name = tag[name_end..].trim_start();
+        let Some(after_equals) = after_name.strip_prefix('=') else {
+            continue;
+        };
+        let value = after_equals.trim_start();
+        let value_offset = tag.len() - value.len();
+
+        return match value.chars().next() {
+            Some(quote @ ('"' | '\'')) => {
+                let length = value[1..].find(quote)?;
+                Some((value_offset + 1, value_offset + 1 + length))
+            }
+            Some(_) => {
+                let length = value
+                    .find(|c: char| c.is_ascii_whitespace())
+                    .unwrap_or(value.len());
+                Some((value_offset, value_offset + length))
+            }
+            None => None,
+        };
+    }
+
+    None
+}
+
+/// 属性値でエスケープされることの多い文字参照を元に戻す
+fn unescape(value: &str) -> String {
+    value
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::rewrite_links;
+
+    fn redirect(url: &str) -> Option<String> {
+        url.starts_with("https://")
+            .then(|| format!("https://r.example.com/?to={}", url))
+    }
+
+    #[test]
+    fn hrefs_of_anchor_tags_are_rewritten() {
+        let html = rewrite_links(
+            r#"<p><a href="https://example.com/a">A</a> and <A class="x" HREF='https://example.com/b'>B</A></p>"#,
+            redirect,
+        );
+
+        assert_eq!(
+            html,
+            r#"<p><a href="https://r.example.com/?to=https://example.com/a">A</a> and <A class="x" HREF='https://r.example.com/?to=https://example.com/b'>B</A></p>"#
+        );
+    }
+
+    #[test]
+    fn urls_are_unescaped_before_rewriting_and_escaped_after() {
+        let mut received = Vec::new();
+        let html = rewrite_links(
+            r#"<a href="https://example.com/?a=1&amp;b=2">A</a>"#,
+            |url| {
+                received.push(url.to_owned());
+                redirect(url)
+            },
+        );
+
+        assert_eq!(received, vec!["https://example.com/?a=1&b=2"]);
+        assert_eq!(
+            html,
+            r#"<a href="https://r.example.com/?to=https://example.com/?a=1&amp;b=2">A</a>"#
+        );
+    }
+
+    #[test]
+    fn links_that_are_not_rewritten_are_left_as_is() {
+        let original = r#"<a href="mailto:me@example.com">Mail</a><a name="top">Top</a><abbr href="x">X</abbr><a data-href="https://example.com">D</a>"#;
+
+        assert_eq!(rewrite_links(original, redirect), original);
+    }
+
+    #[test]
+    fn unquoted_hrefs_are_rewritten() {
+        let html = rewrite_links("<a href=https://example.com/a>A</a>", redirect);
+
+        assert_eq!(
+            html,
+            r#"<a href="https://r.example.com/?to=https://example.com/a">A</a>"#
+        );
+    }
+}
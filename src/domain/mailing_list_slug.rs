@@ -0,0 +1,65 @@
+/// リクエストでメーリングリストを指定するための識別子
+///
+/// フォームやURLにそのまま記述できるように、英小文字・数字・`-` のみ使用できる。
+#[derive(Debug)]
+pub struct MailingListSlug(String);
+
+impl MailingListSlug {
+    /// リストが指定されなかった場合に使用するリスト
+    pub const DEFAULT: &'static str = "default";
+
+    pub fn parse(s: String) -> Result<MailingListSlug, String> {
+        let is_valid = !s.is_empty()
+            && s.len() <= 64
+            && !s.starts_with('-')
+            && !s.ends_with('-')
+            && s.chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+
+        if is_valid {
+            Ok(Self(s))
+        } else {
+            Err(format!("{} is not a valid mailing list identifier", s))
+        }
+    }
+}
+
+impl Default for MailingListSlug {
+    fn default() -> Self {
+        Self(Self::DEFAULT.to_owned())
+    }
+}
+
+impl AsRef<str> for MailingListSlug {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use crate::domain::MailingListSlug;
+
+    #[test]
+    fn lowercase_letters_digits_and_hyphens_are_accepted() {
+        assert_ok!(MailingListSlug::parse("rust-weekly-2023".into()));
+        assert_ok!(MailingListSlug::parse(MailingListSlug::DEFAULT.into()));
+    }
+
+    #[test]
+    fn invalid_identifiers_are_rejected() {
+        for slug in [
+            "",
+            "Rust",
+            "rust weekly",
+            "-rust",
+            "rust-",
+            "ラスト",
+            &"a".repeat(65),
+        ] {
+            assert_err!(MailingListSlug::parse(slug.into()), "{}", slug);
+        }
+    }
+}
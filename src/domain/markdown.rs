@@ -0,0 +1,208 @@
+use pulldown_cmark::{html, CodeBlockKind, Event, Parser, Tag};
+
+use super::newsletter_template::convert_preserving_placeholders;
+
+/// Markdown を HTML に変換する
+///
+/// 投稿者が記述した HTML はそのままメールに含まれるため、
+/// スクリプトなどの危険な要素は取り除く。
+pub fn markdown_to_html(markdown: &str) -> String {
+    convert_preserving_placeholders(markdown, |markdown| {
+        let mut unsafe_html = String::new();
+        html::push_html(&mut unsafe_html, Parser::new(markdown));
+
+        ammonia::clean(&unsafe_html)
+    })
+}
+
+/// Markdown をテキストに変換する
+///
+/// リンクは本文中に `[1]` のような番号を付けて、末尾にURLの一覧として記載する。
+pub fn markdown_to_text(markdown: &str) -> String {
+    convert_preserving_placeholders(markdown, |markdown| {
+        let mut writer = TextWriter::default();
+        for event in Parser::new(markdown) {
+            writer.write(event);
+        }
+
+        writer.finish()
+    })
+}
+
+#[derive(Default)]
+struct TextWriter {
+    text: String,
+    links: Vec<String>,
+    /// 記述中のリンクのURLと、リンクのテキストの開始位置
+    current_link: Option<(String, usize)>,
+    /// 入れ子になったリストごとの次の番号 (番号なしのリストは None)
+    lists: Vec<Option<u64>>,
+}
+
+impl TextWriter {
+    fn write(&mut self, event: Event) {
+        match event {
+            Event::Start(Tag::Item) => {
+                let indent = "  ".repeat(self.lists.len().saturating_sub(1));
+                let marker = match self.lists.last_mut() {
+                    Some(Some(number)) => {
+                        *number += 1;
+                        format!("{}. ", *number - 1)
+                    }
+                    _ => "- ".to_owned(),
+                };
+                self.start_line();
+                self.text.push_str(&indent);
+                self.text.push_str(&marker);
+            }
+            Event::Start(Tag::List(first_number)) => {
+                if self.lists.is_empty() {
+                    self.end_block();
+                }
+                self.lists.push(first_number);
+            }
+            Event::End(Tag::List(_)) => {
+                self.lists.pop();
+                if self.lists.is_empty() {
+                    self.end_block();
+                }
+            }
+            Event::Start(Tag::Link(_, destination, _) | Tag::Image(_, destination, _)) => {
+                self.current_link = Some((destination.to_string(), self.text.len()));
+            }
+            Event::End(Tag::Link(..) | Tag::Image(..)) => {
+                if let Some((destination, start)) = self.current_link.take() {
+                    // URLがそのままリンクのテキストになっている場合は番号を付けない
+                    if self.text[start..] != destination {
+                        let number = self.link_number(destination);
+                        self.text.push_str(&format!("[{}]", number));
+                    }
+                }
+            }
+            Event::Start(Tag::CodeBlock(CodeBlockKind::Fenced(_) | CodeBlockKind::Indented)) => {
+                self.end_block();
+            }
+            Event::End(Tag::Paragraph | Tag::Heading(..) | Tag::CodeBlock(_) | Tag::BlockQuote) => {
+                self.end_block();
+            }
+            Event::Text(text) | Event::Code(text) => self.text.push_str(&text),
+            Event::SoftBreak | Event::HardBreak => self.text.push('\n'),
+            Event::Rule => {
+                self.end_block();
+                self.text.push_str("---");
+                self.end_block();
+            }
+            Event::TaskListMarker(checked) => {
+                self.text.push_str(if checked { "[x] " } else { "[ ] " });
+            }
+            _ => {}
+        }
+    }
+
+    /// 同じURLには同じ番号を割り当てる
+    fn link_number(&mut self, destination: String) -> usize {
+        match self.links.iter().position(|link| *link == destination) {
+            Some(index) => index + 1,
+            None => {
+                self.links.push(destination);
+                self.links.len()
+            }
+        }
+    }
+
+    fn start_line(&mut self) {
+        if !self.text.is_empty() && !self.text.ends_with('\n') {
+            self.text.push('\n');
+        }
+    }
+
+    /// 段落などのブロックの間は空行を入れる (リストの中では改行のみ)
+    fn end_block(&mut self) {
+        if self.text.is_empty() {
+            return;
+        }
+        self.start_line();
+        if self.lists.is_empty() && !self.text.ends_with("\n\n") {
+            self.text.push('\n');
+        }
+    }
+
+    fn finish(mut self) -> String {
+        let mut text = self.text.trim_end().to_owned();
+
+        if !self.links.is_empty() {
+            text.push_str("\n\n");
+            for (i, link) in self.links.drain(..).enumerate() {
+                text.push_str(&format!("[{}] {}\n", i + 1, link));
+            }
+        }
+
+        text.trim_end().to_owned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::domain::{markdown_to_html, markdown_to_text};
+
+    #[test]
+    fn markdown_is_rendered_to_html() {
+        let html = markdown_to_html("# Title\n\nHello **world**");
+
+        assert_eq!(
+            html,
+            "<h1>Title</h1>\n<p>Hello <strong>world</strong></p>\n"
+        );
+    }
+
+    #[test]
+    fn dangerous_html_is_removed() {
+        let html = markdown_to_html(
+            "Hello <script>alert('xss')</script>\n\n[link](javascript:alert(1)) <img src=x onerror=alert(1)>",
+        );
+
+        assert!(!html.contains("<script"));
+        assert!(!html.contains("javascript:"));
+        assert!(!html.contains("onerror"));
+    }
+
+    #[test]
+    fn links_are_kept_as_footnotes_in_text() {
+        let text = markdown_to_text(
+            "Read the [announcement](https://example.com/a) and the [docs](https://example.com/d).\n\n\
+             See the [announcement](https://example.com/a) again or <https://example.com/raw>.",
+        );
+
+        assert_eq!(
+            text,
+            "Read the announcement[1] and the docs[2].\n\n\
+             See the announcement[1] again or https://example.com/raw.\n\n\
+             [1] https://example.com/a\n\
+             [2] https://example.com/d"
+        );
+    }
+
+    #[test]
+    fn blocks_and_lists_are_rendered_as_text() {
+        let text =
+            markdown_to_text("# Title\n\nIntro\n\n- one\n- two\n\n1. first\n2. second\n\nOutro");
+
+        assert_eq!(
+            text,
+            "Title\n\nIntro\n\n- one\n- two\n\n1. first\n2. second\n\nOutro"
+        );
+    }
+
+    #[test]
+    fn placeholders_are_kept_in_links() {
+        let html = markdown_to_html("Hi {{ name }}, [unsubscribe]({{ unsubscribe_url }})");
+        let text = markdown_to_text("Hi {{ name }}, [unsubscribe]({{ unsubscribe_url }})");
+
+        assert!(html.contains("Hi {{ name }}"));
+        assert!(html.contains(r#"href="{{ unsubscribe_url }}""#));
+        assert_eq!(
+            text,
+            "Hi {{ name }}, unsubscribe[1]\n\n[1] {{ unsubscribe_url }}"
+        );
+    }
+}
@@ -1,7 +1,26 @@
+mod email_feedback;
+mod issue_slug;
+mod link_rewriter;
+mod mailing_list_slug;
+mod markdown;
 mod new_subscriber;
+mod newsletter_template;
+mod segment;
 mod subscriber_email;
 mod subscriber_name;
+mod suppression_target;
 
+pub use email_feedback::FeedbackKind;
+pub use issue_slug::IssueSlug;
+pub use link_rewriter::rewrite_links;
+pub use mailing_list_slug::MailingListSlug;
+pub use markdown::{markdown_to_html, markdown_to_text};
 pub use new_subscriber::NewSubscriber;
+pub use newsletter_template::{
+    convert_preserving_placeholders, render_html, render_text, validate_template,
+    TemplateVariables, TEMPLATE_VARIABLES,
+};
+pub use segment::{parse_tag, Segment};
 pub use subscriber_email::SubscriberEmail;
 pub use subscriber_name::SubscriberName;
+pub use suppression_target::SuppressionTarget;
@@ -1,7 +1,10 @@
+use super::MailingListSlug;
 use super::SubscriberEmail;
 use super::SubscriberName;
 
 pub struct NewSubscriber {
     pub email: SubscriberEmail,
     pub name: SubscriberName,
+    /// 購読するメーリングリスト
+    pub mailing_list: MailingListSlug,
 }
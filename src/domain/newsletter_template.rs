@@ -0,0 +1,223 @@
+/// ニュースレターの本文で使用できるプレースホルダーの変数名
+pub const TEMPLATE_VARIABLES: [&str; 3] = ["name", "email", "unsubscribe_url"];
+
+/// 購読者ごとにプレースホルダーへ埋め込む値
+pub struct TemplateVariables<'a> {
+    pub name: &'a str,
+    pub email: &'a str,
+    /// `routes::unsubscribe_url` で作成した `/subscriptions/unsubscribe` への署名付きのURL
+    pub unsubscribe_url: &'a str,
+}
+
+impl TemplateVariables<'_> {
+    fn get(&self, variable: &str) -> Option<&str> {
+        match variable {
+            "name" => Some(self.name),
+            "email" => Some(self.email),
+            "unsubscribe_url" => Some(self.unsubscribe_url),
+            _ => None,
+        }
+    }
+}
+
+enum Segment<'a> {
+    Text(&'a str),
+    /// `{{ name }}` のようなプレースホルダー
+    Placeholder {
+        raw: &'a str,
+        variable: &'a str,
+    },
+}
+
+/// テンプレートをプレースホルダーとそれ以外の文字列に分割する
+///
+/// 閉じられていない `{{` はそのまま文字列として扱う。
+fn segments(template: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find("{{") {
+        let Some(length) = rest[start..].find("}}") else {
+            break;
+        };
+        let end = start + length + 2;
+
+        segments.push(Segment::Text(&rest[..start]));
+        segments.push(Segment::Placeholder {
+            raw: &rest[start..end],
+            variable: rest[start + 2..end - 2].trim(),
+        });
+        rest = &rest[end..];
+    }
+    segments.push(Segment::Text(rest));
+
+    segments
+}
+
+/// テンプレートで未定義の変数が使用されていないか検証する
+pub fn validate_template(template: &str) -> Result<(), String> {
+    let unknown_variables: Vec<_> = segments(template)
+        .into_iter()
+        .filter_map(|segment| match segment {
+            Segment::Placeholder { raw, variable } if !TEMPLATE_VARIABLES.contains(&variable) => {
+                Some(raw)
+            }
+            _ => None,
+        })
+        .collect();
+
+    if unknown_variables.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Unknown template variables: {}. Available variables are {}.",
+            unknown_variables.join(", "),
+            TEMPLATE_VARIABLES.join(", ")
+        ))
+    }
+}
+
+/// プレースホルダーを保護した状態で本文を変換する
+///
+/// Markdown の変換などでプレースホルダーの `{` がエスケープされないように、
+/// 英数字のみの文字列に置き換えてから変換し、変換後に元に戻す。
+pub fn convert_preserving_placeholders(
+    template: &str,
+    convert: impl FnOnce(&str) -> String,
+) -> String {
+    let mut protected = String::with_capacity(template.len());
+    let mut placeholders = Vec::new();
+
+    for segment in segments(template) {
+        match segment {
+            Segment::Text(text) => protected.push_str(text),
+            Segment::Placeholder { raw, .. } => {
+                protected.push_str(&placeholder_token(placeholders.len()));
+                placeholders.push(raw);
+            }
+        }
+    }
+
+    let mut converted = convert(&protected);
+    for (i, raw) in placeholders.into_iter().enumerate() {
+        converted = converted.replace(&placeholder_token(i), raw);
+    }
+
+    converted
+}
+
+fn placeholder_token(index: usize) -> String {
+    format!("zzplaceholder{}zz", index)
+}
+
+/// HTML の本文に値を埋め込む (値はエスケープする)
+pub fn render_html(template: &str, variables: &TemplateVariables) -> String {
+    render(template, variables, |value| {
+        htmlescape::encode_minimal(value)
+    })
+}
+
+/// テキストの本文に値を埋め込む
+pub fn render_text(template: &str, variables: &TemplateVariables) -> String {
+    render(template, variables, |value| value.to_owned())
+}
+
+/// 未定義の変数はそのまま残す
+fn render(
+    template: &str,
+    variables: &TemplateVariables,
+    escape: impl Fn(&str) -> String,
+) -> String {
+    let mut rendered = String::with_capacity(template.len());
+
+    for segment in segments(template) {
+        match segment {
+            Segment::Text(text) => rendered.push_str(text),
+            Segment::Placeholder { raw, variable } => match variables.get(variable) {
+                Some(value) => rendered.push_str(&escape(value)),
+                None => rendered.push_str(raw),
+            },
+        }
+    }
+
+    rendered
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_err, assert_ok};
+
+    use crate::domain::{
+        convert_preserving_placeholders, render_html, render_text, validate_template,
+        TemplateVariables,
+    };
+
+    fn variables() -> TemplateVariables<'static> {
+        TemplateVariables {
+            name: "Tom & Jerry",
+            email: "tom@example.com",
+            unsubscribe_url: "https://example.com/unsubscribe?a=1&b=2",
+        }
+    }
+
+    #[test]
+    fn known_variables_are_accepted() {
+        assert_ok!(validate_template(
+            "Hi {{ name }} ({{email}}), {{  unsubscribe_url  }}"
+        ));
+    }
+
+    #[test]
+    fn unknown_variables_are_rejected() {
+        let error = validate_template("Hi {{ name }} {{ age }} {{}}").unwrap_err();
+
+        assert!(error.contains("{{ age }}"));
+        assert!(error.contains("{{}}"));
+    }
+
+    #[test]
+    fn content_without_placeholders_is_accepted() {
+        assert_ok!(validate_template("Hello { world } {{ unclosed"));
+        assert_err!(validate_template("{{ unknown }} {{ unclosed"));
+    }
+
+    #[test]
+    fn text_is_rendered_without_escaping() {
+        let rendered = render_text("Hi {{ name }}, {{unsubscribe_url}}", &variables());
+
+        assert_eq!(
+            rendered,
+            "Hi Tom & Jerry, https://example.com/unsubscribe?a=1&b=2"
+        );
+    }
+
+    #[test]
+    fn values_are_escaped_in_html() {
+        let rendered = render_html(
+            r#"<p>Hi {{ name }}</p><a href="{{ unsubscribe_url }}">Unsubscribe</a>"#,
+            &variables(),
+        );
+
+        assert_eq!(
+            rendered,
+            r#"<p>Hi Tom &amp; Jerry</p><a href="https://example.com/unsubscribe?a=1&amp;b=2">Unsubscribe</a>"#
+        );
+    }
+
+    #[test]
+    fn unknown_variables_are_left_as_is() {
+        let rendered = render_text("{{ email }} {{ age }}", &variables());
+
+        assert_eq!(rendered, "tom@example.com {{ age }}");
+    }
+
+    #[test]
+    fn placeholders_are_not_affected_by_the_conversion() {
+        let converted = convert_preserving_placeholders("Hi {{ name }} {{name}}", |protected| {
+            assert!(!protected.contains('{'));
+            format!("<p>{}</p>", protected)
+        });
+
+        assert_eq!(converted, "<p>Hi {{ name }} {{name}}</p>");
+    }
+}
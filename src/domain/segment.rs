@@ -0,0 +1,331 @@
+/// 配信対象の購読者をタグで絞り込む条件
+///
+/// 次のような式で記述する。`tags include` は省略できる。
+///
+/// ```text
+/// tags include "beta" and not "churned"
+/// ("beta" or "early-access") and not "churned"
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Segment {
+    /// 指定したタグが付与されている
+    Tag(String),
+    Not(Box<Segment>),
+    And(Box<Segment>, Box<Segment>),
+    Or(Box<Segment>, Box<Segment>),
+}
+
+const MAX_EXPRESSION_LENGTH: usize = 1024;
+const MAX_NESTING_DEPTH: usize = 32;
+
+impl Segment {
+    pub fn parse(expression: &str) -> Result<Segment, String> {
+        if expression.len() > MAX_EXPRESSION_LENGTH {
+            return Err(format!(
+                "The segment expression must be at most {} characters long.",
+                MAX_EXPRESSION_LENGTH
+            ));
+        }
+
+        let tokens = tokenize(expression)?;
+        let mut parser = Parser {
+            tokens: &tokens,
+            position: 0,
+            depth: 0,
+        };
+        let segment = parser.or_expression()?;
+
+        match parser.peek() {
+            None => Ok(segment),
+            Some(token) => Err(format!("Unexpected {} in the segment expression.", token)),
+        }
+    }
+}
+
+/// 正規化した式を出力する (出力した式を再度解釈すると同じ条件になる)
+impl std::fmt::Display for Segment {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Segment::Tag(tag) => write!(
+                f,
+                "tags include \"{}\"",
+                tag.replace('\\', "\\\\").replace('"', "\\\"")
+            ),
+            Segment::Not(segment) => write!(f, "not ({})", segment),
+            Segment::And(left, right) => write!(f, "({}) and ({})", left, right),
+            Segment::Or(left, right) => write!(f, "({}) or ({})", left, right),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Token {
+    Keyword(Keyword),
+    String(String),
+    LeftParen,
+    RightParen,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Keyword {
+    Tags,
+    Include,
+    And,
+    Or,
+    Not,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Keyword(keyword) => write!(f, "`{}`", format!("{:?}", keyword).to_lowercase()),
+            Token::String(s) => write!(f, "\"{}\"", s),
+            Token::LeftParen => write!(f, "`(`"),
+            Token::RightParen => write!(f, "`)`"),
+        }
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::LeftParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::RightParen);
+            }
+            '"' => {
+                chars.next();
+                let mut tag = String::new();
+                loop {
+                    match chars.next() {
+                        Some('"') => break,
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => tag.push(escaped),
+                            None => {
+                                return Err("Unterminated tag in the segment expression.".into())
+                            }
+                        },
+                        Some(c) => tag.push(c),
+                        None => return Err("Unterminated tag in the segment expression.".into()),
+                    }
+                }
+                tokens.push(Token::String(parse_tag(tag)?));
+            }
+            c if c.is_alphabetic() => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek() {
+                    if !c.is_alphanumeric() && c != '_' {
+                        break;
+                    }
+                    word.push(c);
+                    chars.next();
+                }
+                let keyword = match word.to_lowercase().as_str() {
+                    "tags" => Keyword::Tags,
+                    "include" | "includes" => Keyword::Include,
+                    "and" => Keyword::And,
+                    "or" => Keyword::Or,
+                    "not" => Keyword::Not,
+                    _ => {
+                        return Err(format!(
+                            "Unknown keyword `{}` in the segment expression. Tags must be quoted.",
+                            word
+                        ))
+                    }
+                };
+                tokens.push(Token::Keyword(keyword));
+            }
+            c => {
+                return Err(format!(
+                    "Unexpected character `{}` in the segment expression.",
+                    c
+                ))
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// 購読者に付与するタグと同じ規則で検証する
+pub fn parse_tag(tag: String) -> Result<String, String> {
+    let tag = tag.trim();
+
+    if tag.is_empty() {
+        Err("Tags cannot be empty.".into())
+    } else if tag.chars().count() > 64 {
+        Err(format!("The tag `{}` is longer than 64 characters.", tag))
+    } else {
+        Ok(tag.to_owned())
+    }
+}
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    position: usize,
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next_if_keyword(&mut self, keyword: Keyword) -> bool {
+        if self.peek() == Some(&Token::Keyword(keyword)) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn or_expression(&mut self) -> Result<Segment, String> {
+        let mut segment = self.and_expression()?;
+        while self.next_if_keyword(Keyword::Or) {
+            let right = self.and_expression()?;
+            segment = Segment::Or(Box::new(segment), Box::new(right));
+        }
+
+        Ok(segment)
+    }
+
+    fn and_expression(&mut self) -> Result<Segment, String> {
+        let mut segment = self.unary_expression()?;
+        while self.next_if_keyword(Keyword::And) {
+            let right = self.unary_expression()?;
+            segment = Segment::And(Box::new(segment), Box::new(right));
+        }
+
+        Ok(segment)
+    }
+
+    fn unary_expression(&mut self) -> Result<Segment, String> {
+        self.depth += 1;
+        if self.depth > MAX_NESTING_DEPTH {
+            return Err("The segment expression is nested too deeply.".into());
+        }
+
+        let segment = if self.next_if_keyword(Keyword::Not) {
+            Segment::Not(Box::new(self.unary_expression()?))
+        } else {
+            self.primary_expression()?
+        };
+
+        self.depth -= 1;
+        Ok(segment)
+    }
+
+    fn primary_expression(&mut self) -> Result<Segment, String> {
+        if self.next_if_keyword(Keyword::Tags) && !self.next_if_keyword(Keyword::Include) {
+            return Err("Expected `include` after `tags` in the segment expression.".into());
+        }
+
+        match self.tokens.get(self.position) {
+            Some(Token::String(tag)) => {
+                self.position += 1;
+                Ok(Segment::Tag(tag.clone()))
+            }
+            Some(Token::LeftParen) => {
+                self.position += 1;
+                let segment = self.or_expression()?;
+                if self.peek() != Some(&Token::RightParen) {
+                    return Err("Expected `)` in the segment expression.".into());
+                }
+                self.position += 1;
+                Ok(segment)
+            }
+            Some(token) => Err(format!(
+                "Expected a quoted tag but found {} in the segment expression.",
+                token
+            )),
+            None => Err("The segment expression ended unexpectedly.".into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_err;
+
+    use crate::domain::Segment;
+
+    fn tag(tag: &str) -> Box<Segment> {
+        Box::new(Segment::Tag(tag.into()))
+    }
+
+    #[test]
+    fn tags_include_and_not_are_parsed() {
+        let segment = Segment::parse(r#"tags include "beta" and not "churned""#).unwrap();
+
+        assert_eq!(
+            segment,
+            Segment::And(tag("beta"), Box::new(Segment::Not(tag("churned"))))
+        );
+    }
+
+    #[test]
+    fn and_binds_tighter_than_or() {
+        let segment = Segment::parse(r#""a" or "b" and "c""#).unwrap();
+
+        assert_eq!(
+            segment,
+            Segment::Or(tag("a"), Box::new(Segment::And(tag("b"), tag("c"))))
+        );
+    }
+
+    #[test]
+    fn parentheses_group_expressions() {
+        let segment = Segment::parse(r#"("a" OR tags include "b") and not ("c")"#).unwrap();
+
+        assert_eq!(
+            segment,
+            Segment::And(
+                Box::new(Segment::Or(tag("a"), tag("b"))),
+                Box::new(Segment::Not(tag("c")))
+            )
+        );
+    }
+
+    #[test]
+    fn displayed_expressions_are_parsed_to_the_same_segment() {
+        let segment = Segment::parse(r#"("a \"quoted\"" or "b") and not "c""#).unwrap();
+
+        assert_eq!(Segment::parse(&segment.to_string()).unwrap(), segment);
+    }
+
+    #[test]
+    fn invalid_expressions_are_rejected() {
+        let expressions = [
+            "",
+            "beta",
+            r#""beta" and"#,
+            r#""beta" "gamma""#,
+            r#"("beta""#,
+            r#""unterminated"#,
+            r#"tags "beta""#,
+            r#""""#,
+            r#""beta"; DROP TABLE subscriptions"#,
+        ];
+
+        for expression in expressions {
+            assert_err!(Segment::parse(expression), "{}", expression);
+        }
+    }
+
+    #[test]
+    fn deeply_nested_expressions_are_rejected() {
+        let expression = format!("{}\"a\"", "not ".repeat(100));
+        assert_err!(Segment::parse(&expression));
+    }
+}
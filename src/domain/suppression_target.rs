@@ -0,0 +1,102 @@
+use validator::validate_email;
+
+/// ドメイン名の最大文字数
+const MAX_DOMAIN_LENGTH: usize = 253;
+
+/// 送信を停止するメールアドレスまたはドメイン
+///
+/// 大文字小文字を区別せずに照合できるように、小文字に変換して保持する。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SuppressionTarget {
+    Address(String),
+    Domain(String),
+}
+
+impl SuppressionTarget {
+    /// `@` を含む場合はメールアドレス、含まない場合 (または `@example.com` の形式) はドメインとして解釈する
+    pub fn parse(s: &str) -> Result<Self, String> {
+        let value = s.trim().to_lowercase();
+
+        if let Some(domain) = value.strip_prefix('@') {
+            return Self::parse_domain(domain).map(Self::Domain);
+        }
+        if value.contains('@') {
+            return if validate_email(&value) {
+                Ok(Self::Address(value))
+            } else {
+                Err(format!("{} is not a valid email address", s))
+            };
+        }
+        Self::parse_domain(&value).map(Self::Domain)
+    }
+
+    fn parse_domain(domain: &str) -> Result<String, String> {
+        let is_valid_label = |label: &str| {
+            !label.is_empty()
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        };
+
+        if domain.len() <= MAX_DOMAIN_LENGTH
+            && domain.contains('.')
+            && domain.split('.').all(is_valid_label)
+        {
+            Ok(domain.to_owned())
+        } else {
+            Err(format!("{} is not a valid domain", domain))
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Self::Address(_) => "address",
+            Self::Domain(_) => "domain",
+        }
+    }
+
+    pub fn value(&self) -> &str {
+        match self {
+            Self::Address(value) | Self::Domain(value) => value,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_err;
+
+    use crate::domain::SuppressionTarget;
+
+    #[test]
+    fn addresses_are_normalized_to_lowercase() {
+        assert_eq!(
+            SuppressionTarget::parse(" Someone@Example.COM "),
+            Ok(SuppressionTarget::Address("someone@example.com".into()))
+        );
+    }
+
+    #[test]
+    fn domains_are_accepted_with_or_without_a_leading_at_sign() {
+        for value in ["example.com", "@Example.com", "mail.example.co.jp"] {
+            let target = SuppressionTarget::parse(value).unwrap();
+
+            assert_eq!(target.kind(), "domain");
+            assert_eq!(target.value(), value.trim_start_matches('@').to_lowercase());
+        }
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        for value in [
+            "",
+            "@",
+            "localhost",
+            "not an email@",
+            "-example.com",
+            "a..com",
+        ] {
+            assert_err!(SuppressionTarget::parse(value), "{:?} was accepted", value);
+        }
+    }
+}
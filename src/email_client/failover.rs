@@ -0,0 +1,567 @@
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+use super::{
+    BatchEmailResult, EmailMessage, EmailProvider, EmailTransport, SendEmailResponse,
+    TransportError,
+};
+use crate::domain::SubscriberEmail;
+
+/// 配信サービスごとのサーキットブレーカーの設定
+#[derive(Deserialize, Clone, Debug)]
+pub struct CircuitBreakerSettings {
+    /// 遮断するまでに許容する連続した一時的なエラーの回数
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub failure_threshold: u32,
+    /// 遮断してから再び試行するまでの時間
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub open_milliseconds: u64,
+}
+
+impl CircuitBreakerSettings {
+    fn open_duration(&self) -> Duration {
+        Duration::from_millis(self.open_milliseconds)
+    }
+}
+
+/// 優先順に並べた配信サービスで送信し、一時的なエラーの場合は次の配信サービスに切り替える
+///
+/// 停止している配信サービスはサーキットブレーカーが遮断している間は試行しない。
+/// 宛先の誤りなど再試行しても成功しないエラーは、他の配信サービスでも失敗するため切り替えない。
+pub struct FailoverTransport {
+    providers: Vec<FailoverProvider>,
+}
+
+struct FailoverProvider {
+    provider: EmailProvider,
+    transport: Arc<dyn EmailTransport>,
+    circuit_breaker: CircuitBreaker,
+}
+
+impl FailoverTransport {
+    pub fn new(
+        transports: Vec<(EmailProvider, Arc<dyn EmailTransport>)>,
+        settings: &CircuitBreakerSettings,
+    ) -> Self {
+        let providers = transports
+            .into_iter()
+            .map(|(provider, transport)| FailoverProvider {
+                provider,
+                transport,
+                circuit_breaker: CircuitBreaker::new(settings),
+            })
+            .collect();
+
+        Self { providers }
+    }
+
+    /// 配信サービスごとのサーキットブレーカーの状態を優先順に返却する
+    pub fn circuit_states(&self) -> Vec<(EmailProvider, CircuitState)> {
+        self.providers
+            .iter()
+            .map(|provider| (provider.provider, provider.circuit_breaker.state()))
+            .collect()
+    }
+
+    async fn send_with_failover<'a, R, F, Fut>(&'a self, send: F) -> Result<R, TransportError>
+    where
+        F: Fn(&'a dyn EmailTransport) -> Fut,
+        Fut: Future<Output = Result<R, TransportError>>,
+    {
+        let mut last_error = None;
+
+        for provider in &self.providers {
+            if !provider.circuit_breaker.try_acquire() {
+                tracing::debug!(
+                    provider = ?provider.provider,
+                    "Skipping an email provider whose circuit is open"
+                );
+                continue;
+            }
+
+            let e = match send(provider.transport.as_ref()).await {
+                Ok(response) => {
+                    provider.circuit_breaker.record_success();
+                    return Ok(response);
+                }
+                Err(e) => e,
+            };
+
+            // 配信サービスは応答しているため、再試行しても成功しないエラーは停止とみなさない
+            if !e.is_transient() {
+                provider.circuit_breaker.record_success();
+                return Err(e);
+            }
+
+            if provider.circuit_breaker.record_failure() {
+                tracing::warn!(
+                    provider = ?provider.provider,
+                    open_milliseconds = provider.circuit_breaker.open_duration.as_millis() as u64,
+                    "Opened the circuit of an email provider"
+                );
+            }
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                provider = ?provider.provider,
+                "Failed to send an email, failing over to the next email provider"
+            );
+            last_error = Some(e);
+        }
+
+        Err(last_error.unwrap_or_else(|| {
+            // すべての配信サービスが遮断されている場合は、最も早く試行できるまで待つ
+            let retry_after = self
+                .providers
+                .iter()
+                .filter_map(|provider| provider.circuit_breaker.retry_after())
+                .min();
+            TransportError::transient("All email providers are unavailable")
+                .with_retry_after(retry_after)
+        }))
+    }
+}
+
+#[async_trait]
+impl EmailTransport for FailoverTransport {
+    async fn send_email(
+        &self,
+        sender: &SubscriberEmail,
+        message: &EmailMessage<'_>,
+    ) -> Result<SendEmailResponse, TransportError> {
+        self.send_with_failover(|transport| transport.send_email(sender, message))
+            .await
+    }
+
+    async fn send_batch(
+        &self,
+        sender: &SubscriberEmail,
+        messages: &[&EmailMessage<'_>],
+    ) -> Result<Vec<BatchEmailResult>, TransportError> {
+        self.send_with_failover(|transport| transport.send_batch(sender, messages))
+            .await
+    }
+}
+
+/// サーキットブレーカーの状態
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// 通常どおり送信する
+    Closed { consecutive_failures: u32 },
+    /// 指定した時刻まで送信しない
+    Open { until: Instant },
+    /// 遮断期間が過ぎたため、1件だけ試行して結果を待っている
+    HalfOpen { probe_started_at: Instant },
+}
+
+/// 1つの配信サービスの状態を管理するサーキットブレーカー
+struct CircuitBreaker {
+    state: Mutex<CircuitState>,
+    failure_threshold: u32,
+    open_duration: Duration,
+}
+
+impl CircuitBreaker {
+    fn new(settings: &CircuitBreakerSettings) -> Self {
+        Self {
+            state: Mutex::new(CircuitState::Closed {
+                consecutive_failures: 0,
+            }),
+            failure_threshold: settings.failure_threshold.max(1),
+            open_duration: settings.open_duration(),
+        }
+    }
+
+    fn state(&self) -> CircuitState {
+        *self.state.lock().unwrap()
+    }
+
+    /// 送信してよい場合は true を返却する
+    fn try_acquire(&self) -> bool {
+        self.try_acquire_at(Instant::now())
+    }
+
+    fn try_acquire_at(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        match *state {
+            CircuitState::Closed { .. } => true,
+            CircuitState::Open { until } if now < until => false,
+            CircuitState::Open { .. } => {
+                *state = CircuitState::HalfOpen {
+                    probe_started_at: now,
+                };
+                true
+            }
+            // 試行が結果を記録せずに中断された場合に備えて、遮断期間が過ぎたら再び試行する
+            CircuitState::HalfOpen { probe_started_at }
+                if now.duration_since(probe_started_at) >= self.open_duration =>
+            {
+                *state = CircuitState::HalfOpen {
+                    probe_started_at: now,
+                };
+                true
+            }
+            CircuitState::HalfOpen { .. } => false,
+        }
+    }
+
+    fn record_success(&self) {
+        *self.state.lock().unwrap() = CircuitState::Closed {
+            consecutive_failures: 0,
+        };
+    }
+
+    /// 失敗を記録し、遮断した場合は true を返却する
+    fn record_failure(&self) -> bool {
+        self.record_failure_at(Instant::now())
+    }
+
+    fn record_failure_at(&self, now: Instant) -> bool {
+        let mut state = self.state.lock().unwrap();
+
+        let consecutive_failures = match *state {
+            CircuitState::Closed {
+                consecutive_failures,
+            } => consecutive_failures + 1,
+            // 試行に失敗した場合は再び遮断する
+            CircuitState::HalfOpen { .. } => self.failure_threshold,
+            CircuitState::Open { .. } => return false,
+        };
+
+        if consecutive_failures >= self.failure_threshold {
+            *state = CircuitState::Open {
+                until: now + self.open_duration,
+            };
+            true
+        } else {
+            *state = CircuitState::Closed {
+                consecutive_failures,
+            };
+            false
+        }
+    }
+
+    /// 遮断している場合は再び試行できるまでの時間を返却する
+    fn retry_after(&self) -> Option<Duration> {
+        match self.state() {
+            CircuitState::Open { until } => Some(until.saturating_duration_since(Instant::now())),
+            CircuitState::HalfOpen { probe_started_at } => Some(
+                (probe_started_at + self.open_duration).saturating_duration_since(Instant::now()),
+            ),
+            CircuitState::Closed { .. } => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicU32, Ordering},
+            Arc,
+        },
+        time::{Duration, Instant},
+    };
+
+    use async_trait::async_trait;
+    use claims::{assert_err, assert_ok};
+
+    use super::{CircuitBreaker, CircuitBreakerSettings, CircuitState, FailoverTransport};
+    use crate::{
+        domain::SubscriberEmail,
+        email_client::{
+            BatchEmailResult, EmailMessage, EmailProvider, EmailTransport, SendEmailResponse,
+            TransportError,
+        },
+    };
+
+    /// 指定したエラーを返すか、成功した場合は配信サービス名をメッセージIDとして返却する
+    struct StubTransport {
+        name: &'static str,
+        error: Option<fn() -> TransportError>,
+        n_calls: AtomicU32,
+    }
+
+    impl StubTransport {
+        fn healthy(name: &'static str) -> Arc<Self> {
+            Arc::new(Self {
+                name,
+                error: None,
+                n_calls: AtomicU32::new(0),
+            })
+        }
+
+        fn failing(name: &'static str, error: fn() -> TransportError) -> Arc<Self> {
+            Arc::new(Self {
+                name,
+                error: Some(error),
+                n_calls: AtomicU32::new(0),
+            })
+        }
+
+        fn n_calls(&self) -> u32 {
+            self.n_calls.load(Ordering::SeqCst)
+        }
+    }
+
+    #[async_trait]
+    impl EmailTransport for StubTransport {
+        async fn send_email(
+            &self,
+            _sender: &SubscriberEmail,
+            _message: &EmailMessage<'_>,
+        ) -> Result<SendEmailResponse, TransportError> {
+            self.n_calls.fetch_add(1, Ordering::SeqCst);
+            match self.error {
+                Some(error) => Err(error()),
+                None => Ok(SendEmailResponse {
+                    message_id: Some(self.name.into()),
+                }),
+            }
+        }
+
+        async fn send_batch(
+            &self,
+            _sender: &SubscriberEmail,
+            messages: &[&EmailMessage<'_>],
+        ) -> Result<Vec<BatchEmailResult>, TransportError> {
+            self.n_calls.fetch_add(1, Ordering::SeqCst);
+            match self.error {
+                Some(error) => Err(error()),
+                None => Ok(messages
+                    .iter()
+                    .map(|_| BatchEmailResult::Accepted {
+                        message_id: Some(self.name.into()),
+                    })
+                    .collect()),
+            }
+        }
+    }
+
+    fn outage() -> TransportError {
+        TransportError::transient("service unavailable")
+    }
+
+    fn invalid_request() -> TransportError {
+        TransportError::permanent("invalid recipient")
+    }
+
+    fn settings(failure_threshold: u32) -> CircuitBreakerSettings {
+        CircuitBreakerSettings {
+            failure_threshold,
+            open_milliseconds: 60_000,
+        }
+    }
+
+    fn failover(
+        primary: &Arc<StubTransport>,
+        secondary: &Arc<StubTransport>,
+        failure_threshold: u32,
+    ) -> FailoverTransport {
+        FailoverTransport::new(
+            vec![
+                (EmailProvider::Postmark, primary.clone()),
+                (EmailProvider::Smtp, secondary.clone()),
+            ],
+            &settings(failure_threshold),
+        )
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse("ursula@example.com".into()).unwrap()
+    }
+
+    async fn send(transport: &FailoverTransport) -> Result<SendEmailResponse, TransportError> {
+        let recipient = email();
+        let message = EmailMessage {
+            recipient: &recipient,
+            subject: "Newsletter title",
+            html_content: "<p>Newsletter body as HTML</p>",
+            text_content: "Newsletter body as plain text",
+            unsubscribe_url: None,
+            attachments: &[],
+        };
+        transport.send_email(&email(), &message).await
+    }
+
+    #[tokio::test]
+    async fn the_primary_provider_is_used_while_it_is_healthy() {
+        // Arrange
+        let (primary, secondary) = (
+            StubTransport::healthy("primary"),
+            StubTransport::healthy("secondary"),
+        );
+        let transport = failover(&primary, &secondary, 3);
+
+        // Act
+        let response = assert_ok!(send(&transport).await);
+
+        // Assert
+        assert_eq!(response.message_id.as_deref(), Some("primary"));
+        assert_eq!(secondary.n_calls(), 0);
+    }
+
+    #[tokio::test]
+    async fn transient_errors_fail_over_to_the_next_provider() {
+        // Arrange
+        let primary = StubTransport::failing("primary", outage);
+        let secondary = StubTransport::healthy("secondary");
+        let transport = failover(&primary, &secondary, 3);
+
+        // Act
+        let response = assert_ok!(send(&transport).await);
+
+        // Assert
+        assert_eq!(response.message_id.as_deref(), Some("secondary"));
+        assert_eq!(primary.n_calls(), 1);
+    }
+
+    #[tokio::test]
+    async fn batches_fail_over_to_the_next_provider() {
+        // Arrange
+        let primary = StubTransport::failing("primary", outage);
+        let secondary = StubTransport::healthy("secondary");
+        let transport = failover(&primary, &secondary, 3);
+        let recipient = email();
+        let message = EmailMessage {
+            recipient: &recipient,
+            subject: "Newsletter title",
+            html_content: "<p>Newsletter body as HTML</p>",
+            text_content: "Newsletter body as plain text",
+            unsubscribe_url: None,
+            attachments: &[],
+        };
+
+        // Act
+        let results = assert_ok!(transport.send_batch(&email(), &[&message]).await);
+
+        // Assert
+        assert!(matches!(
+            results.as_slice(),
+            [BatchEmailResult::Accepted { message_id: Some(id) }] if id == "secondary"
+        ));
+    }
+
+    #[tokio::test]
+    async fn permanent_errors_do_not_fail_over() {
+        // Arrange
+        let primary = StubTransport::failing("primary", invalid_request);
+        let secondary = StubTransport::healthy("secondary");
+        let transport = failover(&primary, &secondary, 1);
+
+        // Act
+        let outcome = send(&transport).await;
+
+        // Assert
+        let e = assert_err!(outcome);
+        assert!(!e.is_transient());
+        assert_eq!(secondary.n_calls(), 0);
+        assert_eq!(
+            transport.circuit_states()[0].1,
+            CircuitState::Closed {
+                consecutive_failures: 0
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn a_provider_with_an_open_circuit_is_skipped() {
+        // Arrange
+        let primary = StubTransport::failing("primary", outage);
+        let secondary = StubTransport::healthy("secondary");
+        let transport = failover(&primary, &secondary, 2);
+
+        // Act
+        for _ in 0..5 {
+            assert_ok!(send(&transport).await);
+        }
+
+        // Assert
+        assert_eq!(primary.n_calls(), 2);
+        assert_eq!(secondary.n_calls(), 5);
+        assert!(matches!(
+            transport.circuit_states()[0].1,
+            CircuitState::Open { .. }
+        ));
+    }
+
+    #[tokio::test]
+    async fn a_transient_error_is_returned_when_every_provider_is_down() {
+        // Arrange
+        let primary = StubTransport::failing("primary", outage);
+        let secondary = StubTransport::failing("secondary", outage);
+        let transport = failover(&primary, &secondary, 1);
+        assert_err!(send(&transport).await);
+
+        // Act
+        let outcome = send(&transport).await;
+
+        // Assert
+        let e = assert_err!(outcome);
+        assert!(e.is_transient());
+        assert!(e.retry_after().unwrap() <= Duration::from_secs(60));
+        assert_eq!(primary.n_calls(), 1);
+        assert_eq!(secondary.n_calls(), 1);
+    }
+
+    #[test]
+    fn a_single_probe_is_allowed_after_the_open_duration() {
+        // Arrange
+        let circuit_breaker = CircuitBreaker::new(&settings(1));
+        let now = Instant::now();
+        assert!(circuit_breaker.record_failure_at(now));
+
+        // Act & Assert
+        assert!(!circuit_breaker.try_acquire_at(now + Duration::from_secs(59)));
+        assert!(circuit_breaker.try_acquire_at(now + Duration::from_secs(60)));
+        assert!(!circuit_breaker.try_acquire_at(now + Duration::from_secs(61)));
+    }
+
+    #[test]
+    fn a_successful_probe_closes_the_circuit() {
+        // Arrange
+        let circuit_breaker = CircuitBreaker::new(&settings(1));
+        let now = Instant::now();
+        circuit_breaker.record_failure_at(now);
+        assert!(circuit_breaker.try_acquire_at(now + Duration::from_secs(60)));
+
+        // Act
+        circuit_breaker.record_success();
+
+        // Assert
+        assert!(circuit_breaker.try_acquire_at(now + Duration::from_secs(61)));
+        assert_eq!(
+            circuit_breaker.state(),
+            CircuitState::Closed {
+                consecutive_failures: 0
+            }
+        );
+    }
+
+    #[test]
+    fn a_failed_probe_opens_the_circuit_again() {
+        // Arrange
+        let circuit_breaker = CircuitBreaker::new(&settings(3));
+        let now = Instant::now();
+        for _ in 0..3 {
+            circuit_breaker.record_failure_at(now);
+        }
+        let probe_at = now + Duration::from_secs(60);
+        assert!(circuit_breaker.try_acquire_at(probe_at));
+
+        // Act
+        let opened = circuit_breaker.record_failure_at(probe_at);
+
+        // Assert
+        assert!(opened);
+        assert!(!circuit_breaker.try_acquire_at(probe_at + Duration::from_secs(59)));
+    }
+}
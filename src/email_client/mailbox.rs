@@ -0,0 +1,257 @@
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{BatchEmailResult, EmailMessage, EmailTransport, SendEmailResponse, TransportError};
+use crate::domain::SubscriberEmail;
+
+/// provider が mailbox の場合の保存先
+#[derive(Deserialize, Clone, Debug, Default)]
+pub struct MailboxSettings {
+    /// 指定した場合はメールをファイルにも保存し、再起動後も表示できるようにする
+    pub directory: Option<PathBuf>,
+}
+
+/// 開発用に送信したメールを配信せずに保存する受信箱
+///
+/// 複製した受信箱は同じメールを共有する。
+#[derive(Clone)]
+pub struct DevMailbox {
+    emails: Arc<RwLock<Vec<StoredEmail>>>,
+    directory: Option<PathBuf>,
+}
+
+/// 受信箱に保存したメール
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredEmail {
+    pub id: Uuid,
+    pub received_at: DateTime<Utc>,
+    pub from: String,
+    pub to: String,
+    pub subject: String,
+    pub html_content: String,
+    pub text_content: String,
+    pub headers: Vec<(String, String)>,
+    pub attachments: Vec<StoredAttachment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StoredAttachment {
+    pub name: String,
+    pub content_type: String,
+    pub content_id: Option<String>,
+}
+
+impl DevMailbox {
+    /// ディレクトリを指定した場合は、保存済みのメールを読み込む
+    pub fn new(settings: MailboxSettings) -> Result<Self, anyhow::Error> {
+        let mut emails = Vec::new();
+
+        if let Some(directory) = &settings.directory {
+            std::fs::create_dir_all(directory)?;
+            for entry in std::fs::read_dir(directory)? {
+                let path = entry?.path();
+                if matches!(path.extension(), Some(extension) if extension == "json") {
+                    let email = serde_json::from_slice(&std::fs::read(&path)?)?;
+                    emails.push(email);
+                }
+            }
+            emails.sort_by_key(|email: &StoredEmail| email.received_at);
+        }
+
+        Ok(Self {
+            emails: Arc::new(RwLock::new(emails)),
+            directory: settings.directory,
+        })
+    }
+
+    /// 新しいものから順にメールを返却する
+    pub fn emails(&self) -> Vec<StoredEmail> {
+        let emails = self.emails.read().unwrap();
+        emails.iter().rev().cloned().collect()
+    }
+
+    pub fn email(&self, id: Uuid) -> Option<StoredEmail> {
+        let emails = self.emails.read().unwrap();
+        emails.iter().find(|email| email.id == id).cloned()
+    }
+
+    fn store(
+        &self,
+        sender: &SubscriberEmail,
+        message: &EmailMessage<'_>,
+    ) -> Result<Uuid, TransportError> {
+        let email = StoredEmail {
+            id: Uuid::new_v4(),
+            received_at: Utc::now(),
+            from: sender.as_ref().to_owned(),
+            to: message.recipient.as_ref().to_owned(),
+            subject: message.subject.to_owned(),
+            html_content: message.html_content.to_owned(),
+            text_content: message.text_content.to_owned(),
+            headers: message
+                .headers()
+                .into_iter()
+                .map(|(name, value)| (name.to_owned(), value))
+                .collect(),
+            attachments: message
+                .attachments
+                .iter()
+                .map(|attachment| StoredAttachment {
+                    name: attachment.name.clone(),
+                    content_type: attachment.content_type.clone(),
+                    content_id: attachment.content_id.clone(),
+                })
+                .collect(),
+        };
+
+        if let Some(directory) = &self.directory {
+            let path = directory.join(format!("{}.json", email.id));
+            let content = serde_json::to_vec_pretty(&email).map_err(TransportError::permanent)?;
+            std::fs::write(path, content).map_err(TransportError::permanent)?;
+        }
+
+        let id = email.id;
+        self.emails.write().unwrap().push(email);
+
+        tracing::info!(
+            email_id = %id,
+            "Stored an email in the development mailbox instead of sending it"
+        );
+
+        Ok(id)
+    }
+}
+
+#[async_trait]
+impl EmailTransport for DevMailbox {
+    async fn send_email(
+        &self,
+        sender: &SubscriberEmail,
+        message: &EmailMessage<'_>,
+    ) -> Result<SendEmailResponse, TransportError> {
+        let id = self.store(sender, message)?;
+
+        Ok(SendEmailResponse {
+            message_id: Some(id.to_string()),
+        })
+    }
+
+    async fn send_batch(
+        &self,
+        sender: &SubscriberEmail,
+        messages: &[&EmailMessage<'_>],
+    ) -> Result<Vec<BatchEmailResult>, TransportError> {
+        messages
+            .iter()
+            .map(|message| {
+                let id = self.store(sender, message)?;
+                Ok(BatchEmailResult::Accepted {
+                    message_id: Some(id.to_string()),
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::{assert_ok, assert_some};
+    use uuid::Uuid;
+
+    use super::{DevMailbox, MailboxSettings};
+    use crate::{
+        domain::SubscriberEmail,
+        email_client::{EmailMessage, EmailTransport},
+    };
+
+    fn email(address: &str) -> SubscriberEmail {
+        SubscriberEmail::parse(address.into()).unwrap()
+    }
+
+    fn message(recipient: &SubscriberEmail) -> EmailMessage<'_> {
+        EmailMessage {
+            recipient,
+            subject: "Newsletter title",
+            html_content: "<p>Newsletter body as HTML</p>",
+            text_content: "Newsletter body as plain text",
+            unsubscribe_url: Some("https://example.com/unsubscribe"),
+            attachments: &[],
+        }
+    }
+
+    #[tokio::test]
+    async fn sent_emails_are_listed_from_newest_to_oldest() {
+        // Arrange
+        let mailbox = DevMailbox::new(MailboxSettings::default()).unwrap();
+        let (first, second) = (email("ursula@example.com"), email("ulysses@example.com"));
+
+        // Act
+        let sender = email("sender@example.com");
+        assert_ok!(mailbox.send_email(&sender, &message(&first)).await);
+        assert_ok!(mailbox.send_batch(&sender, &[&message(&second)]).await);
+
+        // Assert
+        let emails = mailbox.emails();
+        assert_eq!(emails.len(), 2);
+        assert_eq!(emails[0].to, "ulysses@example.com");
+        assert_eq!(emails[1].to, "ursula@example.com");
+        assert_eq!(emails[1].text_content, "Newsletter body as plain text");
+        assert_eq!(
+            emails[1].headers[0],
+            (
+                "List-Unsubscribe".to_owned(),
+                "<https://example.com/unsubscribe>".to_owned()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn cloned_mailboxes_share_emails() {
+        // Arrange
+        let mailbox = DevMailbox::new(MailboxSettings::default()).unwrap();
+        let cloned = mailbox.clone();
+        let recipient = email("ursula@example.com");
+
+        // Act
+        let response = mailbox
+            .send_email(&email("sender@example.com"), &message(&recipient))
+            .await;
+
+        // Assert
+        let message_id = assert_ok!(response).message_id.unwrap();
+        assert_some!(cloned.email(message_id.parse().unwrap()));
+    }
+
+    #[tokio::test]
+    async fn emails_stored_on_disk_are_loaded_again() {
+        // Arrange
+        let directory = std::env::temp_dir().join(Uuid::new_v4().to_string());
+        let settings = MailboxSettings {
+            directory: Some(directory.clone()),
+        };
+        let mailbox = DevMailbox::new(settings.clone()).unwrap();
+        let recipient = email("ursula@example.com");
+        assert_ok!(
+            mailbox
+                .send_email(&email("sender@example.com"), &message(&recipient))
+                .await
+        );
+
+        // Act
+        let reopened = DevMailbox::new(settings).unwrap();
+
+        // Assert
+        let emails = reopened.emails();
+        assert_eq!(emails.len(), 1);
+        assert_eq!(emails[0].subject, "Newsletter title");
+
+        std::fs::remove_dir_all(directory).unwrap();
+    }
+}
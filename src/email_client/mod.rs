@@ -0,0 +1,1277 @@
+mod failover;
+mod mailbox;
+mod postmark;
+mod smtp;
+mod transport;
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+pub use failover::{CircuitBreakerSettings, CircuitState, FailoverTransport};
+pub use mailbox::{DevMailbox, MailboxSettings, StoredAttachment, StoredEmail};
+pub use postmark::PostmarkTransport;
+pub use smtp::{SmtpAuthMechanism, SmtpSettings, SmtpTls, SmtpTransport};
+pub use transport::{BatchEmailResult, EmailTransport, TransportError};
+
+use crate::{
+    domain::SubscriberEmail,
+    rate_limiter::{RateLimit, RateLimiter, RateLimiterMetrics},
+};
+
+/// バッチAPIで1回のリクエストに含められるメールの最大数
+pub const MAX_BATCH_SIZE: usize = 500;
+
+/// 配信サービスが受け付ける添付ファイルを含めたメール1通の最大サイズ
+pub const MAX_MESSAGE_SIZE: usize = 10 * 1024 * 1024;
+
+/// 配信サービスが受け付けるバッチAPIの1回のリクエストの最大サイズ
+pub const MAX_BATCH_PAYLOAD_SIZE: usize = 50 * 1024 * 1024;
+
+/// 最大サイズを超えたメールのエラーコード (配信サービスが不正なリクエストに返す値と同じ)
+pub const MESSAGE_TOO_LARGE_ERROR_CODE: i64 = 300;
+
+/// メールの送信に利用する配信サービス
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum EmailProvider {
+    Postmark,
+    Smtp,
+    /// 送信せずに開発用の受信箱に保存する (ローカル環境のみ)
+    Mailbox,
+}
+
+/// 配信サービスに依存しない送信処理を行うクライアント
+///
+/// 実際の送信は `EmailTransport` に任せ、再試行と送信数の上限はクライアントで制御する。
+#[derive(Clone)]
+pub struct EmailClient {
+    transport: Arc<dyn EmailTransport>,
+    sender: SubscriberEmail,
+    retry_policy: RetryPolicy,
+    // 複製したクライアント全体で送信数の上限を守れるように共有する
+    rate_limiter: Arc<RateLimiter>,
+}
+
+/// 一時的なエラーで送信に失敗した場合の再試行の方針
+#[derive(Deserialize, Clone, Debug)]
+pub struct RetryPolicy {
+    /// 初回の送信を含む最大試行回数
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_attempts: u32,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub base_delay_milliseconds: u64,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub max_delay_milliseconds: u64,
+    /// 有効な場合は待ち時間を 0 から計算した待ち時間までの乱数にする
+    pub jitter: bool,
+}
+
+impl RetryPolicy {
+    /// 再試行を行わない
+    pub fn none() -> Self {
+        Self {
+            max_attempts: 1,
+            base_delay_milliseconds: 0,
+            max_delay_milliseconds: 0,
+            jitter: false,
+        }
+    }
+
+    /// n回目の試行に失敗した後の待ち時間を計算する
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay_milliseconds
+            .saturating_mul(2_u64.saturating_pow(attempt.saturating_sub(1)))
+            .min(self.max_delay_milliseconds);
+
+        let delay = if self.jitter {
+            rand::thread_rng().gen_range(0..=delay)
+        } else {
+            delay
+        };
+
+        Duration::from_millis(delay)
+    }
+
+    fn max_delay(&self) -> Duration {
+        Duration::from_millis(self.max_delay_milliseconds)
+    }
+}
+
+impl EmailClient {
+    pub fn new(
+        transport: Arc<dyn EmailTransport>,
+        sender: SubscriberEmail,
+        retry_policy: RetryPolicy,
+        rate_limit: RateLimit,
+    ) -> Self {
+        Self {
+            transport,
+            sender,
+            retry_policy,
+            rate_limiter: Arc::new(RateLimiter::new(&rate_limit)),
+        }
+    }
+
+    /// 送信数の上限による待機時間の集計を返却する
+    pub fn rate_limiter_metrics(&self) -> RateLimiterMetrics {
+        self.rate_limiter.metrics()
+    }
+
+    pub async fn send_email(
+        &self,
+        recipient: &SubscriberEmail,
+        subject: &str,
+        html_content: &str,
+        text_content: &str,
+    ) -> Result<SendEmailResponse, TransportError> {
+        let message = EmailMessage {
+            recipient,
+            subject,
+            html_content,
+            text_content,
+            unsubscribe_url: None,
+            attachments: &[],
+        };
+
+        self.send_with_retry(1, || self.transport.send_email(&self.sender, &message))
+            .await
+    }
+
+    /// バッチAPIで複数のメールを送信し、宛先ごとの送信結果を返却する
+    ///
+    /// `MAX_BATCH_SIZE` または `MAX_BATCH_PAYLOAD_SIZE` を超える場合は分割して送信する。
+    /// 分割したリクエストの一部を送信した後に失敗した場合は、送信済みのメールが再送されないように
+    /// エラーを返却せず、残りのメールを後で再送するメールとして返却する。
+    pub async fn send_batch(
+        &self,
+        messages: &[EmailMessage<'_>],
+    ) -> Result<SendBatchResponse, TransportError> {
+        let mut batch_response = SendBatchResponse::default();
+
+        // 配信サービスに拒否されるメールは送信せずに失敗として返却する
+        let mut sendable = Vec::with_capacity(messages.len());
+        for message in messages {
+            let size = message.size();
+            if size > MAX_MESSAGE_SIZE {
+                tracing::warn!(
+                    size,
+                    max_size = MAX_MESSAGE_SIZE,
+                    "Skipping an email that exceeds the maximum message size"
+                );
+                batch_response.failed.push(FailedEmail {
+                    recipient: message.recipient.clone(),
+                    error_code: MESSAGE_TOO_LARGE_ERROR_CODE,
+                    message: format!(
+                        "The message size {} bytes exceeds the limit of {} bytes",
+                        size, MAX_MESSAGE_SIZE
+                    ),
+                });
+            } else {
+                sendable.push(message);
+            }
+        }
+
+        let chunks = batch_chunks(&sendable, MAX_BATCH_SIZE, MAX_BATCH_PAYLOAD_SIZE);
+        let mut sent_any = false;
+        for (i, chunk) in chunks.iter().copied().enumerate() {
+            let outcome = self
+                .send_with_retry(chunk.len() as u32, || {
+                    self.transport.send_batch(&self.sender, chunk)
+                })
+                .await;
+
+            let results = match outcome {
+                Ok(results) => results,
+                Err(e) if !sent_any => return Err(e),
+                Err(e) => {
+                    // 送信済みのメールを再送しないように、このリクエストと残りのリクエストのメールは
+                    // 送信せずに後で再送する
+                    let error_message = e.to_string();
+                    for message in chunks[i..].iter().copied().flatten() {
+                        batch_response.deferred.push(DeferredEmail {
+                            recipient: message.recipient.clone(),
+                            error: TransportError::transient(error_message.clone()),
+                        });
+                    }
+                    break;
+                }
+            };
+            sent_any = true;
+
+            if results.len() != chunk.len() {
+                tracing::warn!(
+                    n_messages = chunk.len(),
+                    n_results = results.len(),
+                    "The email server returned an unexpected number of batch results"
+                );
+            }
+
+            // 結果はリクエストと同じ順番で返却される
+            // 送信自体は成功しているため、結果が返却されなかったメールは送信済みとして扱う
+            let mut results = results.into_iter();
+            for message in chunk {
+                match results.next() {
+                    Some(BatchEmailResult::Rejected {
+                        error_code,
+                        message: error_message,
+                    }) => {
+                        batch_response.failed.push(FailedEmail {
+                            recipient: message.recipient.clone(),
+                            error_code,
+                            message: error_message,
+                        });
+                    }
+                    Some(BatchEmailResult::Deferred { error }) => {
+                        batch_response.deferred.push(DeferredEmail {
+                            recipient: message.recipient.clone(),
+                            error,
+                        });
+                    }
+                    Some(BatchEmailResult::Accepted { message_id }) => {
+                        batch_response.succeeded.push(SentEmail {
+                            recipient: message.recipient.clone(),
+                            message_id,
+                        });
+                    }
+                    None => {
+                        batch_response.succeeded.push(SentEmail {
+                            recipient: message.recipient.clone(),
+                            message_id: None,
+                        });
+                    }
+                }
+            }
+        }
+
+        Ok(batch_response)
+    }
+
+    /// 一時的なエラーの場合は再試行の方針に従って再送する
+    async fn send_with_retry<R, F, Fut>(
+        &self,
+        n_messages: u32,
+        send: F,
+    ) -> Result<R, TransportError>
+    where
+        F: Fn() -> Fut,
+        Fut: Future<Output = Result<R, TransportError>>,
+    {
+        let max_attempts = self.retry_policy.max_attempts.max(1);
+        let mut attempt = 1;
+
+        loop {
+            let wait = self.rate_limiter.acquire_many(n_messages).await;
+            if !wait.is_zero() {
+                tracing::info!(
+                    wait_milliseconds = wait.as_millis() as u64,
+                    n_messages,
+                    "Waited for the email rate limiter"
+                );
+            }
+
+            let e = match send().await {
+                Ok(response) => {
+                    tracing::info!(
+                        attempt,
+                        max_attempts,
+                        n_messages,
+                        "Email accepted by the email server"
+                    );
+                    return Ok(response);
+                }
+                Err(e) => e,
+            };
+
+            let delay = match e.retry_after() {
+                Some(retry_after) => retry_after,
+                None => self.retry_policy.backoff(attempt),
+            };
+
+            // Retry-After で許容できる最大の待ち時間より長く待つよう指定された場合は再試行しない
+            let will_retry = e.is_transient()
+                && attempt < max_attempts
+                && delay <= self.retry_policy.max_delay();
+
+            tracing::warn!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                attempt,
+                max_attempts,
+                n_messages,
+                transient = e.is_transient(),
+                will_retry,
+                retry_in_milliseconds = delay.as_millis() as u64,
+                "Failed to send an email",
+            );
+
+            if !will_retry {
+                return Err(e);
+            }
+
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+}
+
+/// メール配信サービスが受け付けたメールの情報
+#[derive(Debug, Default, Deserialize)]
+pub struct SendEmailResponse {
+    #[serde(rename = "MessageID")]
+    pub message_id: Option<String>,
+}
+
+/// バッチAPIで送信するメール
+pub struct EmailMessage<'a> {
+    pub recipient: &'a SubscriberEmail,
+    pub subject: &'a str,
+    pub html_content: &'a str,
+    pub text_content: &'a str,
+    /// 指定した場合は RFC 8058 のワンクリックでの配信停止に対応したヘッダーを付与する
+    pub unsubscribe_url: Option<&'a str>,
+    pub attachments: &'a [EmailAttachment],
+}
+
+impl EmailMessage<'_> {
+    /// 配信サービスのサイズ制限と比較するためのメールのサイズ
+    pub fn size(&self) -> usize {
+        message_size(
+            self.subject,
+            self.html_content,
+            self.text_content,
+            self.attachments,
+        )
+    }
+
+    /// 本文とは別に付与するヘッダーの名前と値
+    pub fn headers(&self) -> Vec<(&'static str, String)> {
+        self.unsubscribe_url
+            .map(list_unsubscribe_headers)
+            .unwrap_or_default()
+    }
+}
+
+/// メールの数と合計のサイズがそれぞれの上限を超えないように、順番を保ったまま分割する
+///
+/// 1通で上限を超えるメールは単独のリクエストとする。
+fn batch_chunks<'m, 'a>(
+    messages: &'m [&'m EmailMessage<'a>],
+    max_messages: usize,
+    max_payload_size: usize,
+) -> Vec<&'m [&'m EmailMessage<'a>]> {
+    let mut chunks = Vec::new();
+    let (mut start, mut payload_size) = (0, 0);
+
+    for (i, message) in messages.iter().enumerate() {
+        let size = message.size();
+        let is_full = i - start == max_messages || payload_size + size > max_payload_size;
+        if i > start && is_full {
+            chunks.push(&messages[start..i]);
+            start = i;
+            payload_size = 0;
+        }
+        payload_size += size;
+    }
+    if start < messages.len() {
+        chunks.push(&messages[start..]);
+    }
+
+    chunks
+}
+
+/// 件名、本文、Base64 でエンコードされた添付ファイルの合計のバイト数
+pub fn message_size(
+    subject: &str,
+    html_content: &str,
+    text_content: &str,
+    attachments: &[EmailAttachment],
+) -> usize {
+    subject.len()
+        + html_content.len()
+        + text_content.len()
+        + attachments
+            .iter()
+            .map(|attachment| attachment.content.len())
+            .sum::<usize>()
+}
+
+/// メールの添付ファイル
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct EmailAttachment {
+    pub name: String,
+    pub content_type: String,
+    /// Base64 でエンコードされた内容
+    pub content: String,
+    /// HTML の本文から `cid:` で参照するインライン画像の場合に指定する
+    #[serde(rename = "ContentID", skip_serializing_if = "Option::is_none")]
+    pub content_id: Option<String>,
+}
+
+/// バッチAPIで送信したメールの宛先ごとの結果
+#[derive(Debug, Default)]
+pub struct SendBatchResponse {
+    pub succeeded: Vec<SentEmail>,
+    pub failed: Vec<FailedEmail>,
+    /// 一時的なエラーで送信できなかったため、後で再送するメール
+    pub deferred: Vec<DeferredEmail>,
+}
+
+#[derive(Debug)]
+pub struct SentEmail {
+    pub recipient: SubscriberEmail,
+    pub message_id: Option<String>,
+}
+
+/// メール配信サービスに受け付けられなかったメール
+#[derive(Debug)]
+pub struct FailedEmail {
+    pub recipient: SubscriberEmail,
+    /// Postmark のエラーコード (例: 406 は配信停止中の宛先)
+    pub error_code: i64,
+    pub message: String,
+}
+
+#[derive(Debug)]
+pub struct DeferredEmail {
+    pub recipient: SubscriberEmail,
+    pub error: TransportError,
+}
+
+/// メールボックスプロバイダーが配信停止のボタンを表示するためのヘッダー
+fn list_unsubscribe_headers(unsubscribe_url: &str) -> Vec<(&'static str, String)> {
+    vec![
+        ("List-Unsubscribe", format!("<{}>", unsubscribe_url)),
+        ("List-Unsubscribe-Post", "List-Unsubscribe=One-Click".into()),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::email_client::{
+        batch_chunks, BatchEmailResult, EmailAttachment, EmailClient, EmailMessage, EmailTransport,
+        PostmarkTransport, RetryPolicy, SendEmailResponse, TransportError, MAX_BATCH_SIZE,
+        MAX_MESSAGE_SIZE, MESSAGE_TOO_LARGE_ERROR_CODE,
+    };
+    use crate::rate_limiter::RateLimit;
+    use async_trait::async_trait;
+    use claims::{assert_err, assert_ok};
+    use fake::faker::lorem::en::Paragraph;
+    use fake::faker::{internet::en::SafeEmail, lorem::en::Sentence};
+    use fake::{Fake, Faker};
+    use secrecy::Secret;
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+    use wiremock::matchers::{header, method, path};
+    use wiremock::Request;
+    use wiremock::{matchers::any, Mock, MockServer, ResponseTemplate};
+
+    use crate::domain::SubscriberEmail;
+
+    struct SendEmailBodyMatcher;
+
+    impl wiremock::Match for SendEmailBodyMatcher {
+        fn matches(&self, request: &Request) -> bool {
+            // parse
+            let result: Result<serde_json::Value, _> = serde_json::from_slice(&request.body);
+
+            if let Ok(body) = result {
+                dbg!(&body);
+
+                // 必要となるリクエストボディの検証を行う
+                body.get("From").is_some()
+                    && body.get("To").is_some()
+                    && body.get("Subject").is_some()
+                    && body.get("HtmlBody").is_some()
+                    && body.get("TextBody").is_some()
+            } else {
+                false
+            }
+        }
+    }
+
+    /// 指定した回数だけ一時的なエラーを返却した後に送信に成功する
+    struct FlakyTransport {
+        n_failures: AtomicU32,
+    }
+
+    #[async_trait]
+    impl EmailTransport for FlakyTransport {
+        async fn send_email(
+            &self,
+            _sender: &SubscriberEmail,
+            _message: &EmailMessage<'_>,
+        ) -> Result<SendEmailResponse, TransportError> {
+            let remaining = self.n_failures.load(Ordering::SeqCst);
+            if remaining > 0 {
+                self.n_failures.store(remaining - 1, Ordering::SeqCst);
+                return Err(TransportError::transient("connection reset"));
+            }
+
+            Ok(SendEmailResponse {
+                message_id: Some("flaky".into()),
+            })
+        }
+
+        async fn send_batch(
+            &self,
+            _sender: &SubscriberEmail,
+            messages: &[&EmailMessage<'_>],
+        ) -> Result<Vec<BatchEmailResult>, TransportError> {
+            Ok(messages
+                .iter()
+                .map(|_| BatchEmailResult::Accepted { message_id: None })
+                .collect())
+        }
+    }
+
+    fn subject() -> String {
+        Sentence(1..2).fake()
+    }
+
+    fn content() -> String {
+        Paragraph(1..10).fake()
+    }
+
+    fn email() -> SubscriberEmail {
+        SubscriberEmail::parse(SafeEmail().fake()).unwrap()
+    }
+
+    fn email_client(base_url: String) -> EmailClient {
+        EmailClient::new(
+            Arc::new(postmark_transport(base_url)),
+            email(),
+            RetryPolicy::none(),
+            rate_limit(),
+        )
+    }
+
+    fn postmark_transport(base_url: String) -> PostmarkTransport {
+        PostmarkTransport::new(
+            base_url,
+            Secret::new(Faker.fake()),
+            std::time::Duration::from_millis(200),
+        )
+    }
+
+    fn rate_limit() -> RateLimit {
+        RateLimit {
+            messages_per_second: 100,
+            burst: 100,
+        }
+    }
+
+    fn retry_policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 3,
+            base_delay_milliseconds: 10,
+            max_delay_milliseconds: 2000,
+            jitter: true,
+        }
+    }
+
+    fn retrying_email_client(base_url: String) -> EmailClient {
+        EmailClient::new(
+            Arc::new(postmark_transport(base_url)),
+            email(),
+            retry_policy(),
+            rate_limit(),
+        )
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_the_expexted_request() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .and(header("Content-Type", "application/json"))
+            .and(path("/email"))
+            .and(method("POST"))
+            .and(SendEmailBodyMatcher)
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let _ = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+    }
+
+    #[tokio::test]
+    async fn send_email_succeeds_if_the_server_returns_200() {
+        // Arrange
+        // ランダムなポートを使用してバックグラウンドでサーバーを起動する
+        // uriメソッドでURLを取得可能
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        // このテストではHTTPステータスコードの検証のみを行う
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_returns_the_message_id_assigned_by_the_server() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+                "To": "receiver@example.com",
+                "SubmittedAt": "2023-05-21T09:00:00.0000000Z",
+                "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+                "ErrorCode": 0,
+                "Message": "OK"
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        let response = assert_ok!(outcome);
+        assert_eq!(
+            response.message_id.as_deref(),
+            Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817")
+        );
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_if_the_server_returns_500() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        // このテストではHTTPステータスコードの検証のみを行う
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_times_out_if_the_server_takes_too_long() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+
+        // このテストではHTTPステータスコードの検証のみを行う
+        Mock::given(any())
+            // タイムアウトを検証するために３分間送信を遅延させる
+            .respond_with(ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(180)))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_retries_if_the_server_returns_500() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = retrying_email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .up_to_n_times(2)
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_retries_transient_errors_from_any_transport() {
+        // Arrange
+        let email_client = EmailClient::new(
+            Arc::new(FlakyTransport {
+                n_failures: AtomicU32::new(2),
+            }),
+            email(),
+            retry_policy(),
+            rate_limit(),
+        );
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        let response = assert_ok!(outcome);
+        assert_eq!(response.message_id.as_deref(), Some("flaky"));
+    }
+
+    #[tokio::test]
+    async fn send_email_gives_up_after_max_attempts() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = retrying_email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(503))
+            .expect(3)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_does_not_retry_if_the_server_returns_422() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = retrying_email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(422))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_retries_after_a_timeout() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = retrying_email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200).set_delay(std::time::Duration::from_secs(180)))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+    }
+
+    #[tokio::test]
+    async fn send_email_honours_the_retry_after_header() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = retrying_email_client(mock_server.uri());
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "1"))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let started_at = std::time::Instant::now();
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+        assert!(started_at.elapsed() >= std::time::Duration::from_secs(1));
+    }
+
+    #[tokio::test]
+    async fn send_email_does_not_wait_longer_than_the_max_delay() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = retrying_email_client(mock_server.uri());
+
+        // 最大の待ち時間より長い Retry-After が指定された場合は再試行しない
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(429).insert_header("Retry-After", "60"))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    #[test]
+    fn backoff_grows_exponentially_up_to_the_max_delay() {
+        let policy = RetryPolicy {
+            jitter: false,
+            ..retry_policy()
+        };
+
+        assert_eq!(policy.backoff(1), std::time::Duration::from_millis(10));
+        assert_eq!(policy.backoff(2), std::time::Duration::from_millis(20));
+        assert_eq!(policy.backoff(3), std::time::Duration::from_millis(40));
+        assert_eq!(policy.backoff(20), std::time::Duration::from_millis(2000));
+    }
+
+    #[test]
+    fn jittered_backoff_never_exceeds_the_exponential_delay() {
+        let policy = retry_policy();
+
+        for attempt in 1..10 {
+            let expected = RetryPolicy {
+                jitter: false,
+                ..retry_policy()
+            }
+            .backoff(attempt);
+            assert!(policy.backoff(attempt) <= expected);
+        }
+    }
+
+    #[tokio::test]
+    async fn cloned_clients_share_the_rate_limiter() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = EmailClient::new(
+            Arc::new(postmark_transport(mock_server.uri())),
+            email(),
+            RetryPolicy::none(),
+            RateLimit {
+                messages_per_second: 20,
+                burst: 1,
+            },
+        );
+        let cloned_client = email_client.clone();
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let _ = email_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+        let _ = cloned_client
+            .send_email(&email(), &subject(), &content(), &content())
+            .await;
+
+        // Assert
+        // 複製したクライアントの送信も同じバケットからトークンを消費する
+        let metrics = email_client.rate_limiter_metrics();
+        assert_eq!(metrics.acquired, 2);
+        assert_eq!(metrics.throttled, 1);
+    }
+
+    #[tokio::test]
+    async fn send_batch_sends_all_messages_in_a_single_request() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let recipients = vec![email(), email(), email()];
+        let (subject, content) = (subject(), content());
+        let messages = batch_messages(&recipients, &subject, &content);
+
+        Mock::given(path("/email/batch"))
+            .and(method("POST"))
+            .and(header("Content-Type", "application/json"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.send_batch(&messages).await;
+
+        // Assert
+        let response = assert_ok!(outcome);
+        assert_eq!(response.succeeded.len(), 3);
+
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        let body = body.as_array().unwrap();
+        assert_eq!(body.len(), 3);
+        assert_eq!(body[1]["To"], recipients[1].as_ref());
+    }
+
+    #[tokio::test]
+    async fn send_batch_reports_failed_recipients_with_their_error_code() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let recipients = vec![email(), email()];
+        let (subject, content) = (subject(), content());
+        let messages = batch_messages(&recipients, &subject, &content);
+
+        Mock::given(path("/email/batch"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                {
+                    "ErrorCode": 0,
+                    "Message": "OK",
+                    "MessageID": "b7bc2f4a-e38e-4336-af7d-e6c392c2f817",
+                    "SubmittedAt": "2023-05-21T09:00:00.0000000Z",
+                    "To": recipients[0].as_ref()
+                },
+                {
+                    "ErrorCode": 406,
+                    "Message": "You tried to send to a recipient that has been marked as inactive."
+                }
+            ])))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.send_batch(&messages).await;
+
+        // Assert
+        let response = assert_ok!(outcome);
+        assert_eq!(response.succeeded.len(), 1);
+        assert_eq!(
+            response.succeeded[0].recipient.as_ref(),
+            recipients[0].as_ref()
+        );
+        assert_eq!(
+            response.succeeded[0].message_id.as_deref(),
+            Some("b7bc2f4a-e38e-4336-af7d-e6c392c2f817")
+        );
+        assert_eq!(response.failed.len(), 1);
+        assert_eq!(
+            response.failed[0].recipient.as_ref(),
+            recipients[1].as_ref()
+        );
+        assert_eq!(response.failed[0].error_code, 406);
+    }
+
+    #[tokio::test]
+    async fn send_batch_splits_messages_beyond_the_batch_size() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = EmailClient::new(
+            Arc::new(postmark_transport(mock_server.uri())),
+            email(),
+            RetryPolicy::none(),
+            RateLimit {
+                messages_per_second: 10_000,
+                burst: 1_000,
+            },
+        );
+        let recipients: Vec<_> = (0..MAX_BATCH_SIZE + 1).map(|_| email()).collect();
+        let (subject, content) = (subject(), content());
+        let messages = batch_messages(&recipients, &subject, &content);
+
+        Mock::given(path("/email/batch"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(2)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.send_batch(&messages).await;
+
+        // Assert
+        let response = assert_ok!(outcome);
+        assert_eq!(response.succeeded.len(), MAX_BATCH_SIZE + 1);
+    }
+
+    #[tokio::test]
+    async fn send_batch_defers_messages_if_a_later_request_fails() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = EmailClient::new(
+            Arc::new(postmark_transport(mock_server.uri())),
+            email(),
+            RetryPolicy::none(),
+            RateLimit {
+                messages_per_second: 10_000,
+                burst: 1_000,
+            },
+        );
+        let recipients: Vec<_> = (0..MAX_BATCH_SIZE + 1).map(|_| email()).collect();
+        let (subject, content) = (subject(), content());
+        let messages = batch_messages(&recipients, &subject, &content);
+
+        Mock::given(path("/email/batch"))
+            .respond_with(ResponseTemplate::new(200))
+            .up_to_n_times(1)
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(path("/email/batch"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.send_batch(&messages).await;
+
+        // Assert
+        let response = assert_ok!(outcome);
+        assert_eq!(response.succeeded.len(), MAX_BATCH_SIZE);
+        assert_eq!(response.deferred.len(), 1);
+        assert_eq!(
+            response.deferred[0].recipient.as_ref(),
+            recipients[MAX_BATCH_SIZE].as_ref()
+        );
+    }
+
+    #[test]
+    fn batches_are_split_when_the_payload_exceeds_the_size_limit() {
+        // Arrange
+        let recipients = [email(), email(), email()];
+        let (subject, content) = (subject(), content());
+        let messages = batch_messages(&recipients, &subject, &content);
+        let messages: Vec<_> = messages.iter().collect();
+        let size = messages[0].size();
+
+        // Act
+        let chunks = batch_chunks(&messages[..2], MAX_BATCH_SIZE, 2 * size - 1);
+        let fitting = batch_chunks(&messages, MAX_BATCH_SIZE, 2 * size);
+
+        // Assert
+        let lengths = |chunks: Vec<&[&EmailMessage<'_>]>| -> Vec<usize> {
+            chunks.iter().map(|chunk| chunk.len()).collect()
+        };
+        assert_eq!(lengths(chunks), vec![1, 1]);
+        assert_eq!(lengths(fitting), vec![2, 1]);
+    }
+
+    #[tokio::test]
+    async fn send_batch_includes_attachments() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let recipients = vec![email()];
+        let (subject, content) = (subject(), content());
+        let attachments = vec![EmailAttachment {
+            name: "logo.png".into(),
+            content_type: "image/png".into(),
+            content: "iVBORw0KGgo=".into(),
+            content_id: Some("cid:logo".into()),
+        }];
+        let mut messages = batch_messages(&recipients, &subject, &content);
+        messages[0].attachments = &attachments;
+
+        Mock::given(path("/email/batch"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.send_batch(&messages).await;
+
+        // Assert
+        assert_ok!(outcome);
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(
+            body[0]["Attachments"],
+            serde_json::json!([{
+                "Name": "logo.png",
+                "ContentType": "image/png",
+                "Content": "iVBORw0KGgo=",
+                "ContentID": "cid:logo"
+            }])
+        );
+    }
+
+    #[tokio::test]
+    async fn send_batch_does_not_send_messages_exceeding_the_size_limit() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let recipients = vec![email(), email()];
+        let (subject, content) = (subject(), content());
+        let attachments = vec![EmailAttachment {
+            name: "large.pdf".into(),
+            content_type: "application/pdf".into(),
+            content: "A".repeat(MAX_MESSAGE_SIZE),
+            content_id: None,
+        }];
+        let mut messages = batch_messages(&recipients, &subject, &content);
+        messages[1].attachments = &attachments;
+
+        Mock::given(path("/email/batch"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.send_batch(&messages).await;
+
+        // Assert
+        let response = assert_ok!(outcome);
+        assert_eq!(response.succeeded.len(), 1);
+        assert_eq!(response.failed.len(), 1);
+        assert_eq!(
+            response.failed[0].recipient.as_ref(),
+            recipients[1].as_ref()
+        );
+        assert_eq!(response.failed[0].error_code, MESSAGE_TOO_LARGE_ERROR_CODE);
+
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let body: Vec<serde_json::Value> = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(body.len(), 1);
+        assert!(body[0].get("Attachments").is_none());
+    }
+
+    #[tokio::test]
+    async fn send_batch_adds_one_click_unsubscribe_headers() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let recipients = vec![email(), email()];
+        let (subject, content) = (subject(), content());
+        let mut messages = batch_messages(&recipients, &subject, &content);
+        messages[0].unsubscribe_url = Some("https://example.com/unsubscribe?tag=a");
+
+        Mock::given(path("/email/batch"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.send_batch(&messages).await;
+
+        // Assert
+        assert_ok!(outcome);
+        let request = &mock_server.received_requests().await.unwrap()[0];
+        let body: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+        assert_eq!(
+            body[0]["Headers"],
+            serde_json::json!([
+                {
+                    "Name": "List-Unsubscribe",
+                    "Value": "<https://example.com/unsubscribe?tag=a>"
+                },
+                {
+                    "Name": "List-Unsubscribe-Post",
+                    "Value": "List-Unsubscribe=One-Click"
+                }
+            ])
+        );
+        assert!(body[1].get("Headers").is_none());
+    }
+
+    #[tokio::test]
+    async fn send_batch_fails_if_the_server_returns_500() {
+        // Arrange
+        let mock_server = MockServer::start().await;
+        let email_client = email_client(mock_server.uri());
+        let recipients = vec![email()];
+        let (subject, content) = (subject(), content());
+        let messages = batch_messages(&recipients, &subject, &content);
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        // Act
+        let outcome = email_client.send_batch(&messages).await;
+
+        // Assert
+        assert_err!(outcome);
+    }
+
+    fn batch_messages<'a>(
+        recipients: &'a [SubscriberEmail],
+        subject: &'a str,
+        content: &'a str,
+    ) -> Vec<EmailMessage<'a>> {
+        recipients
+            .iter()
+            .map(|recipient| EmailMessage {
+                recipient,
+                subject,
+                html_content: content,
+                text_content: content,
+                unsubscribe_url: None,
+                attachments: &[],
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,192 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+use reqwest::{header::RETRY_AFTER, Client, Response, StatusCode};
+use secrecy::{ExposeSecret, Secret};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+
+use super::{
+    BatchEmailResult, EmailAttachment, EmailMessage, EmailTransport, SendEmailResponse,
+    TransportError,
+};
+use crate::domain::SubscriberEmail;
+
+/// Postmark の JSON API でメールを送信する
+pub struct PostmarkTransport {
+    http_client: Client,
+    base_url: String,
+    authorization_token: Secret<String>,
+}
+
+impl PostmarkTransport {
+    pub fn new(
+        base_url: String,
+        authorization_token: Secret<String>,
+        timeout: std::time::Duration,
+    ) -> Self {
+        let http_client = Client::builder().timeout(timeout).build().unwrap();
+
+        Self {
+            http_client,
+            base_url,
+            authorization_token,
+        }
+    }
+
+    async fn post<B, R>(&self, url: &str, request_body: &B) -> Result<R, TransportError>
+    where
+        B: Serialize + ?Sized,
+        R: DeserializeOwned + Default,
+    {
+        let response = self
+            .http_client
+            .post(url)
+            .header(
+                "X-Postmark-Server-Token",
+                self.authorization_token.expose_secret(),
+            )
+            .json(request_body)
+            .send()
+            .await
+            .map_err(transport_error)?;
+
+        let retry_after = retry_after(&response);
+        // ステータスコードに応じたレスポンスに変換
+        let response = response
+            .error_for_status()
+            .map_err(|e| transport_error(e).with_retry_after(retry_after))?;
+
+        // 送信自体は成功しているため、レスポンスボディが解釈できなくてもエラーにはしない
+        let body = response.bytes().await.map_err(transport_error)?;
+        let response = serde_json::from_slice(&body).unwrap_or_else(|e| {
+            tracing::warn!(
+                error.message = %e,
+                "Failed to parse the response body from the email server"
+            );
+            R::default()
+        });
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl EmailTransport for PostmarkTransport {
+    async fn send_email(
+        &self,
+        sender: &SubscriberEmail,
+        message: &EmailMessage<'_>,
+    ) -> Result<SendEmailResponse, TransportError> {
+        let url = format!("{}/email", self.base_url);
+        let request_body = SendEmailRequest::new(sender, message);
+
+        self.post(&url, &request_body).await
+    }
+
+    async fn send_batch(
+        &self,
+        sender: &SubscriberEmail,
+        messages: &[&EmailMessage<'_>],
+    ) -> Result<Vec<BatchEmailResult>, TransportError> {
+        let url = format!("{}/email/batch", self.base_url);
+        let request_body: Vec<_> = messages
+            .iter()
+            .map(|message| SendEmailRequest::new(sender, message))
+            .collect();
+
+        let results: Vec<PostmarkBatchResult> = self.post(&url, &request_body).await?;
+
+        Ok(results
+            .into_iter()
+            .map(|result| match result.error_code {
+                0 => BatchEmailResult::Accepted {
+                    message_id: result.message_id,
+                },
+                error_code => BatchEmailResult::Rejected {
+                    error_code,
+                    message: result.message,
+                },
+            })
+            .collect())
+    }
+}
+
+/// 接続エラー、タイムアウト、429、5xx のみを再試行の対象とする
+fn transport_error(error: reqwest::Error) -> TransportError {
+    let is_transient = error.is_connect()
+        || error.is_timeout()
+        || match error.status() {
+            Some(status) => status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error(),
+            None => false,
+        };
+
+    if is_transient {
+        TransportError::transient(error)
+    } else {
+        TransportError::permanent(error)
+    }
+}
+
+/// Retry-After ヘッダーの値（秒数またはHTTP日付）を待ち時間に変換する
+fn retry_after(response: &Response) -> Option<Duration> {
+    let value = response.headers().get(RETRY_AFTER)?.to_str().ok()?;
+
+    if let Ok(seconds) = value.trim().parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+
+    let retry_at = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    (retry_at.with_timezone(&chrono::Utc) - chrono::Utc::now())
+        .to_std()
+        .ok()
+        .or(Some(Duration::ZERO))
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct PostmarkBatchResult {
+    error_code: i64,
+    #[serde(default)]
+    message: String,
+    #[serde(rename = "MessageID")]
+    message_id: Option<String>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct SendEmailRequest<'a> {
+    from: &'a str,
+    to: &'a str,
+    subject: &'a str,
+    html_body: &'a str,
+    text_body: &'a str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    headers: Vec<EmailHeader>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    attachments: &'a [EmailAttachment],
+}
+
+impl<'a> SendEmailRequest<'a> {
+    fn new(sender: &'a SubscriberEmail, message: &'a EmailMessage<'_>) -> Self {
+        Self {
+            from: sender.as_ref(),
+            to: message.recipient.as_ref(),
+            subject: message.subject,
+            html_body: message.html_content,
+            text_body: message.text_content,
+            headers: message
+                .headers()
+                .into_iter()
+                .map(|(name, value)| EmailHeader { name, value })
+                .collect(),
+            attachments: message.attachments,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "PascalCase")]
+struct EmailHeader {
+    name: &'static str,
+    value: String,
+}
@@ -0,0 +1,714 @@
+use async_trait::async_trait;
+use base64::Engine;
+use lettre::{
+    message::{header::ContentType, Attachment, Mailbox, MultiPart},
+    transport::smtp::{
+        authentication::{Credentials, Mechanism},
+        client::{Tls, TlsParameters},
+        PoolConfig,
+    },
+    AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use secrecy::{ExposeSecret, Secret};
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+use super::{
+    BatchEmailResult, EmailAttachment, EmailMessage, EmailTransport, SendEmailResponse,
+    TransportError,
+};
+use crate::domain::SubscriberEmail;
+
+/// SMTP リレーへの接続設定
+#[derive(Deserialize, Clone, Debug)]
+pub struct SmtpSettings {
+    pub host: String,
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub port: u16,
+    pub tls: SmtpTls,
+    /// 指定しない場合は認証を行わない
+    pub username: Option<String>,
+    pub password: Option<Secret<String>>,
+    /// 指定しない場合は PLAIN、LOGIN の順にサーバーが対応している方式を利用する
+    pub auth_mechanism: Option<SmtpAuthMechanism>,
+    /// 再利用するために保持しておく接続の最大数
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub pool_max_size: u32,
+}
+
+/// SMTP リレーとの通信の暗号化方式
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// 暗号化しない (ローカルのリレー向け)
+    None,
+    /// 平文で接続した後に STARTTLS で暗号化する。暗号化できない場合は送信しない
+    StartTls,
+    /// 接続時から TLS で暗号化する
+    Tls,
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpAuthMechanism {
+    Plain,
+    Login,
+}
+
+impl From<SmtpAuthMechanism> for Mechanism {
+    fn from(mechanism: SmtpAuthMechanism) -> Self {
+        match mechanism {
+            SmtpAuthMechanism::Plain => Mechanism::Plain,
+            SmtpAuthMechanism::Login => Mechanism::Login,
+        }
+    }
+}
+
+/// SMTP リレーを経由してメールを送信する
+///
+/// 接続はプールで保持し、送信ごとに再利用する。
+pub struct SmtpTransport {
+    mailer: AsyncSmtpTransport<Tokio1Executor>,
+}
+
+impl SmtpTransport {
+    pub fn new(
+        settings: SmtpSettings,
+        timeout: std::time::Duration,
+    ) -> Result<Self, lettre::transport::smtp::Error> {
+        let builder = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&settings.host);
+        let builder = match settings.tls {
+            SmtpTls::None => builder.tls(Tls::None),
+            SmtpTls::StartTls => {
+                builder.tls(Tls::Required(TlsParameters::new(settings.host.clone())?))
+            }
+            SmtpTls::Tls => builder.tls(Tls::Wrapper(TlsParameters::new(settings.host.clone())?)),
+        };
+
+        let mut builder = builder
+            .port(settings.port)
+            .timeout(Some(timeout))
+            .pool_config(PoolConfig::new().max_size(settings.pool_max_size.max(1)));
+
+        if let (Some(username), Some(password)) = (settings.username, settings.password) {
+            let mechanisms = match settings.auth_mechanism {
+                Some(mechanism) => vec![mechanism.into()],
+                None => vec![Mechanism::Plain, Mechanism::Login],
+            };
+            builder = builder
+                .credentials(Credentials::new(
+                    username,
+                    password.expose_secret().to_owned(),
+                ))
+                .authentication(mechanisms);
+        }
+
+        Ok(Self {
+            mailer: builder.build(),
+        })
+    }
+
+    /// メールを送信し、送信したメールの Message-ID を返却する
+    async fn send(
+        &self,
+        sender: &SubscriberEmail,
+        message: &EmailMessage<'_>,
+    ) -> Result<Option<String>, SmtpSendError> {
+        let email = build_message(sender, message).map_err(SmtpSendError::Build)?;
+        let message_id = email
+            .headers()
+            .get_raw("Message-ID")
+            .map(|id| id.trim_matches(|c| c == '<' || c == '>').to_owned());
+
+        // lettre は任意の名前のヘッダーを組み立てられないため、先頭に追加して送信する
+        let mut raw = Vec::new();
+        for (name, value) in message.headers() {
+            raw.extend_from_slice(format!("{}: {}\r\n", name, value).as_bytes());
+        }
+        raw.extend_from_slice(&email.formatted());
+
+        self.mailer
+            .send_raw(email.envelope(), &raw)
+            .await
+            .map_err(SmtpSendError::Smtp)?;
+
+        Ok(message_id)
+    }
+}
+
+#[async_trait]
+impl EmailTransport for SmtpTransport {
+    async fn send_email(
+        &self,
+        sender: &SubscriberEmail,
+        message: &EmailMessage<'_>,
+    ) -> Result<SendEmailResponse, TransportError> {
+        let message_id = self
+            .send(sender, message)
+            .await
+            .map_err(TransportError::from)?;
+
+        Ok(SendEmailResponse { message_id })
+    }
+
+    /// SMTP にはバッチ送信がないため、プールした接続で1通ずつ送信する
+    ///
+    /// 送信済みのメールが再送されないように、エラーを返却するのは1通も送信していない場合のみとする。
+    /// 宛先ごとの一時的な拒否 (4xx) はそのメールのみ、接続エラーなどの場合は残りのメールも後で再送する。
+    async fn send_batch(
+        &self,
+        sender: &SubscriberEmail,
+        messages: &[&EmailMessage<'_>],
+    ) -> Result<Vec<BatchEmailResult>, TransportError> {
+        let mut results = Vec::with_capacity(messages.len());
+        let mut remaining = messages.iter();
+
+        while let Some(message) = remaining.next() {
+            let result = match self.send(sender, message).await {
+                Ok(message_id) => BatchEmailResult::Accepted { message_id },
+                // 宛先が存在しないなどの恒久的なエラーは宛先ごとの失敗として扱う
+                Err(SmtpSendError::Smtp(e)) if e.is_permanent() => BatchEmailResult::Rejected {
+                    error_code: reply_code(&e).unwrap_or_default(),
+                    message: e.to_string(),
+                },
+                Err(SmtpSendError::Smtp(e)) if e.is_transient() => BatchEmailResult::Deferred {
+                    error: TransportError::transient(e),
+                },
+                Err(e) => {
+                    let e = TransportError::from(e);
+                    let nothing_sent = !results
+                        .iter()
+                        .any(|result| matches!(result, BatchEmailResult::Accepted { .. }));
+                    if nothing_sent {
+                        return Err(e);
+                    }
+
+                    if e.is_transient() {
+                        results.push(BatchEmailResult::Deferred { error: e });
+                        results.extend(remaining.map(|_| BatchEmailResult::Deferred {
+                            error: TransportError::transient(
+                                "Not sent because of an earlier error in the batch",
+                            ),
+                        }));
+                        break;
+                    }
+
+                    BatchEmailResult::Rejected {
+                        error_code: 0,
+                        message: e.to_string(),
+                    }
+                }
+            };
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+}
+
+#[derive(thiserror::Error, Debug)]
+enum SmtpSendError {
+    #[error("Failed to build an email message: {0}")]
+    Build(String),
+    #[error(transparent)]
+    Smtp(lettre::transport::smtp::Error),
+}
+
+impl From<SmtpSendError> for TransportError {
+    /// 4xx の応答、接続エラー、タイムアウトのみを再試行の対象とする
+    fn from(e: SmtpSendError) -> Self {
+        match e {
+            SmtpSendError::Smtp(e) if !(e.is_permanent() || e.is_client() || e.is_tls()) => {
+                TransportError::transient(e)
+            }
+            e => TransportError::permanent(e),
+        }
+    }
+}
+
+/// SMTP の応答コード (例: 550)
+fn reply_code(e: &lettre::transport::smtp::Error) -> Option<i64> {
+    e.status()?.to_string().parse().ok()
+}
+
+/// テキストと HTML の本文を multipart/alternative にまとめたメールを組み立てる
+///
+/// インライン画像は HTML の本文と multipart/related に、
+/// それ以外の添付ファイルは全体を multipart/mixed にまとめる。
+fn build_message(sender: &SubscriberEmail, message: &EmailMessage<'_>) -> Result<Message, String> {
+    let from = sender
+        .as_ref()
+        .parse::<Mailbox>()
+        .map_err(|e| e.to_string())?;
+    let to = message
+        .recipient
+        .as_ref()
+        .parse::<Mailbox>()
+        .map_err(|e| e.to_string())?;
+
+    let mut body = MultiPart::alternative_plain_html(
+        message.text_content.to_owned(),
+        message.html_content.to_owned(),
+    );
+
+    let (inline, attached): (Vec<_>, Vec<_>) = message
+        .attachments
+        .iter()
+        .partition(|attachment| attachment.content_id.is_some());
+
+    if !inline.is_empty() {
+        let mut related = MultiPart::related().multipart(body);
+        for attachment in inline {
+            let content_id = attachment
+                .content_id
+                .as_deref()
+                .unwrap_or_default()
+                .trim_start_matches("cid:");
+            let (content, content_type) = decode_attachment(attachment)?;
+            related = related.singlepart(
+                Attachment::new_inline(content_id.to_owned()).body(content, content_type),
+            );
+        }
+        body = related;
+    }
+
+    if !attached.is_empty() {
+        let mut mixed = MultiPart::mixed().multipart(body);
+        for attachment in attached {
+            let (content, content_type) = decode_attachment(attachment)?;
+            mixed = mixed
+                .singlepart(Attachment::new(attachment.name.clone()).body(content, content_type));
+        }
+        body = mixed;
+    }
+
+    Message::builder()
+        .from(from)
+        .to(to)
+        .subject(message.subject)
+        .message_id(None)
+        .multipart(body)
+        .map_err(|e| e.to_string())
+}
+
+fn decode_attachment(attachment: &EmailAttachment) -> Result<(Vec<u8>, ContentType), String> {
+    let content = base64::engine::general_purpose::STANDARD
+        .decode(&attachment.content)
+        .map_err(|e| e.to_string())?;
+    let content_type = ContentType::parse(&attachment.content_type)
+        .map_err(|e| format!("{}: {}", attachment.content_type, e))?;
+
+    Ok((content, content_type))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        net::SocketAddr,
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use claims::{assert_err, assert_ok};
+    use tokio::{
+        io::{AsyncBufReadExt, AsyncWriteExt, BufReader},
+        net::TcpListener,
+    };
+
+    use super::{SmtpAuthMechanism, SmtpSettings, SmtpTls, SmtpTransport};
+    use crate::{
+        domain::SubscriberEmail,
+        email_client::{
+            BatchEmailResult, EmailAttachment, EmailClient, EmailMessage, EmailTransport,
+            RetryPolicy,
+        },
+        rate_limiter::RateLimit,
+    };
+
+    /// テスト用に受信したメールを記録するだけの SMTP サーバー
+    ///
+    /// `rejected` で始まる宛先は 550、`deferred` で始まる宛先は 451 で拒否する。
+    #[derive(Clone, Default)]
+    struct SmtpStandIn {
+        connections: Arc<Mutex<usize>>,
+        auth_mechanisms: Arc<Mutex<Vec<String>>>,
+        received: Arc<Mutex<Vec<ReceivedEmail>>>,
+    }
+
+    #[derive(Clone, Debug)]
+    struct ReceivedEmail {
+        recipients: Vec<String>,
+        data: String,
+    }
+
+    impl SmtpStandIn {
+        async fn start() -> (Self, SocketAddr) {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let address = listener.local_addr().unwrap();
+            let stand_in = Self::default();
+
+            let server = stand_in.clone();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    *server.connections.lock().unwrap() += 1;
+                    let server = server.clone();
+                    tokio::spawn(async move {
+                        let _ = server.handle(stream).await;
+                    });
+                }
+            });
+
+            (stand_in, address)
+        }
+
+        async fn handle(&self, stream: tokio::net::TcpStream) -> std::io::Result<()> {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = BufReader::new(reader).lines();
+            let mut recipients = Vec::new();
+
+            writer.write_all(b"220 stand-in ESMTP\r\n").await?;
+            while let Some(line) = lines.next_line().await? {
+                let command = line.to_uppercase();
+                let reply = if command.starts_with("EHLO") || command.starts_with("HELO") {
+                    "250-stand-in\r\n250 AUTH PLAIN LOGIN\r\n".to_owned()
+                } else if command.starts_with("AUTH PLAIN") {
+                    self.auth_mechanisms.lock().unwrap().push("PLAIN".into());
+                    "235 2.7.0 Authentication successful\r\n".to_owned()
+                } else if command.starts_with("AUTH LOGIN") {
+                    self.auth_mechanisms.lock().unwrap().push("LOGIN".into());
+                    writer.write_all(b"334 VXNlcm5hbWU6\r\n").await?;
+                    lines.next_line().await?;
+                    writer.write_all(b"334 UGFzc3dvcmQ6\r\n").await?;
+                    lines.next_line().await?;
+                    "235 2.7.0 Authentication successful\r\n".to_owned()
+                } else if command.starts_with("RCPT TO") {
+                    if command.contains("<REJECTED") {
+                        "550 5.1.1 No such user\r\n".to_owned()
+                    } else if command.contains("<DEFERRED") {
+                        "451 4.3.0 Try again later\r\n".to_owned()
+                    } else {
+                        recipients
+                            .push(line[9..].trim_matches(|c| c == '<' || c == '>').to_owned());
+                        "250 2.1.5 OK\r\n".to_owned()
+                    }
+                } else if command == "DATA" {
+                    writer
+                        .write_all(b"354 End data with <CR><LF>.<CR><LF>\r\n")
+                        .await?;
+                    let mut data = String::new();
+                    while let Some(line) = lines.next_line().await? {
+                        if line == "." {
+                            break;
+                        }
+                        data.push_str(&line);
+                        data.push_str("\r\n");
+                    }
+                    self.received.lock().unwrap().push(ReceivedEmail {
+                        recipients: std::mem::take(&mut recipients),
+                        data,
+                    });
+                    "250 2.0.0 OK\r\n".to_owned()
+                } else if command == "QUIT" {
+                    writer.write_all(b"221 2.0.0 Bye\r\n").await?;
+                    return Ok(());
+                } else {
+                    if command == "RSET" {
+                        recipients.clear();
+                    }
+                    "250 2.0.0 OK\r\n".to_owned()
+                };
+                writer.write_all(reply.as_bytes()).await?;
+            }
+
+            Ok(())
+        }
+
+        fn received(&self) -> Vec<ReceivedEmail> {
+            self.received.lock().unwrap().clone()
+        }
+    }
+
+    fn settings(address: SocketAddr) -> SmtpSettings {
+        SmtpSettings {
+            host: address.ip().to_string(),
+            port: address.port(),
+            tls: SmtpTls::None,
+            username: None,
+            password: None,
+            auth_mechanism: None,
+            pool_max_size: 2,
+        }
+    }
+
+    fn transport(settings: SmtpSettings) -> SmtpTransport {
+        SmtpTransport::new(settings, Duration::from_secs(2)).unwrap()
+    }
+
+    fn email(address: &str) -> SubscriberEmail {
+        SubscriberEmail::parse(address.into()).unwrap()
+    }
+
+    fn message(recipient: &SubscriberEmail) -> EmailMessage<'_> {
+        EmailMessage {
+            recipient,
+            subject: "Newsletter title",
+            html_content: "<p>Newsletter body as HTML</p>",
+            text_content: "Newsletter body as plain text",
+            unsubscribe_url: None,
+            attachments: &[],
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_sends_a_multipart_alternative_message() {
+        // Arrange
+        let (stand_in, address) = SmtpStandIn::start().await;
+        let transport = transport(settings(address));
+        let recipient = email("ursula@example.com");
+
+        // Act
+        let outcome = transport
+            .send_email(&email("sender@example.com"), &message(&recipient))
+            .await;
+
+        // Assert
+        let response = assert_ok!(outcome);
+        assert!(response.message_id.is_some());
+
+        let received = stand_in.received();
+        assert_eq!(received.len(), 1);
+        assert_eq!(received[0].recipients, vec!["ursula@example.com"]);
+        let data = &received[0].data;
+        assert!(data.contains("Subject: Newsletter title"));
+        assert!(data.contains("multipart/alternative"));
+        assert!(data.contains("Content-Type: text/plain; charset=utf-8"));
+        assert!(data.contains("Newsletter body as plain text"));
+        assert!(data.contains("Content-Type: text/html; charset=utf-8"));
+        assert!(data.contains("<p>Newsletter body as HTML</p>"));
+    }
+
+    #[tokio::test]
+    async fn send_email_authenticates_with_the_configured_mechanism() {
+        for (auth_mechanism, expected) in [
+            (None, "PLAIN"),
+            (Some(SmtpAuthMechanism::Plain), "PLAIN"),
+            (Some(SmtpAuthMechanism::Login), "LOGIN"),
+        ] {
+            // Arrange
+            let (stand_in, address) = SmtpStandIn::start().await;
+            let transport = transport(SmtpSettings {
+                username: Some("relay-user".into()),
+                password: Some("relay-password".to_owned().into()),
+                auth_mechanism,
+                ..settings(address)
+            });
+            let recipient = email("ursula@example.com");
+
+            // Act
+            let outcome = transport
+                .send_email(&email("sender@example.com"), &message(&recipient))
+                .await;
+
+            // Assert
+            assert_ok!(outcome);
+            let auth_mechanisms = stand_in.auth_mechanisms.lock().unwrap();
+            assert!(!auth_mechanisms.is_empty());
+            assert!(auth_mechanisms
+                .iter()
+                .all(|mechanism| mechanism == expected));
+        }
+    }
+
+    #[tokio::test]
+    async fn send_email_adds_headers_and_attachments() {
+        // Arrange
+        let (stand_in, address) = SmtpStandIn::start().await;
+        let transport = transport(settings(address));
+        let recipient = email("ursula@example.com");
+        let attachments = vec![
+            EmailAttachment {
+                name: "agenda.pdf".into(),
+                content_type: "application/pdf".into(),
+                content: "JVBERi0xLjQK".into(),
+                content_id: None,
+            },
+            EmailAttachment {
+                name: "logo.png".into(),
+                content_type: "image/png".into(),
+                content: "iVBORw0KGgo=".into(),
+                content_id: Some("cid:logo".into()),
+            },
+        ];
+        let message = EmailMessage {
+            unsubscribe_url: Some("https://example.com/unsubscribe?token=abc"),
+            attachments: &attachments,
+            ..message(&recipient)
+        };
+
+        // Act
+        let outcome = transport
+            .send_email(&email("sender@example.com"), &message)
+            .await;
+
+        // Assert
+        assert_ok!(outcome);
+        let data = &stand_in.received()[0].data;
+        assert!(data.contains("List-Unsubscribe: <https://example.com/unsubscribe?token=abc>"));
+        assert!(data.contains("List-Unsubscribe-Post: List-Unsubscribe=One-Click"));
+        assert!(data.contains("multipart/mixed"));
+        assert!(data.contains("multipart/related"));
+        assert!(data.contains("Content-ID: <logo>"));
+        assert!(data.contains("filename=\"agenda.pdf\""));
+        assert!(data.contains("Content-Type: application/pdf"));
+    }
+
+    #[tokio::test]
+    async fn send_batch_reuses_pooled_connections() {
+        // Arrange
+        let (stand_in, address) = SmtpStandIn::start().await;
+        let transport = transport(settings(address));
+        let recipients = [
+            email("ursula@example.com"),
+            email("ulysses@example.com"),
+            email("uma@example.com"),
+        ];
+        let messages: Vec<_> = recipients.iter().map(message).collect();
+        let messages: Vec<_> = messages.iter().collect();
+
+        // Act
+        let outcome = transport
+            .send_batch(&email("sender@example.com"), &messages)
+            .await;
+
+        // Assert
+        let results = assert_ok!(outcome);
+        assert_eq!(results.len(), 3);
+        assert_eq!(stand_in.received().len(), 3);
+        // プールは起動時に待機用の接続を1つ作成するため、送信数より少なければ再利用されている
+        assert!(*stand_in.connections.lock().unwrap() < 3);
+    }
+
+    #[tokio::test]
+    async fn send_batch_reports_permanently_rejected_recipients() {
+        // Arrange
+        let (stand_in, address) = SmtpStandIn::start().await;
+        let transport = transport(settings(address));
+        let recipients = [email("rejected@example.com"), email("ursula@example.com")];
+        let messages: Vec<_> = recipients.iter().map(message).collect();
+        let messages: Vec<_> = messages.iter().collect();
+
+        // Act
+        let outcome = transport
+            .send_batch(&email("sender@example.com"), &messages)
+            .await;
+
+        // Assert
+        let results = assert_ok!(outcome);
+        assert!(matches!(
+            results[0],
+            BatchEmailResult::Rejected {
+                error_code: 550,
+                ..
+            }
+        ));
+        assert!(matches!(results[1], BatchEmailResult::Accepted { .. }));
+        assert_eq!(stand_in.received().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn send_batch_defers_only_the_recipients_the_server_defers() {
+        // Arrange
+        let (stand_in, address) = SmtpStandIn::start().await;
+        let transport = transport(settings(address));
+        let recipients = [
+            email("ursula@example.com"),
+            email("deferred@example.com"),
+            email("ulysses@example.com"),
+        ];
+        let messages: Vec<_> = recipients.iter().map(message).collect();
+        let messages: Vec<_> = messages.iter().collect();
+
+        // Act
+        let outcome = transport
+            .send_batch(&email("sender@example.com"), &messages)
+            .await;
+
+        // Assert
+        let results = assert_ok!(outcome);
+        assert!(matches!(results[0], BatchEmailResult::Accepted { .. }));
+        assert!(matches!(
+            &results[1],
+            BatchEmailResult::Deferred { error } if error.is_transient()
+        ));
+        assert!(matches!(results[2], BatchEmailResult::Accepted { .. }));
+        assert_eq!(stand_in.received().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn deferred_recipients_do_not_cause_the_batch_to_be_sent_again() {
+        // Arrange
+        let (stand_in, address) = SmtpStandIn::start().await;
+        let email_client = EmailClient::new(
+            Arc::new(transport(settings(address))),
+            email("sender@example.com"),
+            RetryPolicy {
+                max_attempts: 3,
+                base_delay_milliseconds: 10,
+                max_delay_milliseconds: 100,
+                jitter: false,
+            },
+            RateLimit {
+                messages_per_second: 100,
+                burst: 100,
+            },
+        );
+        let recipients = [
+            email("ursula@example.com"),
+            email("deferred@example.com"),
+            email("ulysses@example.com"),
+        ];
+        let messages: Vec<_> = recipients.iter().map(message).collect();
+
+        // Act
+        let outcome = email_client.send_batch(&messages).await;
+
+        // Assert
+        let response = assert_ok!(outcome);
+        assert_eq!(response.succeeded.len(), 2);
+        assert_eq!(response.deferred.len(), 1);
+        assert_eq!(
+            response.deferred[0].recipient.as_ref(),
+            "deferred@example.com"
+        );
+
+        let received = stand_in.received();
+        let received_by = |address: &str| {
+            received
+                .iter()
+                .filter(|email| email.recipients.iter().any(|r| r == address))
+                .count()
+        };
+        assert_eq!(received_by("ursula@example.com"), 1);
+        assert_eq!(received_by("ulysses@example.com"), 1);
+    }
+
+    #[tokio::test]
+    async fn send_email_fails_with_a_transient_error_if_the_server_is_unreachable() {
+        // Arrange
+        let address = {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            listener.local_addr().unwrap()
+        };
+        let transport = transport(settings(address));
+        let recipient = email("ursula@example.com");
+
+        // Act
+        let outcome = transport
+            .send_email(&email("sender@example.com"), &message(&recipient))
+            .await;
+
+        // Assert
+        let e = assert_err!(outcome);
+        assert!(e.is_transient());
+    }
+}
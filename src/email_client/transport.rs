@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use super::{EmailMessage, SendEmailResponse};
+use crate::{domain::SubscriberEmail, error::error_chain_fmt};
+
+/// メール配信サービスへの送信方法
+///
+/// 実装は1回の送信の試行のみを行い、再試行と送信数の上限は `EmailClient` が制御する。
+#[async_trait]
+pub trait EmailTransport: Send + Sync {
+    /// 1通のメールを送信する
+    async fn send_email(
+        &self,
+        sender: &SubscriberEmail,
+        message: &EmailMessage<'_>,
+    ) -> Result<SendEmailResponse, TransportError>;
+
+    /// 複数のメールを送信し、宛先ごとの結果をメールと同じ順番で返却する
+    ///
+    /// 1回に渡されるメールは `MAX_BATCH_SIZE` 以下となる。
+    /// エラーを返却するとすべてのメールが再送されるため、一部のメールを送信した後のエラーは
+    /// `BatchEmailResult::Deferred` として返却すること。
+    async fn send_batch(
+        &self,
+        sender: &SubscriberEmail,
+        messages: &[&EmailMessage<'_>],
+    ) -> Result<Vec<BatchEmailResult>, TransportError>;
+}
+
+/// 複数のメールを送信した場合の1通ごとの結果
+#[derive(Debug)]
+pub enum BatchEmailResult {
+    Accepted {
+        message_id: Option<String>,
+    },
+    /// 宛先が配信停止中であるなど、再送しても成功しない理由で受け付けられなかった
+    Rejected {
+        error_code: i64,
+        message: String,
+    },
+    /// 一時的なエラーで送信できなかったため、このメールのみ後で再送する
+    Deferred {
+        error: TransportError,
+    },
+}
+
+/// 1回の送信の試行で発生したエラー
+pub struct TransportError {
+    error: Box<dyn std::error::Error + Send + Sync>,
+    transient: bool,
+    /// 配信サービスから指定された再試行までの待ち時間
+    retry_after: Option<Duration>,
+}
+
+impl TransportError {
+    /// 接続エラーやタイムアウトなど、再試行すると成功する可能性のあるエラー
+    pub fn transient(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self {
+            error: error.into(),
+            transient: true,
+            retry_after: None,
+        }
+    }
+
+    /// 認証情報の誤りや不正なリクエストなど、再試行しても成功しないエラー
+    pub fn permanent(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> Self {
+        Self {
+            error: error.into(),
+            transient: false,
+            retry_after: None,
+        }
+    }
+
+    pub fn with_retry_after(mut self, retry_after: Option<Duration>) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
+
+    pub fn is_transient(&self) -> bool {
+        self.transient
+    }
+
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
+}
+
+impl std::fmt::Display for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.error.fmt(f)
+    }
+}
+
+impl std::fmt::Debug for TransportError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl std::error::Error for TransportError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.error.source()
+    }
+}
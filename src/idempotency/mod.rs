@@ -0,0 +1,7 @@
+mod key;
+mod persistence;
+
+pub use key::IdempotencyKey;
+pub use persistence::{
+    run_expiration_worker_until_stopped, save_response, try_processing, NextAction,
+};
@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use anyhow::Context;
+use axum::{
+    body::{boxed, Full},
+    http::StatusCode,
+    response::Response,
+};
+use chrono::Utc;
+use sqlx::{postgres::PgHasArrayType, PgPool, Postgres, Transaction};
+use uuid::Uuid;
+
+use super::IdempotencyKey;
+use crate::{configuration::Settings, startup::get_connection_pool};
+
+#[derive(Debug, sqlx::Type)]
+#[sqlx(type_name = "header_pair")]
+struct HeaderPairRecord {
+    name: String,
+    value: Vec<u8>,
+}
+
+impl PgHasArrayType for HeaderPairRecord {
+    fn array_type_info() -> sqlx::postgres::PgTypeInfo {
+        sqlx::postgres::PgTypeInfo::with_name("_header_pair")
+    }
+}
+
+// リクエストごとに1回だけ作成してすぐに分岐するため、トランザクションの大きさは問題にならない
+#[allow(clippy::large_enum_variant)]
+pub enum NextAction {
+    StartProcessing(Transaction<'static, Postgres>),
+    ReturnSavedResponse(Response),
+}
+
+/// 冪等性キーに対応する処理を開始するか、保存済みのレスポンスを返却するかを決定する
+///
+/// 同じキーで同時にリクエストされた場合、後続のリクエストは INSERT で
+/// 先行するトランザクションの完了を待機するため、処理を実行するのは1つのリクエストのみとなる。
+#[tracing::instrument(skip(pool, idempotency_key, retention))]
+pub async fn try_processing(
+    pool: &PgPool,
+    idempotency_key: &IdempotencyKey,
+    user_id: Uuid,
+    retention: chrono::Duration,
+) -> Result<NextAction, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+
+    // 保存期間を過ぎたキーは新しいリクエストとして扱う
+    sqlx::query!(
+        r#"
+        DELETE FROM idempotency
+        WHERE
+            user_id = $1 AND
+            idempotency_key = $2 AND
+            created_at < $3
+        "#,
+        user_id,
+        idempotency_key.as_ref(),
+        Utc::now() - retention
+    )
+    .execute(&mut transaction)
+    .await?;
+
+    let n_inserted_rows = sqlx::query!(
+        r#"
+        INSERT INTO idempotency (
+            user_id,
+            idempotency_key,
+            created_at
+        )
+        VALUES ($1, $2, now())
+        ON CONFLICT DO NOTHING
+        "#,
+        user_id,
+        idempotency_key.as_ref()
+    )
+    .execute(&mut transaction)
+    .await?
+    .rows_affected();
+
+    if n_inserted_rows > 0 {
+        Ok(NextAction::StartProcessing(transaction))
+    } else {
+        let saved_response = get_saved_response(pool, idempotency_key, user_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("We expected a saved response, we didn't find it"))?;
+
+        Ok(NextAction::ReturnSavedResponse(saved_response))
+    }
+}
+
+#[tracing::instrument(skip(pool, idempotency_key))]
+async fn get_saved_response(
+    pool: &PgPool,
+    idempotency_key: &IdempotencyKey,
+    user_id: Uuid,
+) -> Result<Option<Response>, anyhow::Error> {
+    let saved_response = sqlx::query!(
+        r#"
+        SELECT
+            response_status_code as "response_status_code!",
+            response_headers as "response_headers!: Vec<HeaderPairRecord>",
+            response_body as "response_body!"
+        FROM idempotency
+        WHERE
+            user_id = $1 AND
+            idempotency_key = $2
+        "#,
+        user_id,
+        idempotency_key.as_ref()
+    )
+    .fetch_optional(pool)
+    .await?;
+
+    if let Some(r) = saved_response {
+        let status_code = StatusCode::from_u16(r.response_status_code.try_into()?)?;
+
+        let mut response = Response::builder().status(status_code);
+        for HeaderPairRecord { name, value } in r.response_headers {
+            response = response.header(name, value);
+        }
+
+        Ok(Some(response.body(boxed(Full::from(r.response_body)))?))
+    } else {
+        Ok(None)
+    }
+}
+
+/// レスポンスを保存してトランザクションをコミットする
+///
+/// レスポンスのボディは保存のために一度読み出すため、同じ内容で組み立て直したレスポンスを返却する。
+#[tracing::instrument(skip(transaction, idempotency_key, response))]
+pub async fn save_response(
+    mut transaction: Transaction<'static, Postgres>,
+    idempotency_key: &IdempotencyKey,
+    user_id: Uuid,
+    response: Response,
+) -> Result<Response, anyhow::Error> {
+    let (response_head, body) = response.into_parts();
+    let body = hyper::body::to_bytes(body)
+        .await
+        .map_err(|e| anyhow::anyhow!("{}", e))
+        .context("Failed to read the response body")?;
+
+    let status_code = response_head.status.as_u16() as i16;
+    let headers = {
+        let mut h = Vec::with_capacity(response_head.headers.len());
+        for (name, value) in response_head.headers.iter() {
+            let name = name.as_str().to_owned();
+            let value = value.as_bytes().to_owned();
+            h.push(HeaderPairRecord { name, value });
+        }
+        h
+    };
+
+    // sqlx::query! では複合型の配列を推論できないため query_unchecked! を使用する
+    sqlx::query_unchecked!(
+        r#"
+        UPDATE idempotency
+        SET
+            response_status_code = $3,
+            response_headers = $4,
+            response_body = $5
+        WHERE
+            user_id = $1 AND
+            idempotency_key = $2
+        "#,
+        user_id,
+        idempotency_key.as_ref(),
+        status_code,
+        headers,
+        body.as_ref()
+    )
+    .execute(&mut transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(Response::from_parts(response_head, boxed(Full::from(body))))
+}
+
+/// 保存期間を過ぎた冪等性キーを定期的に削除する
+pub async fn run_expiration_worker_until_stopped(
+    configuration: Settings,
+) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    let retention = configuration.application.idempotency_retention();
+
+    loop {
+        if let Err(e) = delete_expired_keys(&connection_pool, retention).await {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "Failed to delete expired idempotency keys",
+            );
+        }
+
+        tokio::time::sleep(Duration::from_secs(60 * 60)).await;
+    }
+}
+
+#[tracing::instrument(skip(pool))]
+async fn delete_expired_keys(
+    pool: &PgPool,
+    retention: chrono::Duration,
+) -> Result<u64, anyhow::Error> {
+    let n_deleted_rows = sqlx::query!(
+        r#"
+        DELETE FROM idempotency
+        WHERE created_at < $1
+        "#,
+        Utc::now() - retention
+    )
+    .execute(pool)
+    .await?
+    .rows_affected();
+
+    Ok(n_deleted_rows)
+}
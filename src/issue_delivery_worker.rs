@@ -0,0 +1,794 @@
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use anyhow::Context;
+use chrono::Utc;
+use sqlx::{PgExecutor, PgPool, Postgres, QueryBuilder, Transaction};
+use tracing::{field::display, Span};
+use uuid::Uuid;
+
+use crate::{
+    configuration::Settings,
+    domain::{
+        render_html, render_text, rewrite_links, Segment, SubscriberEmail, TemplateVariables,
+    },
+    email_client::{EmailAttachment, EmailClient, EmailMessage, TransportError, MAX_BATCH_SIZE},
+    routes::unsubscribe_url,
+    startup::{get_connection_pool, ApplicationBaseUrl, HmacSecret},
+};
+
+/// 配信に失敗したタスクを破棄するまでの最大試行回数
+const MAX_DELIVERY_ATTEMPTS: i16 = 5;
+
+/// 送信数の上限による待機時間の集計をログに出力する間隔
+const RATE_LIMITER_METRICS_INTERVAL: Duration = Duration::from_secs(60);
+
+type PgTransaction = Transaction<'static, Postgres>;
+
+pub enum ExecutionOutcome {
+    TaskCompleted,
+    EmptyQueue,
+}
+
+pub async fn run_worker_until_stopped(
+    configuration: Settings,
+    email_client: EmailClient,
+) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+    let base_url = ApplicationBaseUrl(configuration.application.base_url);
+    let hmac_secret = HmacSecret(configuration.application.hmac_secret);
+
+    worker_loop(connection_pool, email_client, base_url, hmac_secret).await
+}
+
+async fn worker_loop(
+    pool: PgPool,
+    email_client: EmailClient,
+    base_url: ApplicationBaseUrl,
+    hmac_secret: HmacSecret,
+) -> Result<(), anyhow::Error> {
+    let mut last_metrics_report = Instant::now();
+    loop {
+        match try_execute_task(&pool, &email_client, &base_url, &hmac_secret).await {
+            Ok(ExecutionOutcome::EmptyQueue) => {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+        }
+
+        if last_metrics_report.elapsed() >= RATE_LIMITER_METRICS_INTERVAL {
+            report_rate_limiter_metrics(&email_client);
+            last_metrics_report = Instant::now();
+        }
+    }
+}
+
+/// レートリミッターは確認メールの送信と共有しているため、値はアプリケーション全体の累計になる
+fn report_rate_limiter_metrics(email_client: &EmailClient) {
+    let metrics = email_client.rate_limiter_metrics();
+    tracing::info!(
+        acquired = metrics.acquired,
+        throttled = metrics.throttled,
+        total_wait_milliseconds = metrics.total_wait.as_millis() as u64,
+        "Email rate limiter metrics"
+    );
+}
+
+/// キューから同じニュースレターのタスクをまとめて取り出し、バッチAPIで配信する
+///
+/// 本文のプレースホルダーには購読者ごとの値を埋め込む。
+///
+/// タスクの行ロックはメールの送信が完了してトランザクションをコミットするまで保持する。
+/// そのため複数のワーカーが同じタスクを同時に処理することはなく、
+/// 途中でプロセスが停止した場合はロックが解放されてタスクがキューに残る。
+#[tracing::instrument(
+    skip_all,
+    fields(
+        newsletter_issue_id=tracing::field::Empty,
+        n_tasks=tracing::field::Empty,
+    ),
+    err
+)]
+pub async fn try_execute_task(
+    pool: &PgPool,
+    email_client: &EmailClient,
+    base_url: &ApplicationBaseUrl,
+    hmac_secret: &HmacSecret,
+) -> Result<ExecutionOutcome, anyhow::Error> {
+    let Some((mut transaction, tasks)) = dequeue_tasks(pool).await? else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+
+    let newsletter_issue_id = tasks[0].newsletter_issue_id;
+    Span::current()
+        .record("newsletter_issue_id", display(newsletter_issue_id))
+        .record("n_tasks", tasks.len());
+
+    let mut recipients = Vec::with_capacity(tasks.len());
+    for task in &tasks {
+        // タスクを作成した後に配信を停止した購読者には送信しない
+        if task.subscriber_status != "confirmed" {
+            tracing::info!(
+                subscriber_id = %task.subscriber_id,
+                subscriber_status = %task.subscriber_status,
+                "Skipping a subscriber who is no longer confirmed",
+            );
+            let delivery = Delivery::skipped(
+                task.n_retries,
+                format!("The subscription is {}", task.subscriber_status),
+            );
+            record_delivery(&mut transaction, task, delivery).await?;
+            delete_task(&mut transaction, task).await?;
+            continue;
+        }
+        if task.is_suppressed {
+            tracing::info!(
+                subscriber_id = %task.subscriber_id,
+                "Skipping a subscriber whose address is suppressed",
+            );
+            let delivery = Delivery::skipped(task.n_retries, "The address is suppressed".into());
+            record_delivery(&mut transaction, task, delivery).await?;
+            delete_task(&mut transaction, task).await?;
+            continue;
+        }
+
+        match SubscriberEmail::parse(task.subscriber_email.clone()) {
+            Ok(email) => recipients.push((task, email)),
+            Err(e) => {
+                tracing::error!(
+                    error.cause_chain = ?e,
+                    error.message = %e,
+                    subscriber_email = %task.subscriber_email,
+                    "Skipping a confirmed subscriber. \
+                     Their stored contact details are invalid",
+                );
+                let delivery = Delivery::skipped(task.n_retries, e);
+                record_delivery(&mut transaction, task, delivery).await?;
+                delete_task(&mut transaction, task).await?;
+            }
+        }
+    }
+
+    if !recipients.is_empty() {
+        let issue = get_issue(pool, newsletter_issue_id).await?;
+        let attachments = get_issue_attachments(pool, newsletter_issue_id).await?;
+        let web_version_url = web_version_url(base_url, &issue.slug);
+        let contents: Vec<_> = recipients
+            .iter()
+            .map(|(task, email)| {
+                let unsubscribe_url = unsubscribe_url(base_url, hmac_secret, task.subscriber_id);
+                let variables = TemplateVariables {
+                    name: &task.subscriber_name,
+                    email: email.as_ref(),
+                    unsubscribe_url: &unsubscribe_url,
+                };
+                let (html_content, text_content) = render_email(
+                    &issue.html_content,
+                    &issue.text_content,
+                    &variables,
+                    &web_version_url,
+                );
+                let mut html_content = rewrite_links(&html_content, |url| {
+                    is_trackable_link(url, base_url).then(|| {
+                        click_tracking_url(
+                            base_url,
+                            hmac_secret,
+                            newsletter_issue_id,
+                            task.subscriber_id,
+                            url,
+                        )
+                    })
+                });
+                if issue.track_opens && task.track_opens {
+                    let tracking_url = open_tracking_url(
+                        base_url,
+                        hmac_secret,
+                        newsletter_issue_id,
+                        task.subscriber_id,
+                    );
+                    append_tracking_pixel(&mut html_content, &tracking_url);
+                }
+                (html_content, text_content, unsubscribe_url)
+            })
+            .collect();
+        let messages: Vec<_> = recipients
+            .iter()
+            .zip(&contents)
+            .map(
+                |((_, email), (html_content, text_content, unsubscribe_url))| EmailMessage {
+                    recipient: email,
+                    subject: &issue.title,
+                    html_content,
+                    text_content,
+                    unsubscribe_url: Some(unsubscribe_url),
+                    attachments: &attachments,
+                },
+            )
+            .collect();
+
+        match email_client.send_batch(&messages).await {
+            Ok(response) => {
+                let tasks_by_email: HashMap<&str, &DeliveryTask> = recipients
+                    .iter()
+                    .map(|(task, email)| (email.as_ref(), *task))
+                    .collect();
+
+                for sent in response.succeeded {
+                    let task = tasks_by_email[sent.recipient.as_ref()];
+                    let delivery = Delivery::sent(task.n_retries + 1, sent.message_id);
+                    record_delivery(&mut transaction, task, delivery).await?;
+                    delete_task(&mut transaction, task).await?;
+                }
+
+                // 宛先ごとのエラーは配信停止中の宛先などの恒久的なものなので再試行しない
+                for failed in response.failed {
+                    let task = tasks_by_email[failed.recipient.as_ref()];
+                    tracing::error!(
+                        error_code = failed.error_code,
+                        error.message = %failed.message,
+                        subscriber_email = %task.subscriber_email,
+                        "The email server rejected the issue for a confirmed subscriber",
+                    );
+                    let delivery = Delivery::failed(
+                        task.n_retries + 1,
+                        format!("ErrorCode {}: {}", failed.error_code, failed.message),
+                    );
+                    record_delivery(&mut transaction, task, delivery).await?;
+                    delete_task(&mut transaction, task).await?;
+                }
+
+                // 送信済みのメールを再送しないように、送信できなかったメールのみ再試行する
+                for deferred in response.deferred {
+                    let task = tasks_by_email[deferred.recipient.as_ref()];
+                    retry_or_fail_task(&mut transaction, task, &deferred.error).await?;
+                }
+            }
+            // 認証エラーや送信元の不備などの恒久的なエラーは再試行しても成功しないため失敗とする
+            Err(e) if !e.is_transient() => {
+                for (task, _) in &recipients {
+                    tracing::error!(
+                        error.cause_chain = ?e,
+                        error.message = %e,
+                        subscriber_email = %task.subscriber_email,
+                        "Failed to deliver issue to a confirmed subscriber. \
+                         The error is permanent and will not be retried.",
+                    );
+                    let delivery = Delivery::failed(task.n_retries + 1, e.to_string());
+                    record_delivery(&mut transaction, task, delivery).await?;
+                    delete_task(&mut transaction, task).await?;
+                }
+            }
+            Err(e) => {
+                for (task, _) in &recipients {
+                    retry_or_fail_task(&mut transaction, task, &e).await?;
+                }
+            }
+        }
+    }
+
+    transaction.commit().await?;
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
+
+struct DeliveryTask {
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    subscriber_email: String,
+    subscriber_name: String,
+    n_retries: i16,
+    subscriber_status: String,
+    /// 購読者が開封の計測を許可しているか
+    track_opens: bool,
+    /// タスクを作成した後に送信停止リストに登録されたか
+    is_suppressed: bool,
+}
+
+/// 購読者ごとの開封を記録するためのURLを作成する
+///
+/// 号と購読者のIDを HMAC タグで署名し、他の購読者の開封を記録できないようにする。
+pub fn open_tracking_url(
+    base_url: &ApplicationBaseUrl,
+    hmac_secret: &HmacSecret,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+) -> String {
+    let query_string = format!(
+        "newsletter_issue_id={}&subscriber_id={}",
+        newsletter_issue_id, subscriber_id
+    );
+    let tag = hmac_secret.sign(&query_string);
+
+    format!("{}/tracking/open?{}&tag={}", base_url.0, query_string, tag)
+}
+
+/// リンクのクリックを記録してから元のURLへリダイレクトするURLを作成する
+///
+/// リダイレクト先のURLも署名に含めることで、任意のURLへのリダイレクトに悪用されないようにする。
+pub fn click_tracking_url(
+    base_url: &ApplicationBaseUrl,
+    hmac_secret: &HmacSecret,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    url: &str,
+) -> String {
+    let query_string = format!(
+        "newsletter_issue_id={}&subscriber_id={}&url={}",
+        newsletter_issue_id,
+        subscriber_id,
+        urlencoding::encode(url)
+    );
+    let tag = hmac_secret.sign(&query_string);
+
+    format!("{}/tracking/click?{}&tag={}", base_url.0, query_string, tag)
+}
+
+/// `mailto:` やページ内のリンクなどはリダイレクトできないため書き換えない
+///
+/// 配信停止やブラウザで表示するためのリンクなど、このサービス自身へのリンクも計測しない。
+fn is_trackable_link(url: &str, base_url: &ApplicationBaseUrl) -> bool {
+    let url = url.trim_start().to_ascii_lowercase();
+    let base_url = base_url.0.trim_end_matches('/').to_ascii_lowercase();
+
+    let is_own_link = url
+        .strip_prefix(&base_url)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with('/') || rest.starts_with('?'));
+
+    (url.starts_with("https://") || url.starts_with("http://")) && !is_own_link
+}
+
+/// 開封を計測するための 1x1 の画像を HTML の本文の末尾に追加する
+fn append_tracking_pixel(html_content: &mut String, tracking_url: &str) {
+    html_content.push_str(&format!(
+        r#"<img src="{}" width="1" height="1" alt="" style="display:block;border:0">"#,
+        htmlescape::encode_minimal(tracking_url)
+    ));
+}
+
+/// 購読者ごとの配信状況
+struct Delivery {
+    status: &'static str,
+    n_attempts: i16,
+    last_error: Option<String>,
+    provider_message_id: Option<String>,
+}
+
+impl Delivery {
+    fn queued(n_attempts: i16, error: String) -> Self {
+        Self {
+            status: "queued",
+            n_attempts,
+            last_error: Some(error),
+            provider_message_id: None,
+        }
+    }
+
+    fn sent(n_attempts: i16, provider_message_id: Option<String>) -> Self {
+        Self {
+            status: "sent",
+            n_attempts,
+            last_error: None,
+            provider_message_id,
+        }
+    }
+
+    fn failed(n_attempts: i16, error: String) -> Self {
+        Self {
+            status: "failed",
+            n_attempts,
+            last_error: Some(error),
+            provider_message_id: None,
+        }
+    }
+
+    fn skipped(n_attempts: i16, reason: String) -> Self {
+        Self {
+            status: "skipped",
+            n_attempts,
+            last_error: Some(reason),
+            provider_message_id: None,
+        }
+    }
+}
+
+/// 号のメーリングリストの確認済みの購読者のうち、配信対象の条件に一致する購読者ごとに
+/// 配信タスクと配信状況の記録を作成する
+#[tracing::instrument(skip_all)]
+pub async fn enqueue_delivery_tasks(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+) -> Result<(), anyhow::Error> {
+    let issue = sqlx::query!(
+        r#"
+        SELECT mailing_list_id, segment
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_one(&mut *transaction)
+    .await?;
+    let segment = issue
+        .segment
+        .map(|segment| Segment::parse(&segment))
+        .transpose()
+        .map_err(anyhow::Error::msg)
+        .context("The stored segment expression is invalid")?;
+
+    // 配信タスクと配信状況が同じ購読者の集合から作成されるように1つのクエリで登録する
+    let mut query = QueryBuilder::new("WITH recipients AS (");
+    push_recipients_query(
+        &mut query,
+        "id, email",
+        issue.mailing_list_id,
+        segment.as_ref(),
+    );
+    query
+        .push(
+            r#"), tasks AS (
+            INSERT INTO issue_delivery_queue (
+                newsletter_issue_id,
+                subscriber_id,
+                subscriber_email
+            )
+            SELECT "#,
+        )
+        .push_bind(newsletter_issue_id)
+        .push(
+            r#", id, email
+            FROM recipients
+        )
+        INSERT INTO issue_deliveries (
+            newsletter_issue_id,
+            subscriber_id,
+            subscriber_email,
+            status,
+            updated_at
+        )
+        SELECT "#,
+        )
+        .push_bind(newsletter_issue_id)
+        .push(
+            r#", id, email, 'queued', now()
+        FROM recipients"#,
+        );
+    query.build().execute(transaction).await?;
+
+    Ok(())
+}
+
+/// メーリングリストの購読者のうち、条件に一致する配信対象の購読者数を数える
+#[tracing::instrument(skip(executor))]
+pub async fn count_recipients(
+    executor: impl PgExecutor<'_>,
+    mailing_list_id: Uuid,
+    segment: Option<&Segment>,
+) -> Result<i64, sqlx::Error> {
+    let mut query = QueryBuilder::new("SELECT count(*) FROM (");
+    push_recipients_query(&mut query, "id", mailing_list_id, segment);
+    query.push(") AS recipients");
+
+    let (n_recipients,) = query.build_query_as().fetch_one(executor).await?;
+
+    Ok(n_recipients)
+}
+
+/// メーリングリストの確認済みの購読者から配信対象を選択するクエリを組み立てる
+///
+/// 送信停止リストに登録されたメールアドレスとドメインは除外する。
+fn push_recipients_query(
+    query: &mut QueryBuilder<'_, Postgres>,
+    columns: &str,
+    mailing_list_id: Uuid,
+    segment: Option<&Segment>,
+) {
+    query
+        .push("SELECT ")
+        .push(columns)
+        .push(" FROM subscriptions WHERE status = 'confirmed' AND mailing_list_id = ")
+        .push_bind(mailing_list_id)
+        .push(
+            r#" AND NOT EXISTS (
+            SELECT 1
+            FROM suppressions
+            WHERE
+                (kind = 'address' AND value = lower(subscriptions.email)) OR
+                (kind = 'domain' AND value = split_part(lower(subscriptions.email), '@', 2))
+        )"#,
+        );
+    if let Some(segment) = segment {
+        query.push(" AND ");
+        push_segment_condition(query, segment);
+    }
+}
+
+/// 配信対象の条件を SQL の条件式に変換する (タグはバインド変数として渡す)
+fn push_segment_condition(query: &mut QueryBuilder<'_, Postgres>, segment: &Segment) {
+    match segment {
+        Segment::Tag(tag) => {
+            query
+                .push("tags @> ARRAY[")
+                .push_bind(tag.clone())
+                .push("]");
+        }
+        Segment::Not(segment) => {
+            query.push("NOT (");
+            push_segment_condition(query, segment);
+            query.push(")");
+        }
+        Segment::And(left, right) | Segment::Or(left, right) => {
+            let operator = match segment {
+                Segment::And(..) => " AND ",
+                _ => " OR ",
+            };
+            query.push("(");
+            push_segment_condition(query, left);
+            query.push(operator);
+            push_segment_condition(query, right);
+            query.push(")");
+        }
+    }
+}
+
+/// 配信可能なタスクを同じニュースレターごとに最大 `MAX_BATCH_SIZE` 件取り出す
+#[tracing::instrument(skip_all)]
+async fn dequeue_tasks(
+    pool: &PgPool,
+) -> Result<Option<(PgTransaction, Vec<DeliveryTask>)>, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+
+    // 他のワーカーがロックしている行は読み飛ばす
+    let tasks = sqlx::query_as!(
+        DeliveryTask,
+        r#"
+        SELECT
+            q.newsletter_issue_id,
+            q.subscriber_id,
+            q.subscriber_email,
+            s.name AS subscriber_name,
+            q.n_retries,
+            s.status AS subscriber_status,
+            s.track_opens,
+            EXISTS (
+                SELECT 1
+                FROM suppressions
+                WHERE
+                    (kind = 'address' AND value = lower(q.subscriber_email)) OR
+                    (kind = 'domain' AND value = split_part(lower(q.subscriber_email), '@', 2))
+            ) AS "is_suppressed!"
+        FROM issue_delivery_queue q
+        JOIN subscriptions s ON s.id = q.subscriber_id
+        WHERE
+            q.execute_after <= now() AND
+            q.newsletter_issue_id = (
+                SELECT newsletter_issue_id
+                FROM issue_delivery_queue
+                WHERE execute_after <= now()
+                FOR UPDATE
+                SKIP LOCKED
+                LIMIT 1
+            )
+        FOR UPDATE OF q
+        SKIP LOCKED
+        LIMIT $1
+        "#,
+        MAX_BATCH_SIZE as i64
+    )
+    .fetch_all(&mut transaction)
+    .await?;
+
+    if tasks.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some((transaction, tasks)))
+    }
+}
+
+#[tracing::instrument(skip_all)]
+async fn delete_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        DELETE FROM issue_delivery_queue
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// 一時的なエラーで送信できなかったタスクを後で再試行し、試行回数の上限に達した場合は失敗とする
+#[tracing::instrument(skip_all)]
+async fn retry_or_fail_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    e: &TransportError,
+) -> Result<(), anyhow::Error> {
+    let n_attempts = task.n_retries + 1;
+
+    if n_attempts < MAX_DELIVERY_ATTEMPTS {
+        tracing::warn!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            n_attempts,
+            subscriber_email = %task.subscriber_email,
+            "Failed to deliver issue to a confirmed subscriber. \
+             The task will be retried later.",
+        );
+        let delivery = Delivery::queued(n_attempts, e.to_string());
+        record_delivery(transaction, task, delivery).await?;
+        reschedule_task(transaction, task, n_attempts).await?;
+    } else {
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            n_attempts,
+            subscriber_email = %task.subscriber_email,
+            "Failed to deliver issue to a confirmed subscriber. \
+             Giving up after too many attempts.",
+        );
+        let delivery = Delivery::failed(n_attempts, e.to_string());
+        record_delivery(transaction, task, delivery).await?;
+        delete_task(transaction, task).await?;
+    }
+
+    Ok(())
+}
+
+/// 配信状況の行がないタスクの場合も、送信結果を失わないように作成する
+#[tracing::instrument(skip_all)]
+async fn record_delivery(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    delivery: Delivery,
+) -> Result<(), anyhow::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_deliveries (
+            newsletter_issue_id,
+            subscriber_id,
+            subscriber_email,
+            status,
+            n_attempts,
+            last_error,
+            provider_message_id,
+            updated_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, now())
+        ON CONFLICT (newsletter_issue_id, subscriber_id) DO UPDATE
+        SET
+            status = EXCLUDED.status,
+            n_attempts = EXCLUDED.n_attempts,
+            last_error = EXCLUDED.last_error,
+            provider_message_id = EXCLUDED.provider_message_id,
+            updated_at = EXCLUDED.updated_at
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_id,
+        task.subscriber_email,
+        delivery.status,
+        delivery.n_attempts,
+        delivery.last_error,
+        delivery.provider_message_id
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip_all)]
+async fn reschedule_task(
+    transaction: &mut PgTransaction,
+    task: &DeliveryTask,
+    n_retries: i16,
+) -> Result<(), anyhow::Error> {
+    // 再試行のたびに待ち時間を倍にする (2, 4, 8, ... 秒)
+    let backoff = chrono::Duration::seconds(2_i64.pow(n_retries as u32));
+
+    sqlx::query!(
+        r#"
+        UPDATE issue_delivery_queue
+        SET
+            n_retries = $3,
+            execute_after = $4
+        WHERE
+            newsletter_issue_id = $1 AND
+            subscriber_email = $2
+        "#,
+        task.newsletter_issue_id,
+        task.subscriber_email,
+        n_retries,
+        Utc::now() + backoff
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
+
+/// 公開アーカイブで号を表示するURLを作成する
+fn web_version_url(base_url: &ApplicationBaseUrl, slug: &str) -> String {
+    format!("{}/issues/{}", base_url.0, urlencoding::encode(slug))
+}
+
+/// 購読者ごとの値を埋め込み、末尾にブラウザで表示するためのリンクを追加した本文を作成する
+pub fn render_email(
+    html_template: &str,
+    text_template: &str,
+    variables: &TemplateVariables,
+    web_version_url: &str,
+) -> (String, String) {
+    let mut html_content = render_html(html_template, variables);
+    let mut text_content = render_text(text_template, variables);
+
+    html_content.push_str(&format!(
+        r#"<p><a href="{}">View this issue in your browser</a></p>"#,
+        htmlescape::encode_minimal(web_version_url)
+    ));
+    text_content.push_str(&format!(
+        "\n\nView this issue in your browser: {}",
+        web_version_url
+    ));
+
+    (html_content, text_content)
+}
+
+struct NewsletterIssue {
+    title: String,
+    text_content: String,
+    html_content: String,
+    slug: String,
+    track_opens: bool,
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_issue(pool: &PgPool, issue_id: Uuid) -> Result<NewsletterIssue, anyhow::Error> {
+    let issue = sqlx::query_as!(
+        NewsletterIssue,
+        r#"
+        SELECT title, text_content, html_content, slug, track_opens
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue_id
+    )
+    .fetch_one(pool)
+    .await?;
+
+    Ok(issue)
+}
+
+#[tracing::instrument(skip_all)]
+async fn get_issue_attachments(
+    pool: &PgPool,
+    issue_id: Uuid,
+) -> Result<Vec<EmailAttachment>, anyhow::Error> {
+    let attachments = sqlx::query_as!(
+        EmailAttachment,
+        r#"
+        SELECT name, content_type, content, content_id
+        FROM newsletter_issue_attachments
+        WHERE newsletter_issue_id = $1
+        ORDER BY position
+        "#,
+        issue_id
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(attachments)
+}
@@ -0,0 +1,81 @@
+use std::time::Duration;
+
+use sqlx::PgPool;
+use tracing::{field::display, Span};
+
+use crate::{
+    configuration::Settings,
+    issue_delivery_worker::{enqueue_delivery_tasks, ExecutionOutcome},
+    startup::get_connection_pool,
+};
+
+pub async fn run_scheduler_until_stopped(configuration: Settings) -> Result<(), anyhow::Error> {
+    let connection_pool = get_connection_pool(&configuration.database);
+
+    scheduler_loop(connection_pool).await
+}
+
+async fn scheduler_loop(pool: PgPool) -> Result<(), anyhow::Error> {
+    loop {
+        match try_release_scheduled_issue(&pool).await {
+            Ok(ExecutionOutcome::EmptyQueue) => {
+                tokio::time::sleep(Duration::from_secs(10)).await;
+            }
+            Err(_) => {
+                tokio::time::sleep(Duration::from_secs(1)).await;
+            }
+            Ok(ExecutionOutcome::TaskCompleted) => {}
+        }
+    }
+}
+
+/// 配信予定日時を過ぎた予約配信を1件取り出して、配信タスクを登録する
+#[tracing::instrument(
+    skip_all,
+    fields(newsletter_issue_id=tracing::field::Empty),
+    err
+)]
+pub async fn try_release_scheduled_issue(pool: &PgPool) -> Result<ExecutionOutcome, anyhow::Error> {
+    let mut transaction = pool.begin().await?;
+
+    // 他のスケジューラーが処理中の号は読み飛ばす
+    let issue = sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id
+        FROM newsletter_issues
+        WHERE
+            status = 'scheduled' AND
+            send_at <= now()
+        FOR UPDATE
+        SKIP LOCKED
+        LIMIT 1
+        "#
+    )
+    .fetch_optional(&mut transaction)
+    .await?;
+
+    let Some(issue) = issue else {
+        return Ok(ExecutionOutcome::EmptyQueue);
+    };
+
+    Span::current().record("newsletter_issue_id", display(issue.newsletter_issue_id));
+
+    enqueue_delivery_tasks(&mut transaction, issue.newsletter_issue_id).await?;
+
+    sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET
+            status = 'published',
+            published_at = now()
+        WHERE newsletter_issue_id = $1
+        "#,
+        issue.newsletter_issue_id
+    )
+    .execute(&mut transaction)
+    .await?;
+
+    transaction.commit().await?;
+
+    Ok(ExecutionOutcome::TaskCompleted)
+}
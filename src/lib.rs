@@ -3,6 +3,10 @@ pub mod configuration;
 pub mod domain;
 pub mod email_client;
 pub mod error;
+pub mod idempotency;
+pub mod issue_delivery_worker;
+pub mod issue_scheduler;
+pub mod rate_limiter;
 pub mod routes;
 pub mod startup;
 pub mod telemetry;
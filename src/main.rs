@@ -1,5 +1,11 @@
+use std::fmt::{Debug, Display};
+
+use tokio::task::JoinError;
 use zero2prod::{
     configuration::get_configuration,
+    idempotency::run_expiration_worker_until_stopped,
+    issue_delivery_worker::run_worker_until_stopped,
+    issue_scheduler::run_scheduler_until_stopped,
     startup::Application,
     telemetry::{get_subscriber, init_subscriber},
 };
@@ -10,8 +16,48 @@ async fn main() {
     init_subscriber(subscriber);
 
     let configuration = get_configuration().expect("Failed to read configuration");
-    let application = Application::build(configuration);
+    let application = Application::build(configuration.clone());
 
     tracing::debug!("Listening on port: {}", application.addr().port());
-    application.run_until_stopped().await.unwrap();
+
+    // APIサーバーとワーカーを並行して実行し、いずれかが停止した時点で終了する
+    let email_client = application.email_client();
+    let application_task = tokio::spawn(application.run_until_stopped());
+    let worker_task = tokio::spawn(run_worker_until_stopped(
+        configuration.clone(),
+        email_client,
+    ));
+    let scheduler_task = tokio::spawn(run_scheduler_until_stopped(configuration.clone()));
+    let expiration_task = tokio::spawn(run_expiration_worker_until_stopped(configuration));
+
+    tokio::select! {
+        outcome = application_task => report_exit("API", outcome),
+        outcome = worker_task => report_exit("Background worker", outcome),
+        outcome = scheduler_task => report_exit("Scheduler", outcome),
+        outcome = expiration_task => report_exit("Idempotency expiration worker", outcome),
+    };
+}
+
+fn report_exit(task_name: &str, outcome: Result<Result<(), impl Debug + Display>, JoinError>) {
+    match outcome {
+        Ok(Ok(())) => {
+            tracing::info!("{} has exited", task_name)
+        }
+        Ok(Err(e)) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "{} failed",
+                task_name
+            )
+        }
+        Err(e) => {
+            tracing::error!(
+                error.cause_chain = ?e,
+                error.message = %e,
+                "{} task failed to complete",
+                task_name
+            )
+        }
+    }
 }
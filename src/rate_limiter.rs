@@ -0,0 +1,194 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use serde::Deserialize;
+use serde_aux::field_attributes::deserialize_number_from_string;
+
+/// メール配信サービスの送信数の上限
+#[derive(Deserialize, Clone, Debug)]
+pub struct RateLimit {
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub messages_per_second: u32,
+    /// 待機せずに連続して送信できる数
+    #[serde(deserialize_with = "deserialize_number_from_string")]
+    pub burst: u32,
+}
+
+/// トークンバケット方式のレートリミッター
+///
+/// 呼び出し元は送信前に `acquire` でトークンを1つ予約する。
+/// トークンが不足している場合はマイナスの残高として予約し、補充されるまで待機するため、
+/// 同時に呼び出された場合でも予約した順番に送信される。
+pub struct RateLimiter {
+    bucket: Mutex<Bucket>,
+    messages_per_second: f64,
+    burst: f64,
+    metrics: Metrics,
+}
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+#[derive(Default)]
+struct Metrics {
+    acquired: AtomicU64,
+    throttled: AtomicU64,
+    total_wait_microseconds: AtomicU64,
+}
+
+/// レートリミッターの待機時間の集計
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RateLimiterMetrics {
+    /// 取得したトークンの数
+    pub acquired: u64,
+    /// 待機が必要になった回数
+    pub throttled: u64,
+    /// 待機した時間の合計
+    pub total_wait: Duration,
+}
+
+impl RateLimiter {
+    pub fn new(rate_limit: &RateLimit) -> Self {
+        let messages_per_second = f64::from(rate_limit.messages_per_second.max(1));
+        let burst = f64::from(rate_limit.burst.max(1));
+
+        Self {
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                last_refill: Instant::now(),
+            }),
+            messages_per_second,
+            burst,
+            metrics: Metrics::default(),
+        }
+    }
+
+    /// トークンを1つ取得し、取得するまでに待機した時間を返却する
+    pub async fn acquire(&self) -> Duration {
+        self.acquire_many(1).await
+    }
+
+    /// まとめて送信するメールの数だけトークンを取得し、取得するまでに待機した時間を返却する
+    pub async fn acquire_many(&self, n_tokens: u32) -> Duration {
+        let wait = {
+            let mut bucket = self.bucket.lock().unwrap();
+
+            let now = Instant::now();
+            let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+            bucket.tokens = (bucket.tokens + elapsed * self.messages_per_second).min(self.burst);
+            bucket.last_refill = now;
+
+            bucket.tokens -= f64::from(n_tokens);
+            if bucket.tokens < 0.0 {
+                Duration::from_secs_f64(-bucket.tokens / self.messages_per_second)
+            } else {
+                Duration::ZERO
+            }
+        };
+
+        self.metrics
+            .acquired
+            .fetch_add(u64::from(n_tokens), Ordering::Relaxed);
+
+        if !wait.is_zero() {
+            self.metrics.throttled.fetch_add(1, Ordering::Relaxed);
+            self.metrics
+                .total_wait_microseconds
+                .fetch_add(wait.as_micros() as u64, Ordering::Relaxed);
+
+            tokio::time::sleep(wait).await;
+        }
+
+        wait
+    }
+
+    pub fn metrics(&self) -> RateLimiterMetrics {
+        RateLimiterMetrics {
+            acquired: self.metrics.acquired.load(Ordering::Relaxed),
+            throttled: self.metrics.throttled.load(Ordering::Relaxed),
+            total_wait: Duration::from_micros(
+                self.metrics.total_wait_microseconds.load(Ordering::Relaxed),
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use crate::rate_limiter::{RateLimit, RateLimiter};
+
+    fn rate_limiter(messages_per_second: u32, burst: u32) -> RateLimiter {
+        RateLimiter::new(&RateLimit {
+            messages_per_second,
+            burst,
+        })
+    }
+
+    #[tokio::test]
+    async fn requests_within_the_burst_do_not_wait() {
+        let rate_limiter = rate_limiter(1, 5);
+
+        for _ in 0..5 {
+            assert_eq!(rate_limiter.acquire().await, Duration::ZERO);
+        }
+
+        assert_eq!(rate_limiter.metrics().throttled, 0);
+    }
+
+    #[tokio::test]
+    async fn requests_beyond_the_burst_wait_for_a_refill() {
+        let rate_limiter = rate_limiter(20, 1);
+        let started_at = Instant::now();
+
+        rate_limiter.acquire().await;
+        rate_limiter.acquire().await;
+        rate_limiter.acquire().await;
+
+        // 1秒あたり20通なので、2通目以降はそれぞれ50ミリ秒待機する
+        assert!(started_at.elapsed() >= Duration::from_millis(100));
+
+        let metrics = rate_limiter.metrics();
+        assert_eq!(metrics.acquired, 3);
+        assert_eq!(metrics.throttled, 2);
+        assert!(metrics.total_wait > Duration::ZERO);
+    }
+
+    #[tokio::test]
+    async fn acquiring_many_tokens_waits_for_all_of_them() {
+        let rate_limiter = rate_limiter(20, 1);
+
+        // 1通分はバケットに残っているため、残りの2通分 (100ミリ秒) を待機する
+        let wait = rate_limiter.acquire_many(3).await;
+
+        assert!(wait >= Duration::from_millis(90));
+        assert_eq!(rate_limiter.metrics().acquired, 3);
+    }
+
+    #[tokio::test]
+    async fn concurrent_requests_are_spread_over_time() {
+        let rate_limiter = std::sync::Arc::new(rate_limiter(50, 1));
+        let started_at = Instant::now();
+
+        let tasks: Vec<_> = (0..5)
+            .map(|_| {
+                let rate_limiter = rate_limiter.clone();
+                tokio::spawn(async move { rate_limiter.acquire().await })
+            })
+            .collect();
+        for task in tasks {
+            task.await.unwrap();
+        }
+
+        // 1通目以外は20ミリ秒ずつ順番に待機する
+        assert!(started_at.elapsed() >= Duration::from_millis(80));
+    }
+}
@@ -0,0 +1,130 @@
+use axum::{
+    extract::{Path, State},
+    response::{Html, IntoResponse, Response},
+};
+use hyper::StatusCode;
+use uuid::Uuid;
+
+use crate::email_client::{DevMailbox, StoredEmail};
+
+#[tracing::instrument(name = "List emails in the development mailbox", skip(mailbox))]
+pub async fn list_mailbox(State(mailbox): State<DevMailbox>) -> Html<String> {
+    let emails = mailbox.emails();
+
+    let items = if emails.is_empty() {
+        "<p>No emails have been sent yet.</p>".to_owned()
+    } else {
+        let mut items = String::from("<ul>");
+        for email in &emails {
+            items.push_str(&format!(
+                r#"<li><a href="/_dev/mailbox/{}">{}</a> to {} <time>{}</time></li>"#,
+                email.id,
+                escape(&email.subject),
+                escape(&email.to),
+                email.received_at.format("%Y-%m-%d %H:%M:%S"),
+            ));
+        }
+        items.push_str("</ul>");
+        items
+    };
+
+    layout("Mailbox", &format!("<h1>Mailbox</h1>{}", items))
+}
+
+#[tracing::instrument(name = "Show an email in the development mailbox", skip(mailbox))]
+pub async fn show_mailbox_email(
+    State(mailbox): State<DevMailbox>,
+    Path(email_id): Path<Uuid>,
+) -> Response {
+    let email = match mailbox.email(email_id) {
+        Some(email) => email,
+        None => return not_found(),
+    };
+
+    let mut headers = format!(
+        "<dt>From</dt><dd>{}</dd><dt>To</dt><dd>{}</dd>",
+        escape(&email.from),
+        escape(&email.to)
+    );
+    for (name, value) in &email.headers {
+        headers.push_str(&format!(
+            "<dt>{}</dt><dd>{}</dd>",
+            escape(name),
+            escape(value)
+        ));
+    }
+
+    layout(
+        &email.subject,
+        &format!(
+            r#"<h1>{subject}</h1><dl>{headers}</dl>{attachments}<h2>HTML</h2><iframe src="/_dev/mailbox/{id}/html" sandbox title="HTML content" width="100%" height="480"></iframe><h2>Text</h2><pre>{text}</pre><p><a href="/_dev/mailbox">Back to the mailbox</a></p>"#,
+            subject = escape(&email.subject),
+            headers = headers,
+            attachments = attachments(&email),
+            id = email.id,
+            text = escape(&email.text_content),
+        ),
+    )
+    .into_response()
+}
+
+/// HTML の本文はアプリケーションの画面に影響しないように iframe で個別に表示する
+#[tracing::instrument(name = "Render an email in the development mailbox", skip(mailbox))]
+pub async fn show_mailbox_email_html(
+    State(mailbox): State<DevMailbox>,
+    Path(email_id): Path<Uuid>,
+) -> Response {
+    match mailbox.email(email_id) {
+        Some(email) => Html(email.html_content).into_response(),
+        None => not_found(),
+    }
+}
+
+fn attachments(email: &StoredEmail) -> String {
+    if email.attachments.is_empty() {
+        return String::new();
+    }
+
+    let mut items = String::from("<h2>Attachments</h2><ul>");
+    for attachment in &email.attachments {
+        items.push_str(&format!(
+            "<li>{} ({})</li>",
+            escape(&attachment.name),
+            escape(&attachment.content_type)
+        ));
+    }
+    items.push_str("</ul>");
+    items
+}
+
+fn not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        layout(
+            "Not found",
+            "<p>The email you are looking for does not exist.</p>",
+        ),
+    )
+        .into_response()
+}
+
+fn escape(value: &str) -> String {
+    htmlescape::encode_minimal(value)
+}
+
+fn layout(title: &str, body: &str) -> Html<String> {
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{}</title>
+  </head>
+  <body>
+    {}
+  </body>
+</html>"#,
+        escape(title),
+        body
+    ))
+}
@@ -0,0 +1,260 @@
+use anyhow::Context;
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use hyper::{header, HeaderMap, StatusCode};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{render_web_version, ArchiveError};
+use crate::startup::{AppState, ApplicationBaseUrl};
+
+/// フィードに含める号の数
+const FEED_ENTRIES: i64 = 20;
+
+const FEED_TITLE: &str = "Newsletter";
+
+struct FeedEntry {
+    newsletter_issue_id: Uuid,
+    title: String,
+    slug: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "Get the RSS feed", skip_all)]
+pub async fn rss_feed(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Response, ArchiveError> {
+    let entries = get_feed_entries(&state.db_state.db_pool)
+        .await
+        .context("Failed to retrieve published newsletter issues for the feed")?;
+    let body = rss(&state.base_url, &entries);
+
+    Ok(conditional_response(
+        &headers,
+        "application/rss+xml; charset=utf-8",
+        body,
+        last_modified(&entries),
+    ))
+}
+
+#[tracing::instrument(name = "Get the Atom feed", skip_all)]
+pub async fn atom_feed(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Response, ArchiveError> {
+    let entries = get_feed_entries(&state.db_state.db_pool)
+        .await
+        .context("Failed to retrieve published newsletter issues for the feed")?;
+    let body = atom(&state.base_url, &entries);
+
+    Ok(conditional_response(
+        &headers,
+        "application/atom+xml; charset=utf-8",
+        body,
+        last_modified(&entries),
+    ))
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_feed_entries(pool: &PgPool) -> Result<Vec<FeedEntry>, sqlx::Error> {
+    sqlx::query_as!(
+        FeedEntry,
+        r#"
+        SELECT
+            newsletter_issue_id,
+            title,
+            slug,
+            html_content,
+            published_at as "published_at!"
+        FROM newsletter_issues
+        WHERE status = 'published'
+          -- セグメントや既定以外のリストに送信した号は全員に公開しない
+          AND segment IS NULL
+          AND mailing_list_id = (SELECT id FROM mailing_lists WHERE slug = 'default')
+        ORDER BY published_at DESC, newsletter_issue_id
+        LIMIT $1
+        "#,
+        FEED_ENTRIES
+    )
+    .fetch_all(pool)
+    .await
+}
+
+/// 公開済みの号は更新されないため、最新の号の公開日時をフィードの更新日時とする
+fn last_modified(entries: &[FeedEntry]) -> Option<DateTime<Utc>> {
+    entries.iter().map(|entry| entry.published_at).max()
+}
+
+fn issue_url(base_url: &ApplicationBaseUrl, entry: &FeedEntry) -> String {
+    format!("{}/issues/{}", base_url.0, urlencoding::encode(&entry.slug))
+}
+
+fn rss(base_url: &ApplicationBaseUrl, entries: &[FeedEntry]) -> String {
+    let mut items = String::new();
+    for entry in entries {
+        let url = issue_url(base_url, entry);
+        items.push_str(&format!(
+            r#"
+    <item>
+      <title>{}</title>
+      <link>{}</link>
+      <guid isPermaLink="true">{}</guid>
+      <pubDate>{}</pubDate>
+      <description>{}</description>
+    </item>"#,
+            escape(&entry.title),
+            escape(&url),
+            escape(&url),
+            entry.published_at.to_rfc2822(),
+            escape(&render_web_version(&entry.html_content)),
+        ));
+    }
+
+    let last_build_date = last_modified(entries)
+        .map(|last_modified| {
+            format!(
+                "\n    <lastBuildDate>{}</lastBuildDate>",
+                last_modified.to_rfc2822()
+            )
+        })
+        .unwrap_or_default();
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<rss version="2.0" xmlns:atom="http://www.w3.org/2005/Atom">
+  <channel>
+    <title>{title}</title>
+    <link>{base_url}/issues</link>
+    <description>Past issues of {title}</description>
+    <atom:link href="{base_url}/feed.rss" rel="self" type="application/rss+xml"/>{last_build_date}{items}
+  </channel>
+</rss>
+"#,
+        title = escape(FEED_TITLE),
+        base_url = escape(&base_url.0),
+        last_build_date = last_build_date,
+        items = items,
+    )
+}
+
+fn atom(base_url: &ApplicationBaseUrl, entries: &[FeedEntry]) -> String {
+    let mut items = String::new();
+    for entry in entries {
+        items.push_str(&format!(
+            r#"
+  <entry>
+    <title>{}</title>
+    <id>urn:uuid:{}</id>
+    <link rel="alternate" type="text/html" href="{}"/>
+    <published>{}</published>
+    <updated>{}</updated>
+    <content type="html">{}</content>
+  </entry>"#,
+            escape(&entry.title),
+            entry.newsletter_issue_id,
+            escape(&issue_url(base_url, entry)),
+            entry.published_at.to_rfc3339(),
+            entry.published_at.to_rfc3339(),
+            escape(&render_web_version(&entry.html_content)),
+        ));
+    }
+
+    // 号が1つもない場合も updated は必須のため、エポックを使用する
+    let updated = last_modified(entries).unwrap_or_default().to_rfc3339();
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>{title}</title>
+  <id>{base_url}/feed.atom</id>
+  <link rel="self" type="application/atom+xml" href="{base_url}/feed.atom"/>
+  <link rel="alternate" type="text/html" href="{base_url}/issues"/>
+  <updated>{updated}</updated>
+  <author>
+    <name>{title}</name>
+  </author>{items}
+</feed>
+"#,
+        title = escape(FEED_TITLE),
+        base_url = escape(&base_url.0),
+        updated = updated,
+        items = items,
+    )
+}
+
+fn escape(value: &str) -> String {
+    htmlescape::encode_minimal(value)
+}
+
+/// ETag と Last-Modified を付与し、フィードが更新されていなければ 304 を返却する
+///
+/// RFC 9110 に従い、If-None-Match が指定された場合は If-Modified-Since を無視する。
+fn conditional_response(
+    headers: &HeaderMap,
+    content_type: &'static str,
+    body: String,
+    last_modified: Option<DateTime<Utc>>,
+) -> Response {
+    let etag = format!("\"{:x}\"", Sha256::digest(body.as_bytes()));
+    let last_modified = last_modified.map(http_date);
+
+    let not_modified = match headers.get(header::IF_NONE_MATCH) {
+        Some(if_none_match) => if_none_match.to_str().is_ok_and(|value| {
+            value
+                .split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == etag)
+        }),
+        None => match (headers.get(header::IF_MODIFIED_SINCE), &last_modified) {
+            (Some(if_modified_since), Some(last_modified)) => {
+                is_not_modified_since(if_modified_since.to_str().ok(), last_modified)
+            }
+            _ => false,
+        },
+    };
+
+    let status = if not_modified {
+        StatusCode::NOT_MODIFIED
+    } else {
+        StatusCode::OK
+    };
+
+    let mut response = Response::builder()
+        .status(status)
+        .header(header::ETAG, &etag)
+        .header(header::CACHE_CONTROL, "no-cache");
+    if let Some(last_modified) = &last_modified {
+        response = response.header(header::LAST_MODIFIED, last_modified);
+    }
+
+    if not_modified {
+        response.body(String::new()).unwrap().into_response()
+    } else {
+        response
+            .header(header::CONTENT_TYPE, content_type)
+            .body(body)
+            .unwrap()
+            .into_response()
+    }
+}
+
+/// Last-Modified は秒単位に切り捨てているため、文字列から解釈した日時同士で比較する
+fn is_not_modified_since(if_modified_since: Option<&str>, last_modified: &str) -> bool {
+    let parse = |value: &str| DateTime::parse_from_rfc2822(value).ok();
+
+    match (if_modified_since.and_then(parse), parse(last_modified)) {
+        (Some(if_modified_since), Some(last_modified)) => last_modified <= if_modified_since,
+        _ => false,
+    }
+}
+
+/// HTTPの日付の形式 (例: Sun, 06 Nov 1994 08:49:37 GMT) に変換する
+fn http_date(date: DateTime<Utc>) -> String {
+    date.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
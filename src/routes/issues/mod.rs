@@ -0,0 +1,218 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, Query, State},
+    response::{Html, IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use hyper::StatusCode;
+use serde::Deserialize;
+use sqlx::PgPool;
+
+use crate::{
+    domain::{render_html, TemplateVariables},
+    error::error_chain_fmt,
+    startup::AppState,
+};
+
+mod feed;
+
+pub use feed::*;
+
+/// アーカイブの1ページに表示する号の数
+const ISSUES_PER_PAGE: i64 = 10;
+
+#[derive(thiserror::Error)]
+pub enum ArchiveError {
+    #[error("The newsletter issue was not found")]
+    NotFound,
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for ArchiveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl IntoResponse for ArchiveError {
+    fn into_response(self) -> Response {
+        match self {
+            ArchiveError::NotFound => (
+                StatusCode::NOT_FOUND,
+                layout(
+                    "Not found",
+                    "<p>The issue you are looking for does not exist.</p>",
+                ),
+            )
+                .into_response(),
+            ArchiveError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ArchiveParams {
+    /// 1から始まるページ番号
+    page: Option<i64>,
+}
+
+struct IssueSummary {
+    title: String,
+    slug: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(name = "List published newsletter issues", skip(state))]
+pub async fn list_published_issues(
+    State(state): State<AppState>,
+    Query(params): Query<ArchiveParams>,
+) -> Result<Html<String>, ArchiveError> {
+    // OFFSET と次のページ番号の計算がオーバーフローしないように上限を設ける
+    let page = params
+        .page
+        .unwrap_or(1)
+        .clamp(1, i64::MAX / ISSUES_PER_PAGE - 1);
+
+    // 次のページがあるか判定するために1件多く取得する
+    let mut issues = sqlx::query_as!(
+        IssueSummary,
+        r#"
+        SELECT title, slug, published_at as "published_at!"
+        FROM newsletter_issues
+        WHERE status = 'published'
+          -- セグメントや既定以外のリストに送信した号は全員に公開しない
+          AND segment IS NULL
+          AND mailing_list_id = (SELECT id FROM mailing_lists WHERE slug = 'default')
+        ORDER BY published_at DESC, newsletter_issue_id
+        LIMIT $1
+        OFFSET $2
+        "#,
+        ISSUES_PER_PAGE + 1,
+        (page - 1) * ISSUES_PER_PAGE
+    )
+    .fetch_all(&state.db_state.db_pool)
+    .await
+    .context("Failed to retrieve published newsletter issues")?;
+
+    let has_next_page = issues.len() as i64 > ISSUES_PER_PAGE;
+    issues.truncate(ISSUES_PER_PAGE as usize);
+
+    let items = if issues.is_empty() {
+        "<p>No issues have been published yet.</p>".to_owned()
+    } else {
+        let mut items = String::from("<ul>");
+        for issue in &issues {
+            items.push_str(&format!(
+                r#"<li><a href="/issues/{}">{}</a> <time>{}</time></li>"#,
+                urlencoding::encode(&issue.slug),
+                htmlescape::encode_minimal(&issue.title),
+                issue.published_at.format("%Y-%m-%d"),
+            ));
+        }
+        items.push_str("</ul>");
+        items
+    };
+
+    let mut pagination = Vec::new();
+    if page > 1 {
+        pagination.push(format!(
+            r#"<a href="/issues?page={}">Newer issues</a>"#,
+            page - 1
+        ));
+    }
+    if has_next_page {
+        pagination.push(format!(
+            r#"<a href="/issues?page={}">Older issues</a>"#,
+            page + 1
+        ));
+    }
+
+    Ok(layout(
+        "Archive",
+        &format!(
+            "<h1>Archive</h1>{}<nav>{}</nav>",
+            items,
+            pagination.join(" ")
+        ),
+    ))
+}
+
+#[tracing::instrument(name = "Show a published newsletter issue", skip(state))]
+pub async fn show_published_issue(
+    State(state): State<AppState>,
+    Path(slug): Path<String>,
+) -> Result<Html<String>, ArchiveError> {
+    let issue = get_published_issue(&state.db_state.db_pool, &slug)
+        .await
+        .context("Failed to retrieve a published newsletter issue")?
+        .ok_or(ArchiveError::NotFound)?;
+
+    let content = render_web_version(&issue.html_content);
+
+    Ok(layout(
+        &issue.title,
+        &format!(
+            r#"<article><h1>{}</h1><time>{}</time>{}</article><p><a href="/issues">Back to the archive</a></p>"#,
+            htmlescape::encode_minimal(&issue.title),
+            issue.published_at.format("%Y-%m-%d"),
+            content,
+        ),
+    ))
+}
+
+struct PublishedIssue {
+    title: String,
+    html_content: String,
+    published_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(skip(pool))]
+async fn get_published_issue(
+    pool: &PgPool,
+    slug: &str,
+) -> Result<Option<PublishedIssue>, sqlx::Error> {
+    sqlx::query_as!(
+        PublishedIssue,
+        r#"
+        SELECT title, html_content, published_at as "published_at!"
+        FROM newsletter_issues
+        WHERE slug = $1
+          AND status = 'published'
+          AND segment IS NULL
+          AND mailing_list_id = (SELECT id FROM mailing_lists WHERE slug = 'default')
+        "#,
+        slug
+    )
+    .fetch_optional(pool)
+    .await
+}
+
+/// Web版は特定の購読者向けではないため、購読者ごとの値は埋め込まない
+fn render_web_version(html_content: &str) -> String {
+    let variables = TemplateVariables {
+        name: "reader",
+        email: "",
+        unsubscribe_url: "",
+    };
+
+    render_html(html_content, &variables)
+}
+
+/// ホーム画面と同じレイアウトで本文を表示する
+fn layout(title: &str, body: &str) -> Html<String> {
+    Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>{}</title>
+  </head>
+  <body>
+    {}
+  </body>
+</html>"#,
+        htmlescape::encode_minimal(title),
+        body
+    ))
+}
@@ -2,8 +2,6 @@ use axum::{
     extract::{Query, State},
     response::Html,
 };
-use hmac::{Hmac, Mac};
-use secrecy::ExposeSecret;
 use serde::Deserialize;
 
 use crate::startup::{AppState, HmacSecret};
@@ -18,8 +16,8 @@ impl QueryParams {
     fn verify(self, secret: &HmacSecret) -> Result<String, anyhow::Error> {
         let tag = match self.tag {
             None => return Ok("".into()),
-            Some(tag) => hex::decode(tag),
-        }?;
+            Some(tag) => tag,
+        };
 
         let (query_string, error) = match self.error {
             None => return Ok("".into()),
@@ -29,10 +27,7 @@ impl QueryParams {
             ),
         };
 
-        let mut mac =
-            Hmac::<sha2::Sha256>::new_from_slice(secret.0.expose_secret().as_bytes()).unwrap();
-        mac.update(query_string.as_bytes());
-        mac.verify_slice(&tag)?;
+        secret.verify(&query_string, &tag)?;
 
         Ok(error)
     }
@@ -5,8 +5,7 @@ use axum::{
     response::{IntoResponse, Redirect, Response},
     Form,
 };
-use hmac::{Hmac, Mac};
-use secrecy::{ExposeSecret, Secret};
+use secrecy::Secret;
 use serde::Deserialize;
 
 use crate::{
@@ -63,19 +62,12 @@ pub async fn login(State(state): State<AppState>, Form(form): Form<FormData>) ->
 
             let query_string = format!("error={}", urlencoding::Encoded::new(e.to_string()));
 
-            let hmac_tag = {
-                let mut mac = Hmac::<sha2::Sha256>::new_from_slice(
-                    state.hmac_secret.0.expose_secret().as_bytes(),
-                )
-                .unwrap();
-                mac.update(query_string.as_bytes());
-                mac.finalize().into_bytes()
-            };
+            let hmac_tag = state.hmac_secret.sign(&query_string);
 
             Response::builder()
                 .header(
                     http::header::LOCATION,
-                    format!("/login?{query_string}&tag={hmac_tag:x}"),
+                    format!("/login?{query_string}&tag={hmac_tag}"),
                 )
                 .status(StatusCode::SEE_OTHER)
                 .body(Body::empty())
@@ -0,0 +1,341 @@
+use anyhow::Context;
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use hyper::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgExecutor, PgPool};
+use uuid::Uuid;
+
+use super::{
+    authenticate, parse_segment, publish_issue, resolve_mailing_list, Content, PublishError,
+};
+use crate::startup::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct DraftData {
+    title: String,
+    content: Content,
+}
+
+/// 誤った指定ですぐに全員に配信されないように、未知のフィールドはエラーにする
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PublishDraftData {
+    send_at: Option<DateTime<Utc>>,
+    segment: Option<String>,
+    list: Option<String>,
+    track_opens: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Draft {
+    draft_id: Uuid,
+    author_id: Uuid,
+    title: String,
+    content: Content,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+struct DraftRecord {
+    draft_id: Uuid,
+    author_id: Uuid,
+    title: String,
+    text_content: String,
+    html_content: String,
+    created_at: DateTime<Utc>,
+    updated_at: DateTime<Utc>,
+}
+
+impl From<DraftRecord> for Draft {
+    fn from(r: DraftRecord) -> Self {
+        Self {
+            draft_id: r.draft_id,
+            author_id: r.author_id,
+            title: r.title,
+            content: Content {
+                html: r.html_content,
+                text: r.text_content,
+            },
+            created_at: r.created_at,
+            updated_at: r.updated_at,
+        }
+    }
+}
+
+#[tracing::instrument(
+    name = "Create a newsletter draft",
+    skip(state, headers, body),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn create_draft(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(body): Json<DraftData>,
+) -> Result<impl IntoResponse, PublishError> {
+    let user_id = authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let draft = sqlx::query_as!(
+        DraftRecord,
+        r#"
+        INSERT INTO newsletter_drafts (
+            draft_id,
+            author_id,
+            title,
+            text_content,
+            html_content,
+            created_at,
+            updated_at
+        )
+        VALUES ($1, $2, $3, $4, $5, now(), now())
+        RETURNING draft_id, author_id, title, text_content, html_content, created_at, updated_at
+        "#,
+        Uuid::new_v4(),
+        user_id,
+        body.title,
+        body.content.text,
+        body.content.html
+    )
+    .fetch_one(&state.db_state.db_pool)
+    .await
+    .context("Failed to store a newsletter draft")?;
+
+    Ok((StatusCode::CREATED, Json(Draft::from(draft))))
+}
+
+#[tracing::instrument(
+    name = "List newsletter drafts",
+    skip(state, headers),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn list_drafts(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let drafts = sqlx::query_as!(
+        DraftRecord,
+        r#"
+        SELECT draft_id, author_id, title, text_content, html_content, created_at, updated_at
+        FROM newsletter_drafts
+        ORDER BY updated_at DESC
+        "#
+    )
+    .fetch_all(&state.db_state.db_pool)
+    .await
+    .context("Failed to retrieve newsletter drafts")?
+    .into_iter()
+    .map(Draft::from)
+    .collect::<Vec<_>>();
+
+    Ok(Json(drafts))
+}
+
+#[tracing::instrument(
+    name = "Get a newsletter draft",
+    skip(state, headers),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn get_draft(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(draft_id): Path<Uuid>,
+) -> Result<impl IntoResponse, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let draft = fetch_draft(&state.db_state.db_pool, draft_id)
+        .await
+        .context("Failed to retrieve a newsletter draft")?
+        .ok_or(PublishError::NotFound)?;
+
+    Ok(Json(draft))
+}
+
+#[tracing::instrument(
+    name = "Update a newsletter draft",
+    skip(state, headers, body),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn update_draft(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(draft_id): Path<Uuid>,
+    Json(body): Json<DraftData>,
+) -> Result<impl IntoResponse, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let n_updated = sqlx::query!(
+        r#"
+        UPDATE newsletter_drafts
+        SET
+            title = $2,
+            text_content = $3,
+            html_content = $4,
+            updated_at = now()
+        WHERE draft_id = $1
+        "#,
+        draft_id,
+        body.title,
+        body.content.text,
+        body.content.html
+    )
+    .execute(&state.db_state.db_pool)
+    .await
+    .context("Failed to update a newsletter draft")?
+    .rows_affected();
+
+    if n_updated == 0 {
+        return Err(PublishError::NotFound);
+    }
+
+    let draft = fetch_draft(&state.db_state.db_pool, draft_id)
+        .await
+        .context("Failed to retrieve a newsletter draft")?
+        .ok_or(PublishError::NotFound)?;
+
+    Ok(Json(draft))
+}
+
+#[tracing::instrument(
+    name = "Delete a newsletter draft",
+    skip(state, headers),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn delete_draft(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(draft_id): Path<Uuid>,
+) -> Result<impl IntoResponse, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    if !remove_draft(&state.db_state.db_pool, draft_id)
+        .await
+        .context("Failed to delete a newsletter draft")?
+    {
+        return Err(PublishError::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 下書きを号として配信する
+///
+/// 下書きは配信と同じトランザクションで削除するため、同じ下書きが重複して配信されることはない。
+#[tracing::instrument(
+    name = "Publish a newsletter draft",
+    skip(state, headers, body),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn publish_draft(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(draft_id): Path<Uuid>,
+    body: Bytes,
+) -> Result<Response, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+    let body = parse_publish_draft_data(&body)?;
+    let segment = parse_segment(body.segment.as_deref())?;
+    let mailing_list = resolve_mailing_list(&state.db_state.db_pool, body.list).await?;
+
+    let mut transaction = state
+        .db_state
+        .db_pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    // 同時に配信された場合に備えて、下書きの行をロックする
+    let draft = sqlx::query!(
+        r#"
+        SELECT title, text_content, html_content
+        FROM newsletter_drafts
+        WHERE draft_id = $1
+        FOR UPDATE
+        "#,
+        draft_id
+    )
+    .fetch_optional(&mut transaction)
+    .await
+    .context("Failed to retrieve a newsletter draft")?
+    .ok_or(PublishError::NotFound)?;
+
+    let content = Content {
+        html: draft.html_content,
+        text: draft.text_content,
+    };
+    content.validate()?;
+
+    let response = publish_issue(
+        &mut transaction,
+        &draft.title,
+        &content,
+        body.send_at,
+        mailing_list.id,
+        segment.as_ref(),
+        body.track_opens.unwrap_or(true),
+        &[],
+    )
+    .await
+    .context("Failed to publish a newsletter draft")?;
+
+    remove_draft(&mut transaction, draft_id)
+        .await
+        .context("Failed to delete a published newsletter draft")?;
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to publish a newsletter draft")?;
+
+    Ok(response)
+}
+
+/// 設定を指定せずに配信できるように、空のリクエストボディは既定の設定として扱う
+fn parse_publish_draft_data(body: &[u8]) -> Result<PublishDraftData, PublishError> {
+    if body.is_empty() {
+        return Ok(PublishDraftData::default());
+    }
+
+    serde_json::from_slice(body).map_err(|e| PublishError::ValidationError(e.to_string()))
+}
+
+#[tracing::instrument(name = "Fetch a newsletter draft", skip(pool))]
+async fn fetch_draft(pool: &PgPool, draft_id: Uuid) -> Result<Option<Draft>, sqlx::Error> {
+    let draft = sqlx::query_as!(
+        DraftRecord,
+        r#"
+        SELECT draft_id, author_id, title, text_content, html_content, created_at, updated_at
+        FROM newsletter_drafts
+        WHERE draft_id = $1
+        "#,
+        draft_id
+    )
+    .fetch_optional(pool)
+    .await?
+    .map(Draft::from);
+
+    Ok(draft)
+}
+
+/// 下書きを削除する。対象の下書きが存在しない場合は false を返却する
+#[tracing::instrument(name = "Remove a newsletter draft", skip(executor))]
+async fn remove_draft(executor: impl PgExecutor<'_>, draft_id: Uuid) -> Result<bool, sqlx::Error> {
+    let n_deleted = sqlx::query!(
+        r#"
+        DELETE FROM newsletter_drafts
+        WHERE draft_id = $1
+        "#,
+        draft_id
+    )
+    .execute(executor)
+    .await?
+    .rows_affected();
+
+    Ok(n_deleted > 0)
+}
@@ -0,0 +1,43 @@
+use anyhow::Context;
+use axum::{extract::State, Json};
+use hyper::HeaderMap;
+use serde::Serialize;
+
+use super::{
+    authenticate, parse_attachments, parse_segment, resolve_mailing_list, BodyData, Content,
+    PublishError,
+};
+use crate::{issue_delivery_worker::count_recipients, startup::AppState};
+
+#[derive(Debug, Serialize)]
+pub struct DryRunResult {
+    recipients: i64,
+}
+
+/// 号を配信した場合の配信対象の購読者数を返却する
+///
+/// `POST /newsletters` と同じ内容を検証するが、号の保存や配信タスクの登録は行わない。
+#[tracing::instrument(
+    name = "Dry-run a newsletter issue",
+    skip(state, headers, body),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn dry_run_newsletter(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(body): Json<BodyData>,
+) -> Result<Json<DryRunResult>, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let segment = parse_segment(body.segment.as_deref())?;
+    let content = Content::from(body.content);
+    content.validate()?;
+    parse_attachments(&body.title, &content, body.attachments)?;
+    let mailing_list = resolve_mailing_list(&state.db_state.db_pool, body.list).await?;
+
+    let recipients = count_recipients(&state.db_state.db_pool, mailing_list.id, segment.as_ref())
+        .await
+        .context("Failed to count the recipients of a newsletter issue")?;
+
+    Ok(Json(DryRunResult { recipients }))
+}
@@ -0,0 +1,135 @@
+use anyhow::Context;
+use axum::{extract::State, response::IntoResponse, Json};
+use chrono::{DateTime, Utc};
+use hyper::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use super::{authenticate, PublishError};
+use crate::{domain::MailingListSlug, startup::AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct MailingListData {
+    slug: String,
+    name: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MailingList {
+    pub id: Uuid,
+    pub slug: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(
+    name = "Create a mailing list",
+    skip(state, headers, body),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn create_mailing_list(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(body): Json<MailingListData>,
+) -> Result<impl IntoResponse, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let slug = MailingListSlug::parse(body.slug).map_err(PublishError::ValidationError)?;
+    let name = body.name.trim();
+    if name.is_empty() {
+        return Err(PublishError::ValidationError(
+            "The mailing list name cannot be empty.".into(),
+        ));
+    }
+
+    let mailing_list = sqlx::query_as!(
+        MailingList,
+        r#"
+        INSERT INTO mailing_lists (id, slug, name, created_at)
+        VALUES ($1, $2, $3, now())
+        ON CONFLICT (slug) DO NOTHING
+        RETURNING id, slug, name, created_at
+        "#,
+        Uuid::new_v4(),
+        slug.as_ref(),
+        name
+    )
+    .fetch_optional(&state.db_state.db_pool)
+    .await
+    .context("Failed to store a mailing list")?
+    .ok_or_else(|| {
+        PublishError::ValidationError(format!(
+            "The mailing list {} already exists.",
+            slug.as_ref()
+        ))
+    })?;
+
+    Ok((StatusCode::CREATED, Json(mailing_list)))
+}
+
+#[tracing::instrument(
+    name = "List mailing lists",
+    skip(state, headers),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn list_mailing_lists(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<MailingList>>, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let mailing_lists = sqlx::query_as!(
+        MailingList,
+        r#"
+        SELECT id, slug, name, created_at
+        FROM mailing_lists
+        ORDER BY created_at, slug
+        "#
+    )
+    .fetch_all(&state.db_state.db_pool)
+    .await
+    .context("Failed to retrieve mailing lists")?;
+
+    Ok(Json(mailing_lists))
+}
+
+/// 識別子からメーリングリストを取得する
+#[tracing::instrument(name = "Find a mailing list", skip(executor))]
+pub async fn find_mailing_list(
+    executor: impl PgExecutor<'_>,
+    slug: &MailingListSlug,
+) -> Result<Option<MailingList>, sqlx::Error> {
+    sqlx::query_as!(
+        MailingList,
+        r#"
+        SELECT id, slug, name, created_at
+        FROM mailing_lists
+        WHERE slug = $1
+        "#,
+        slug.as_ref()
+    )
+    .fetch_optional(executor)
+    .await
+}
+
+/// リクエストで指定されたリストを取得する (指定されなかった場合はデフォルトのリスト)
+pub(super) async fn resolve_mailing_list(
+    executor: impl PgExecutor<'_>,
+    list: Option<String>,
+) -> Result<MailingList, PublishError> {
+    let slug = match list {
+        Some(list) => MailingListSlug::parse(list).map_err(PublishError::ValidationError)?,
+        None => MailingListSlug::default(),
+    };
+
+    find_mailing_list(executor, &slug)
+        .await
+        .context("Failed to retrieve a mailing list")?
+        .ok_or_else(|| {
+            PublishError::ValidationError(format!(
+                "The mailing list {} does not exist.",
+                slug.as_ref()
+            ))
+        })
+}
@@ -0,0 +1,93 @@
+use axum::{
+    body::Body,
+    response::{IntoResponse, Response},
+};
+use hyper::{header, HeaderMap, StatusCode};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    authentication::{basic_authentication, validate_credentials, AuthError},
+    error::error_chain_fmt,
+};
+
+mod drafts;
+mod dry_run;
+mod lists;
+mod post;
+mod report;
+mod scheduled;
+mod subscribers;
+mod suppressions;
+mod test_send;
+
+pub use drafts::*;
+pub use dry_run::*;
+pub use lists::*;
+pub use post::*;
+pub use report::*;
+pub use scheduled::*;
+pub use subscribers::*;
+pub use suppressions::*;
+pub use test_send::*;
+
+#[derive(thiserror::Error)]
+pub enum PublishError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("The newsletter issue was not found")]
+    NotFound,
+    #[error("Authentication failed")]
+    AuthError(#[source] anyhow::Error),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for PublishError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl IntoResponse for PublishError {
+    fn into_response(self) -> axum::response::Response {
+        let response = match self {
+            PublishError::ValidationError(_) => Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::empty())
+                .unwrap(),
+            PublishError::NotFound => Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+                .unwrap(),
+            PublishError::UnexpectedError(_) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .body(Body::empty())
+                .unwrap(),
+            PublishError::AuthError(_) => Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .header(header::WWW_AUTHENTICATE, r#"Basic realm="publish""#)
+                .body(Body::empty())
+                .unwrap(),
+        };
+
+        response.into_response()
+    }
+}
+
+/// Basic認証のヘッダーを検証して、認証されたユーザーのIDを返却する
+async fn authenticate(headers: &HeaderMap, pool: &PgPool) -> Result<Uuid, PublishError> {
+    let credentials = basic_authentication(headers).map_err(PublishError::AuthError)?;
+    tracing::Span::current().record("username", &tracing::field::display(&credentials.username));
+
+    let user_id = validate_credentials(credentials, pool)
+        .await
+        .map_err(|e| match e {
+            AuthError::InvalidCredentials(_) => PublishError::AuthError(e.into()),
+            AuthError::UnexpectedError(_) => PublishError::UnexpectedError(e.into()),
+        })?;
+
+    tracing::Span::current().record("user_id", &tracing::field::display(&user_id));
+
+    Ok(user_id)
+}
@@ -0,0 +1,379 @@
+use anyhow::Context;
+use axum::{
+    extract::State,
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use hyper::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use super::{authenticate, resolve_mailing_list, PublishError, ScheduledIssue};
+use crate::{
+    domain::{markdown_to_html, markdown_to_text, validate_template, IssueSlug, Segment},
+    email_client::{message_size, EmailAttachment, MAX_MESSAGE_SIZE},
+    idempotency::{save_response, try_processing, IdempotencyKey, NextAction},
+    issue_delivery_worker::enqueue_delivery_tasks,
+    startup::AppState,
+};
+
+#[derive(Debug, Deserialize)]
+pub struct BodyData {
+    pub(super) title: String,
+    pub(super) content: ContentData,
+    /// 指定された場合はその日時まで配信を保留する
+    pub(super) send_at: Option<DateTime<Utc>>,
+    /// 配信対象を絞り込む条件 (例: `tags include "beta" and not "churned"`)
+    pub(super) segment: Option<String>,
+    /// 配信するメーリングリストの識別子 (省略した場合はデフォルトのリスト)
+    pub(super) list: Option<String>,
+    /// false の場合は開封を計測しない (デフォルトは true)
+    pub(super) track_opens: Option<bool>,
+    /// 全ての購読者に送信する添付ファイル
+    #[serde(default)]
+    pub(super) attachments: Vec<AttachmentData>,
+}
+
+/// 投稿された添付ファイル
+#[derive(Debug, Deserialize)]
+pub struct AttachmentData {
+    name: String,
+    content_type: String,
+    /// Base64 でエンコードされた内容
+    content: String,
+    /// HTML の本文から `cid:` で参照するインライン画像の場合に指定する
+    content_id: Option<String>,
+}
+
+impl TryFrom<AttachmentData> for EmailAttachment {
+    type Error = String;
+
+    fn try_from(attachment: AttachmentData) -> Result<Self, Self::Error> {
+        if attachment.name.trim().is_empty() {
+            return Err("The attachment name must not be empty.".into());
+        }
+        if !attachment.content_type.contains('/') {
+            return Err(format!(
+                "{} is not a valid content type for the attachment {}.",
+                attachment.content_type, attachment.name
+            ));
+        }
+        if base64::engine::general_purpose::STANDARD
+            .decode(&attachment.content)
+            .is_err()
+        {
+            return Err(format!(
+                "The content of the attachment {} is not valid base64.",
+                attachment.name
+            ));
+        }
+
+        Ok(EmailAttachment {
+            name: attachment.name,
+            content_type: attachment.content_type,
+            content: attachment.content,
+            content_id: attachment.content_id,
+        })
+    }
+}
+
+/// 添付ファイルを検証し、添付ファイルを含めたメールが配信サービスの最大サイズに収まるか確認する
+///
+/// 購読者ごとに置換するプレースホルダーの分だけサイズが変わるため、置換前の本文で判定する。
+pub(super) fn parse_attachments(
+    title: &str,
+    content: &Content,
+    attachments: Vec<AttachmentData>,
+) -> Result<Vec<EmailAttachment>, PublishError> {
+    let attachments = attachments
+        .into_iter()
+        .map(EmailAttachment::try_from)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(PublishError::ValidationError)?;
+
+    let size = message_size(title, &content.html, &content.text, &attachments);
+    if size > MAX_MESSAGE_SIZE {
+        return Err(PublishError::ValidationError(format!(
+            "The newsletter issue is {} bytes including attachments, which exceeds the limit of {} bytes.",
+            size, MAX_MESSAGE_SIZE
+        )));
+    }
+
+    Ok(attachments)
+}
+
+/// 配信対象の条件を検証する。指定されなかった場合は確認済みの全ての購読者が対象となる
+pub(super) fn parse_segment(segment: Option<&str>) -> Result<Option<Segment>, PublishError> {
+    segment
+        .map(Segment::parse)
+        .transpose()
+        .map_err(PublishError::ValidationError)
+}
+
+/// 投稿された本文
+///
+/// HTML とテキストの本文を個別に指定するか、Markdown で指定する。
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum ContentData {
+    Markdown { markdown: String },
+    HtmlAndText(Content),
+}
+
+impl From<ContentData> for Content {
+    /// Markdown の場合は安全な HTML と、リンクを脚注にしたテキストに変換する
+    fn from(content: ContentData) -> Self {
+        match content {
+            ContentData::Markdown { markdown } => Content {
+                html: markdown_to_html(&markdown),
+                text: markdown_to_text(&markdown),
+            },
+            ContentData::HtmlAndText(content) => content,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Content {
+    pub(super) html: String,
+    pub(super) text: String,
+}
+
+impl Content {
+    /// 本文で未定義のプレースホルダーが使用されていないか検証する
+    pub(super) fn validate(&self) -> Result<(), PublishError> {
+        validate_template(&self.html)
+            .and_then(|_| validate_template(&self.text))
+            .map_err(PublishError::ValidationError)
+    }
+}
+
+#[tracing::instrument(
+    name = "Publish a newsletterissue",
+    skip(state, headers, body),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn publish_subscriber(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(body): Json<BodyData>,
+) -> Result<Response, PublishError> {
+    let user_id = authenticate(&headers, &state.db_state.db_pool).await?;
+    let idempotency_key = idempotency_key(&headers)?;
+    let segment = parse_segment(body.segment.as_deref())?;
+    let content = Content::from(body.content);
+    content.validate()?;
+    let attachments = parse_attachments(&body.title, &content, body.attachments)?;
+
+    let mailing_list = resolve_mailing_list(&state.db_state.db_pool, body.list).await?;
+
+    // 配信処理はワーカーに任せて、ここでは配信タスクをキューに登録するのみとする
+    let mut transaction = match &idempotency_key {
+        Some(idempotency_key) => match try_processing(
+            &state.db_state.db_pool,
+            idempotency_key,
+            user_id,
+            state.idempotency_retention,
+        )
+        .await?
+        {
+            NextAction::StartProcessing(transaction) => transaction,
+            NextAction::ReturnSavedResponse(saved_response) => return Ok(saved_response),
+        },
+        None => state
+            .db_state
+            .db_pool
+            .begin()
+            .await
+            .context("Failed to acquire a Postgres connection from the pool")?,
+    };
+
+    let response = publish_issue(
+        &mut transaction,
+        &body.title,
+        &content,
+        body.send_at,
+        mailing_list.id,
+        segment.as_ref(),
+        body.track_opens.unwrap_or(true),
+        &attachments,
+    )
+    .await
+    .context("Failed to publish a newsletter issue")?;
+
+    match idempotency_key {
+        Some(idempotency_key) => {
+            let response = save_response(transaction, &idempotency_key, user_id, response).await?;
+            Ok(response)
+        }
+        None => {
+            transaction
+                .commit()
+                .await
+                .context("Failed to commit SQL transaction to store a newsletter issue")?;
+            Ok(response)
+        }
+    }
+}
+
+/// 号を保存して配信タスクを登録する
+///
+/// 配信予定日時が未来の場合は予約配信として保存のみ行い、
+/// 配信予定日時になった時点でスケジューラーが配信タスクを登録する。
+/// 過去の日時が指定された場合は即時配信として扱う。
+#[allow(clippy::too_many_arguments)]
+pub(super) async fn publish_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    title: &str,
+    content: &Content,
+    send_at: Option<DateTime<Utc>>,
+    mailing_list_id: Uuid,
+    segment: Option<&Segment>,
+    track_opens: bool,
+    attachments: &[EmailAttachment],
+) -> Result<Response, anyhow::Error> {
+    let send_at = send_at.filter(|send_at| *send_at > Utc::now());
+
+    let issue_id = insert_newsletter_issue(
+        transaction,
+        title,
+        content,
+        send_at,
+        mailing_list_id,
+        segment,
+        track_opens,
+    )
+    .await
+    .context("Failed to store newsletter issue details")?;
+    insert_newsletter_issue_attachments(transaction, issue_id, attachments)
+        .await
+        .context("Failed to store newsletter issue attachments")?;
+
+    let response = match send_at {
+        Some(send_at) => {
+            let scheduled_issue = ScheduledIssue {
+                newsletter_issue_id: issue_id,
+                title: title.to_owned(),
+                send_at,
+            };
+            (StatusCode::ACCEPTED, Json(scheduled_issue)).into_response()
+        }
+        None => {
+            enqueue_delivery_tasks(transaction, issue_id)
+                .await
+                .context("Failed to enqueue delivery tasks")?;
+            StatusCode::OK.into_response()
+        }
+    };
+
+    Ok(response)
+}
+
+/// 任意の Idempotency-Key ヘッダーを取り出す
+fn idempotency_key(headers: &HeaderMap) -> Result<Option<IdempotencyKey>, PublishError> {
+    let Some(header_value) = headers.get("Idempotency-Key") else {
+        return Ok(None);
+    };
+
+    let key = header_value
+        .to_str()
+        .map_err(|_| {
+            PublishError::ValidationError(
+                "The 'Idempotency-Key' header was not a valid UTF8 string.".into(),
+            )
+        })?
+        .to_string();
+
+    key.try_into()
+        .map(Some)
+        .map_err(|e: anyhow::Error| PublishError::ValidationError(e.to_string()))
+}
+
+#[tracing::instrument(skip_all)]
+async fn insert_newsletter_issue(
+    transaction: &mut Transaction<'_, Postgres>,
+    title: &str,
+    content: &Content,
+    send_at: Option<DateTime<Utc>>,
+    mailing_list_id: Uuid,
+    segment: Option<&Segment>,
+    track_opens: bool,
+) -> Result<Uuid, sqlx::Error> {
+    let newsletter_issue_id = Uuid::new_v4();
+    let slug = IssueSlug::generate(title, newsletter_issue_id);
+
+    let (status, published_at) = match send_at {
+        Some(_) => ("scheduled", None),
+        None => ("published", Some(Utc::now())),
+    };
+
+    sqlx::query!(
+        r#"
+        INSERT INTO newsletter_issues (
+            newsletter_issue_id,
+            title,
+            text_content,
+            html_content,
+            status,
+            send_at,
+            published_at,
+            slug,
+            segment,
+            mailing_list_id,
+            track_opens
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        "#,
+        newsletter_issue_id,
+        title,
+        content.text,
+        content.html,
+        status,
+        send_at,
+        published_at,
+        slug.as_ref(),
+        segment.map(|segment| segment.to_string()),
+        mailing_list_id,
+        track_opens
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(newsletter_issue_id)
+}
+
+#[tracing::instrument(skip_all)]
+async fn insert_newsletter_issue_attachments(
+    transaction: &mut Transaction<'_, Postgres>,
+    newsletter_issue_id: Uuid,
+    attachments: &[EmailAttachment],
+) -> Result<(), sqlx::Error> {
+    for (position, attachment) in attachments.iter().enumerate() {
+        sqlx::query!(
+            r#"
+            INSERT INTO newsletter_issue_attachments (
+                newsletter_issue_id,
+                position,
+                name,
+                content_type,
+                content,
+                content_id
+            )
+            VALUES ($1, $2, $3, $4, $5, $6)
+            "#,
+            newsletter_issue_id,
+            position as i32,
+            attachment.name,
+            attachment.content_type,
+            attachment.content,
+            attachment.content_id
+        )
+        .execute(&mut *transaction)
+        .await?;
+    }
+
+    Ok(())
+}
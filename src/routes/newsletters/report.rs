@@ -0,0 +1,176 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, Query, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use hyper::HeaderMap;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{authenticate, PublishError};
+use crate::startup::AppState;
+
+#[derive(Debug, Deserialize)]
+pub struct ReportParams {
+    /// failed または skipped を指定する（デフォルトは failed）
+    status: Option<String>,
+    /// 購読者のメールアドレスの部分一致で絞り込む
+    email: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliveryReport {
+    newsletter_issue_id: Uuid,
+    counts: DeliveryCounts,
+    failed_recipients: Vec<FailedRecipient>,
+    links: Vec<LinkClicks>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeliveryCounts {
+    total: i64,
+    queued: i64,
+    sent: i64,
+    failed: i64,
+    skipped: i64,
+    /// 開封が記録された購読者数
+    opened: i64,
+}
+
+/// リンクごとのクリック数
+#[derive(Debug, Serialize)]
+pub struct LinkClicks {
+    url: String,
+    /// 全てのクリック数
+    clicks: i64,
+    /// クリックした購読者数
+    subscribers: i64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FailedRecipient {
+    subscriber_id: Uuid,
+    subscriber_email: String,
+    status: String,
+    n_attempts: i16,
+    last_error: Option<String>,
+    updated_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(
+    name = "Get a delivery report of a newsletter issue",
+    skip(state, headers, params),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn delivery_report(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(newsletter_issue_id): Path<Uuid>,
+    Query(params): Query<ReportParams>,
+) -> Result<impl IntoResponse, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let status = params.status.unwrap_or_else(|| "failed".into());
+    if !["failed", "skipped"].contains(&status.as_str()) {
+        return Err(PublishError::ValidationError(format!(
+            "{} is not a supported status. Use either `failed` or `skipped`.",
+            status
+        )));
+    }
+    let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+    let offset = params.offset.unwrap_or(0).max(0);
+
+    let pool = &state.db_state.db_pool;
+
+    sqlx::query!(
+        r#"
+        SELECT newsletter_issue_id
+        FROM newsletter_issues
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_optional(pool)
+    .await
+    .context("Failed to retrieve a newsletter issue")?
+    .ok_or(PublishError::NotFound)?;
+
+    let counts = sqlx::query_as!(
+        DeliveryCounts,
+        r#"
+        SELECT
+            COUNT(*) as "total!",
+            COUNT(*) FILTER (WHERE status = 'queued') as "queued!",
+            COUNT(*) FILTER (WHERE status = 'sent') as "sent!",
+            COUNT(*) FILTER (WHERE status = 'failed') as "failed!",
+            COUNT(*) FILTER (WHERE status = 'skipped') as "skipped!",
+            COUNT(*) FILTER (WHERE first_opened_at IS NOT NULL) as "opened!"
+        FROM issue_deliveries
+        WHERE newsletter_issue_id = $1
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_one(pool)
+    .await
+    .context("Failed to count deliveries of a newsletter issue")?;
+
+    let failed_recipients = sqlx::query_as!(
+        FailedRecipient,
+        r#"
+        SELECT subscriber_id, subscriber_email, status, n_attempts, last_error, updated_at
+        FROM issue_deliveries
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = $2 AND
+            ($3::TEXT IS NULL OR subscriber_email ILIKE '%' || $3 || '%')
+        ORDER BY subscriber_email
+        LIMIT $4
+        OFFSET $5
+        "#,
+        newsletter_issue_id,
+        status,
+        params.email.as_deref().map(escape_like_pattern),
+        limit,
+        offset
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to retrieve failed deliveries of a newsletter issue")?;
+
+    let links = sqlx::query_as!(
+        LinkClicks,
+        r#"
+        SELECT
+            url,
+            SUM(click_count) as "clicks!",
+            COUNT(*) as "subscribers!"
+        FROM issue_link_clicks
+        WHERE newsletter_issue_id = $1
+        GROUP BY url
+        ORDER BY 3 DESC, url
+        "#,
+        newsletter_issue_id
+    )
+    .fetch_all(pool)
+    .await
+    .context("Failed to count link clicks of a newsletter issue")?;
+
+    Ok(Json(DeliveryReport {
+        newsletter_issue_id,
+        counts,
+        failed_recipients,
+        links,
+    }))
+}
+
+/// 入力した文字列のとおりに部分一致させるため、LIKE のワイルドカードをエスケープする
+fn escape_like_pattern(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
@@ -0,0 +1,127 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use hyper::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use super::{authenticate, PublishError};
+use crate::startup::AppState;
+
+#[derive(Debug, Serialize)]
+pub struct ScheduledIssue {
+    pub newsletter_issue_id: Uuid,
+    pub title: String,
+    pub send_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct RescheduleData {
+    send_at: DateTime<Utc>,
+}
+
+#[tracing::instrument(
+    name = "List scheduled newsletter issues",
+    skip(state, headers),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn list_scheduled_issues(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<impl IntoResponse, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let scheduled_issues = get_scheduled_issues(&state.db_state.db_pool)
+        .await
+        .context("Failed to retrieve scheduled newsletter issues")?;
+
+    Ok(Json(scheduled_issues))
+}
+
+#[tracing::instrument(
+    name = "Reschedule a newsletter issue",
+    skip(state, headers, body),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn reschedule_issue(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(newsletter_issue_id): Path<Uuid>,
+    Json(body): Json<RescheduleData>,
+) -> Result<impl IntoResponse, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let scheduled_issue = sqlx::query_as!(
+        ScheduledIssue,
+        r#"
+        UPDATE newsletter_issues
+        SET send_at = $2
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = 'scheduled'
+        RETURNING newsletter_issue_id, title, send_at as "send_at!"
+        "#,
+        newsletter_issue_id,
+        body.send_at
+    )
+    .fetch_optional(&state.db_state.db_pool)
+    .await
+    .context("Failed to reschedule a newsletter issue")?
+    .ok_or(PublishError::NotFound)?;
+
+    Ok(Json(scheduled_issue))
+}
+
+#[tracing::instrument(
+    name = "Cancel a scheduled newsletter issue",
+    skip(state, headers),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn cancel_scheduled_issue(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(newsletter_issue_id): Path<Uuid>,
+) -> Result<impl IntoResponse, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let n_cancelled = sqlx::query!(
+        r#"
+        UPDATE newsletter_issues
+        SET status = 'cancelled'
+        WHERE
+            newsletter_issue_id = $1 AND
+            status = 'scheduled'
+        "#,
+        newsletter_issue_id
+    )
+    .execute(&state.db_state.db_pool)
+    .await
+    .context("Failed to cancel a scheduled newsletter issue")?
+    .rows_affected();
+
+    if n_cancelled == 0 {
+        return Err(PublishError::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[tracing::instrument(name = "Get scheduled newsletter issues", skip(pool))]
+async fn get_scheduled_issues(pool: &PgPool) -> Result<Vec<ScheduledIssue>, sqlx::Error> {
+    sqlx::query_as!(
+        ScheduledIssue,
+        r#"
+        SELECT newsletter_issue_id, title, send_at as "send_at!"
+        FROM newsletter_issues
+        WHERE status = 'scheduled'
+        ORDER BY send_at
+        "#
+    )
+    .fetch_all(pool)
+    .await
+}
@@ -0,0 +1,118 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use hyper::HeaderMap;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{authenticate, PublishError};
+use crate::{domain::parse_tag, startup::AppState};
+
+#[derive(Debug, Deserialize)]
+pub struct TagsData {
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubscriberTags {
+    subscriber_id: Uuid,
+    tags: Vec<String>,
+}
+
+/// 購読者のタグを指定したタグで置き換える
+///
+/// タグは前後の空白を取り除き、重複を除いた上で並べ替えて保存する。
+#[tracing::instrument(
+    name = "Update subscriber tags",
+    skip(state, headers, body),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn update_subscriber_tags(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(subscriber_id): Path<Uuid>,
+    Json(body): Json<TagsData>,
+) -> Result<Json<SubscriberTags>, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let mut tags = body
+        .tags
+        .into_iter()
+        .map(parse_tag)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(PublishError::ValidationError)?;
+    tags.sort();
+    tags.dedup();
+
+    let tags = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET tags = $2
+        WHERE id = $1
+        RETURNING tags
+        "#,
+        subscriber_id,
+        &tags
+    )
+    .fetch_optional(&state.db_state.db_pool)
+    .await
+    .context("Failed to update subscriber tags")?
+    .ok_or(PublishError::NotFound)?
+    .tags;
+
+    Ok(Json(SubscriberTags {
+        subscriber_id,
+        tags,
+    }))
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TrackingData {
+    track_opens: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SubscriberTracking {
+    subscriber_id: Uuid,
+    track_opens: bool,
+}
+
+/// 購読者ごとに開封の計測を有効化・無効化する
+///
+/// 無効化した購読者に送信済みの号についても、以降の開封は記録しない。
+#[tracing::instrument(
+    name = "Update subscriber tracking preference",
+    skip(state, headers, body),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn update_subscriber_tracking(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(subscriber_id): Path<Uuid>,
+    Json(body): Json<TrackingData>,
+) -> Result<Json<SubscriberTracking>, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let track_opens = sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET track_opens = $2
+        WHERE id = $1
+        RETURNING track_opens
+        "#,
+        subscriber_id,
+        body.track_opens
+    )
+    .fetch_optional(&state.db_state.db_pool)
+    .await
+    .context("Failed to update subscriber tracking preference")?
+    .ok_or(PublishError::NotFound)?
+    .track_opens;
+
+    Ok(Json(SubscriberTracking {
+        subscriber_id,
+        track_opens,
+    }))
+}
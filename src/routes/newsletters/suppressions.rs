@@ -0,0 +1,173 @@
+use anyhow::Context;
+use axum::{
+    extract::{Path, State},
+    response::IntoResponse,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use hyper::{HeaderMap, StatusCode};
+use serde::{Deserialize, Serialize};
+use sqlx::PgExecutor;
+use uuid::Uuid;
+
+use super::{authenticate, PublishError};
+use crate::{domain::SuppressionTarget, startup::AppState};
+
+/// 管理者が指定できる送信停止の理由 (bounce と complaint は Webhook から登録する)
+const ADMIN_REASONS: [&str; 2] = ["admin", "legal"];
+
+#[derive(Debug, Deserialize)]
+pub struct SuppressionData {
+    /// メールアドレスまたはドメイン
+    value: String,
+    reason: Option<String>,
+    note: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Suppression {
+    pub id: Uuid,
+    pub kind: String,
+    pub value: String,
+    pub reason: String,
+    pub note: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// 送信停止リストにメールアドレスまたはドメインを追加する
+///
+/// 登録済みの場合は既存の登録内容を返却する。
+#[tracing::instrument(
+    name = "Add a suppression",
+    skip(state, headers, body),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn create_suppression(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(body): Json<SuppressionData>,
+) -> Result<impl IntoResponse, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let target = SuppressionTarget::parse(&body.value).map_err(PublishError::ValidationError)?;
+    let reason = body.reason.as_deref().unwrap_or("admin");
+    if !ADMIN_REASONS.contains(&reason) {
+        return Err(PublishError::ValidationError(format!(
+            "The reason must be one of {:?}.",
+            ADMIN_REASONS
+        )));
+    }
+
+    let suppression = suppress(
+        &state.db_state.db_pool,
+        &target,
+        reason,
+        body.note.as_deref(),
+    )
+    .await
+    .context("Failed to store a suppression")?;
+
+    Ok((StatusCode::CREATED, Json(suppression)))
+}
+
+#[tracing::instrument(
+    name = "List suppressions",
+    skip(state, headers),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn list_suppressions(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> Result<Json<Vec<Suppression>>, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let suppressions = sqlx::query_as!(
+        Suppression,
+        r#"
+        SELECT id, kind, value, reason, note, created_at
+        FROM suppressions
+        ORDER BY created_at, value
+        "#
+    )
+    .fetch_all(&state.db_state.db_pool)
+    .await
+    .context("Failed to retrieve suppressions")?;
+
+    Ok(Json(suppressions))
+}
+
+#[tracing::instrument(
+    name = "Delete a suppression",
+    skip(state, headers),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn delete_suppression(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path(suppression_id): Path<Uuid>,
+) -> Result<impl IntoResponse, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let n_deleted = sqlx::query!("DELETE FROM suppressions WHERE id = $1", suppression_id)
+        .execute(&state.db_state.db_pool)
+        .await
+        .context("Failed to delete a suppression")?
+        .rows_affected();
+
+    if n_deleted == 0 {
+        return Err(PublishError::NotFound);
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// 送信停止リストに登録し、登録済みの場合は既存の登録内容を返却する
+#[tracing::instrument(name = "Suppress an address or domain", skip(executor))]
+pub async fn suppress(
+    executor: impl PgExecutor<'_>,
+    target: &SuppressionTarget,
+    reason: &str,
+    note: Option<&str>,
+) -> Result<Suppression, sqlx::Error> {
+    // 競合した場合も RETURNING で既存の行を返却するために、値を変えずに更新する
+    sqlx::query_as!(
+        Suppression,
+        r#"
+        INSERT INTO suppressions (id, kind, value, reason, note, created_at)
+        VALUES ($1, $2, $3, $4, $5, now())
+        ON CONFLICT (kind, value) DO UPDATE SET kind = EXCLUDED.kind
+        RETURNING id, kind, value, reason, note, created_at
+        "#,
+        Uuid::new_v4(),
+        target.kind(),
+        target.value(),
+        reason,
+        note
+    )
+    .fetch_one(executor)
+    .await
+}
+
+/// メールアドレスまたはそのドメインが送信停止リストに登録されているか確認する
+#[tracing::instrument(name = "Check the suppression list", skip(executor))]
+pub async fn is_suppressed(
+    executor: impl PgExecutor<'_>,
+    email: &str,
+) -> Result<bool, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT EXISTS (
+            SELECT 1
+            FROM suppressions
+            WHERE
+                (kind = 'address' AND value = lower($1)) OR
+                (kind = 'domain' AND value = split_part(lower($1), '@', 2))
+        ) AS "is_suppressed!"
+        "#,
+        email
+    )
+    .fetch_one(executor)
+    .await?;
+
+    Ok(row.is_suppressed)
+}
@@ -0,0 +1,175 @@
+use anyhow::Context;
+use axum::{extract::State, Json};
+use hyper::HeaderMap;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use super::{authenticate, is_suppressed, parse_attachments, BodyData, Content, PublishError};
+use crate::{
+    domain::{SubscriberEmail, TemplateVariables},
+    email_client::EmailMessage,
+    issue_delivery_worker::render_email,
+    routes::unsubscribe_url,
+    startup::AppState,
+};
+
+/// 1回のテスト送信で指定できる宛先の最大数
+const MAX_TEST_RECIPIENTS: usize = 5;
+
+/// 送信停止リストに登録された宛先のエラーコード (配信サービスの無効な宛先と同じ値)
+const SUPPRESSED_ERROR_CODE: i64 = 406;
+
+/// 一時的なエラーで送信できなかった宛先のエラーコード (SMTP の一時的な拒否と同じ値)
+const DEFERRED_ERROR_CODE: i64 = 451;
+
+#[derive(Debug, Deserialize)]
+pub struct TestSendData {
+    #[serde(flatten)]
+    newsletter: BodyData,
+    recipients: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestSendResult {
+    sent: Vec<String>,
+    failed: Vec<TestSendFailure>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TestSendFailure {
+    email: String,
+    error_code: i64,
+    message: String,
+}
+
+/// 公開前の号を指定した宛先にのみ送信する
+///
+/// 購読者や配信状況の記録は変更せず、件名に `[TEST]` を付けて送信する。
+/// 購読者ごとの値には、テスト用の名前と配信停止されることのない購読者IDを使用する。
+#[tracing::instrument(
+    name = "Send a test newsletter",
+    skip(state, headers, body),
+    fields(username=tracing::field::Empty, user_id=tracing::field::Empty)
+)]
+pub async fn test_send_newsletter(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Json(body): Json<TestSendData>,
+) -> Result<Json<TestSendResult>, PublishError> {
+    authenticate(&headers, &state.db_state.db_pool).await?;
+
+    let parsed_recipients = parse_recipients(body.recipients)?;
+    let content = Content::from(body.newsletter.content);
+    content.validate()?;
+    let attachments = parse_attachments(
+        &body.newsletter.title,
+        &content,
+        body.newsletter.attachments,
+    )?;
+
+    // 送信停止リストに登録された宛先には送信せず、失敗として返却する
+    let mut recipients = Vec::with_capacity(parsed_recipients.len());
+    let mut suppressed = Vec::new();
+    for email in parsed_recipients {
+        if is_suppressed(&state.db_state.db_pool, email.as_ref())
+            .await
+            .context("Failed to check the suppression list")?
+        {
+            suppressed.push(email);
+        } else {
+            recipients.push(email);
+        }
+    }
+
+    let subject = format!("[TEST] {}", body.newsletter.title);
+    // 未公開の号には Web版がないため、アーカイブの一覧へのリンクにする
+    let web_version_url = format!("{}/issues", state.base_url.0);
+    let unsubscribe_url = unsubscribe_url(&state.base_url, &state.hmac_secret, Uuid::nil());
+
+    let contents: Vec<_> = recipients
+        .iter()
+        .map(|email| {
+            let variables = TemplateVariables {
+                name: "Test subscriber",
+                email: email.as_ref(),
+                unsubscribe_url: &unsubscribe_url,
+            };
+            render_email(&content.html, &content.text, &variables, &web_version_url)
+        })
+        .collect();
+    let messages: Vec<_> = recipients
+        .iter()
+        .zip(&contents)
+        .map(|(email, (html_content, text_content))| EmailMessage {
+            recipient: email,
+            subject: &subject,
+            html_content,
+            text_content,
+            unsubscribe_url: None,
+            attachments: &attachments,
+        })
+        .collect();
+
+    let response = state
+        .email_client
+        .send_batch(&messages)
+        .await
+        .context("Failed to send a test newsletter")?;
+
+    Ok(Json(TestSendResult {
+        sent: response
+            .succeeded
+            .into_iter()
+            .map(|sent| sent.recipient.to_string())
+            .collect(),
+        failed: response
+            .failed
+            .into_iter()
+            .map(|failed| TestSendFailure {
+                email: failed.recipient.to_string(),
+                error_code: failed.error_code,
+                message: failed.message,
+            })
+            .chain(
+                response
+                    .deferred
+                    .into_iter()
+                    .map(|deferred| TestSendFailure {
+                        email: deferred.recipient.to_string(),
+                        error_code: DEFERRED_ERROR_CODE,
+                        message: deferred.error.to_string(),
+                    }),
+            )
+            .chain(suppressed.into_iter().map(|email| TestSendFailure {
+                email: email.to_string(),
+                error_code: SUPPRESSED_ERROR_CODE,
+                message: "The address is suppressed".into(),
+            }))
+            .collect(),
+    }))
+}
+
+fn parse_recipients(recipients: Vec<String>) -> Result<Vec<SubscriberEmail>, PublishError> {
+    if recipients.is_empty() {
+        return Err(PublishError::ValidationError(
+            "At least one recipient is required.".into(),
+        ));
+    }
+    if recipients.len() > MAX_TEST_RECIPIENTS {
+        return Err(PublishError::ValidationError(format!(
+            "A test newsletter can be sent to at most {} recipients.",
+            MAX_TEST_RECIPIENTS
+        )));
+    }
+
+    let mut parsed: Vec<SubscriberEmail> = Vec::with_capacity(recipients.len());
+    for recipient in recipients {
+        let email = SubscriberEmail::parse(recipient).map_err(PublishError::ValidationError)?;
+        // 同じ宛先に重複して送信しない
+        if !parsed.iter().any(|p| p.as_ref() == email.as_ref()) {
+            parsed.push(email);
+        }
+    }
+
+    Ok(parsed)
+}
@@ -7,9 +7,10 @@ use sqlx::{Postgres, Transaction};
 use uuid::Uuid;
 
 use crate::{
-    domain::{NewSubscriber, SubscriberEmail, SubscriberName},
-    email_client::EmailClient,
+    domain::{MailingListSlug, NewSubscriber, SubscriberEmail, SubscriberName},
+    email_client::{EmailClient, TransportError},
     error::error_chain_fmt,
+    routes::{find_mailing_list, is_suppressed},
     startup::AppState,
 };
 
@@ -17,6 +18,8 @@ use crate::{
 pub struct Subscribe {
     name: String,
     email: String,
+    /// 購読するメーリングリストの識別子 (省略した場合はデフォルトのリスト)
+    list: Option<String>,
 }
 
 impl TryFrom<Subscribe> for NewSubscriber {
@@ -25,7 +28,15 @@ impl TryFrom<Subscribe> for NewSubscriber {
     fn try_from(value: Subscribe) -> Result<Self, Self::Error> {
         let name = SubscriberName::parse(value.name)?;
         let email = SubscriberEmail::parse(value.email)?;
-        Ok(NewSubscriber { email, name })
+        let mailing_list = match value.list {
+            Some(list) => MailingListSlug::parse(list)?,
+            None => MailingListSlug::default(),
+        };
+        Ok(NewSubscriber {
+            email,
+            name,
+            mailing_list,
+        })
     }
 }
 
@@ -67,7 +78,8 @@ pub async fn subscribe(
     State(app_state): State<AppState>,
     Form(form): Form<Subscribe>,
 ) -> Result<impl IntoResponse, SubscriberError> {
-    let new_subscriber = form.try_into().map_err(SubscriberError::ValidationError)?;
+    let new_subscriber: NewSubscriber =
+        form.try_into().map_err(SubscriberError::ValidationError)?;
 
     let mut transaction = app_state
         .db_state
@@ -76,7 +88,17 @@ pub async fn subscribe(
         .await
         .context("Failed to acquire a Postgres connection from the pool")?;
 
-    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber)
+    let mailing_list = find_mailing_list(&mut transaction, &new_subscriber.mailing_list)
+        .await
+        .context("Failed to retrieve the mailing list to subscribe to.")?
+        .ok_or_else(|| {
+            SubscriberError::ValidationError(format!(
+                "The mailing list {} does not exist.",
+                new_subscriber.mailing_list.as_ref()
+            ))
+        })?;
+
+    let subscriber_id = insert_subscriber(&mut transaction, &new_subscriber, mailing_list.id)
         .await
         .context("Failed to insert new subscriber in the database.")?;
 
@@ -86,14 +108,25 @@ pub async fn subscribe(
         .await
         .context("Failed to store the confirmation token for a new subscriber.")?;
 
+    let is_suppressed = is_suppressed(&mut transaction, new_subscriber.email.as_ref())
+        .await
+        .context("Failed to check the suppression list.")?;
+
     transaction
         .commit()
         .await
         .context("Failed to commit SQL transaction to store a new subscriber.")?;
 
+    // 送信停止中であることが分からないように、メールを送信せずに同じレスポンスを返却する
+    if is_suppressed {
+        tracing::info!("Skipping the confirmation email to a suppressed address");
+        return Ok(StatusCode::CREATED);
+    }
+
     send_confirmation_email(
         &app_state.email_client,
         new_subscriber,
+        &mailing_list.name,
         &app_state.base_url.0,
         &subscription_token,
     )
@@ -110,18 +143,20 @@ pub async fn subscribe(
 pub async fn insert_subscriber(
     transaction: &mut Transaction<'_, Postgres>,
     new_subscriber: &NewSubscriber,
+    mailing_list_id: Uuid,
 ) -> Result<Uuid, sqlx::Error> {
     let subscriber_id = Uuid::new_v4();
 
     sqlx::query!(
         r#"
-        INSERT INTO subscriptions (id, email, name, subscribed_at, status)
-        VALUES ($1, $2, $3, $4, 'pending_confirmation')
+        INSERT INTO subscriptions (id, email, name, subscribed_at, status, mailing_list_id)
+        VALUES ($1, $2, $3, $4, 'pending_confirmation', $5)
         "#,
         subscriber_id,
         new_subscriber.email.as_ref(),
         new_subscriber.name.as_ref(),
-        Utc::now()
+        Utc::now(),
+        mailing_list_id
     )
     .execute(transaction)
     .await
@@ -167,27 +202,31 @@ pub async fn store_token(
 pub async fn send_confirmation_email(
     email_client: &EmailClient,
     new_subscriber: NewSubscriber,
+    mailing_list_name: &str,
     base_url: &str,
     subscription_token: &str,
-) -> Result<(), reqwest::Error> {
+) -> Result<(), TransportError> {
     let confirmation_link = format!(
         "{}/subscriptions/confirm?subscription_token={}",
         base_url, subscription_token
     );
 
     let html_body = format!(
-        "Welcome to our newsletter!<br />\
+        "Welcome to {}!<br />\
         Click <a href=\"{}\">here</a> to confirm your subscription.",
+        htmlescape::encode_minimal(mailing_list_name),
         confirmation_link
     );
     let plain_body = format!(
-        "Welcome to our newsletter\nVisit {} to confirm your subscriptions",
-        confirmation_link
+        "Welcome to {}\nVisit {} to confirm your subscriptions",
+        mailing_list_name, confirmation_link
     );
 
     email_client
         .send_email(&new_subscriber.email, "Welcome!", &html_body, &plain_body)
-        .await
+        .await?;
+
+    Ok(())
 }
 
 /// 十分長いトークンを生成する。以下のルールに従う
@@ -0,0 +1,165 @@
+use anyhow::Context;
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse},
+    Json,
+};
+use serde::Deserialize;
+use serde_json::json;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    error::error_chain_fmt,
+    startup::{AppState, ApplicationBaseUrl, HmacSecret},
+};
+
+/// 購読者ごとの配信停止用のURLを作成する
+///
+/// 購読者IDを改ざんして他の購読者の配信を停止できないように HMAC タグで署名する。
+/// リンク先と署名の検証がずれないように、配信停止のルートと同じモジュールで作成する。
+pub fn unsubscribe_url(
+    base_url: &ApplicationBaseUrl,
+    hmac_secret: &HmacSecret,
+    subscriber_id: Uuid,
+) -> String {
+    let query_string = format!("subscriber_id={}", subscriber_id);
+    let tag = hmac_secret.sign(&query_string);
+
+    format!("{}{}", base_url.0, unsubscribe_path(&query_string, &tag))
+}
+
+fn unsubscribe_path(query_string: &str, tag: &str) -> String {
+    format!("/subscriptions/unsubscribe?{}&tag={}", query_string, tag)
+}
+
+#[derive(Deserialize)]
+pub struct UnsubscribeParameters {
+    subscriber_id: Uuid,
+    tag: String,
+}
+
+impl UnsubscribeParameters {
+    fn verify(&self, state: &AppState) -> Result<(), UnsubscribeError> {
+        state
+            .hmac_secret
+            .verify(&self.query_string(), &self.tag)
+            .map_err(UnsubscribeError::InvalidSignature)
+    }
+
+    fn query_string(&self) -> String {
+        format!("subscriber_id={}", self.subscriber_id)
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum UnsubscribeError {
+    #[error("The unsubscribe link has an invalid signature")]
+    InvalidSignature(#[source] anyhow::Error),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for UnsubscribeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl IntoResponse for UnsubscribeError {
+    fn into_response(self) -> axum::response::Response {
+        let (status_code, error_message) = match self {
+            UnsubscribeError::InvalidSignature(_) => (StatusCode::UNAUTHORIZED, "Invalid Link"),
+            UnsubscribeError::UnexpectedError(_) => {
+                (StatusCode::INTERNAL_SERVER_ERROR, "Unexpected Error")
+            }
+        };
+
+        let body = Json(json!({ "error": error_message }));
+
+        (status_code, body).into_response()
+    }
+}
+
+/// 配信停止の確認画面を表示する
+///
+/// メールのリンクを開いただけで配信を停止しないように、停止はフォームの送信で行う。
+#[tracing::instrument(
+    name = "Show the unsubscribe confirmation page",
+    skip(state, params),
+    fields(subscriber_id = %params.subscriber_id)
+)]
+pub async fn unsubscribe_form(
+    State(state): State<AppState>,
+    Query(params): Query<UnsubscribeParameters>,
+) -> Result<Html<String>, UnsubscribeError> {
+    params.verify(&state)?;
+
+    let action = unsubscribe_path(&params.query_string(), &params.tag);
+
+    Ok(Html(format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribe</title>
+  </head>
+  <body>
+    <p>Do you want to stop receiving our newsletter?</p>
+    <form action="{}" method="post">
+      <input type="hidden" name="List-Unsubscribe" value="One-Click">
+      <button type="submit">Unsubscribe</button>
+    </form>
+  </body>
+</html>"#,
+        htmlescape::encode_minimal(&action)
+    )))
+}
+
+/// 購読者の配信を停止する
+///
+/// RFC 8058 のワンクリックでの配信停止としてメールボックスプロバイダーからも呼び出されるため、
+/// 本文の内容には依存せず署名されたURLのみで処理する。
+#[tracing::instrument(
+    name = "Unsubscribe a subscriber",
+    skip(state, params),
+    fields(subscriber_id = %params.subscriber_id)
+)]
+pub async fn unsubscribe(
+    State(state): State<AppState>,
+    Query(params): Query<UnsubscribeParameters>,
+) -> Result<impl IntoResponse, UnsubscribeError> {
+    params.verify(&state)?;
+
+    unsubscribe_subscriber(&state.db_state.db_pool, params.subscriber_id)
+        .await
+        .context("Failed to update the subscriber status to 'unsubscribed'")?;
+
+    Ok(Html(
+        r#"<!DOCTYPE html>
+<html lang="en">
+  <head>
+    <meta http-equiv="content-type" content="text/html; charset=utf-8">
+    <title>Unsubscribed</title>
+  </head>
+  <body>
+    <p>You have been unsubscribed.</p>
+  </body>
+</html>"#,
+    ))
+}
+
+#[tracing::instrument(name = "Mark subscriber as unsubscribed", skip(pool))]
+async fn unsubscribe_subscriber(pool: &PgPool, subscriber_id: Uuid) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions SET status = 'unsubscribed' WHERE id = $1
+        "#,
+        subscriber_id
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
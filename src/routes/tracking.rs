@@ -0,0 +1,231 @@
+use anyhow::Context;
+use axum::{
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{error::error_chain_fmt, startup::AppState};
+
+/// 透明な 1x1 の GIF 画像
+const TRACKING_PIXEL: &[u8] = &[
+    0x47, 0x49, 0x46, 0x38, 0x39, 0x61, 0x01, 0x00, 0x01, 0x00, 0x80, 0x00, 0x00, 0x00, 0x00, 0x00,
+    0xff, 0xff, 0xff, 0x21, 0xf9, 0x04, 0x01, 0x00, 0x00, 0x00, 0x00, 0x2c, 0x00, 0x00, 0x00, 0x00,
+    0x01, 0x00, 0x01, 0x00, 0x00, 0x02, 0x02, 0x44, 0x01, 0x00, 0x3b,
+];
+
+/// 記録する User-Agent の最大文字数
+const MAX_USER_AGENT_LENGTH: usize = 512;
+
+#[derive(Deserialize)]
+pub struct OpenParameters {
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    tag: String,
+}
+
+#[derive(Deserialize)]
+pub struct ClickParameters {
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    url: String,
+    tag: String,
+}
+
+#[derive(thiserror::Error)]
+pub enum TrackingError {
+    #[error("The tracking link has an invalid signature")]
+    InvalidSignature(#[source] anyhow::Error),
+    #[error("The tracking link has an invalid target")]
+    InvalidTarget(#[source] anyhow::Error),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for TrackingError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl IntoResponse for TrackingError {
+    fn into_response(self) -> Response {
+        match self {
+            TrackingError::InvalidSignature(_) | TrackingError::InvalidTarget(_) => {
+                StatusCode::BAD_REQUEST.into_response()
+            }
+            TrackingError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
+
+/// 号の開封を記録して 1x1 の画像を返却する
+///
+/// 号または購読者で開封の計測が無効化されている場合は記録せずに画像のみ返却する。
+#[tracing::instrument(
+    name = "Record an issue open",
+    skip(state, headers, params),
+    fields(
+        newsletter_issue_id = %params.newsletter_issue_id,
+        subscriber_id = %params.subscriber_id,
+    )
+)]
+pub async fn track_open(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Query(params): Query<OpenParameters>,
+) -> Result<Response, TrackingError> {
+    let message = format!(
+        "newsletter_issue_id={}&subscriber_id={}",
+        params.newsletter_issue_id, params.subscriber_id
+    );
+    state
+        .hmac_secret
+        .verify(&message, &params.tag)
+        .map_err(TrackingError::InvalidSignature)?;
+
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|user_agent| user_agent.to_str().ok())
+        .map(|user_agent| user_agent.chars().take(MAX_USER_AGENT_LENGTH).collect());
+
+    record_open(
+        &state.db_state.db_pool,
+        params.newsletter_issue_id,
+        params.subscriber_id,
+        user_agent,
+    )
+    .await
+    .context("Failed to record an issue open")?;
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, "image/gif"),
+            (
+                header::CACHE_CONTROL,
+                "no-store, no-cache, must-revalidate, private",
+            ),
+        ],
+        TRACKING_PIXEL,
+    )
+        .into_response())
+}
+
+/// リンクのクリックを記録して元のURLへリダイレクトする
+///
+/// 署名されたURLにのみリダイレクトするため、任意のURLへのリダイレクトには使用できない。
+/// クリックの記録に失敗した場合も、購読者がリンク先を表示できるようにリダイレクトする。
+#[tracing::instrument(
+    name = "Record a link click",
+    skip(state, params),
+    fields(
+        newsletter_issue_id = %params.newsletter_issue_id,
+        subscriber_id = %params.subscriber_id,
+    )
+)]
+pub async fn track_click(
+    State(state): State<AppState>,
+    Query(params): Query<ClickParameters>,
+) -> Result<Response, TrackingError> {
+    let message = format!(
+        "newsletter_issue_id={}&subscriber_id={}&url={}",
+        params.newsletter_issue_id,
+        params.subscriber_id,
+        urlencoding::encode(&params.url)
+    );
+    state
+        .hmac_secret
+        .verify(&message, &params.tag)
+        .map_err(TrackingError::InvalidSignature)?;
+
+    // Location ヘッダーに使用できるように、ASCII 以外の文字はパーセントエンコードする
+    let target = reqwest::Url::parse(params.url.trim())
+        .context("Failed to parse the link target")
+        .map_err(TrackingError::InvalidTarget)?;
+
+    if let Err(e) = record_click(
+        &state.db_state.db_pool,
+        params.newsletter_issue_id,
+        params.subscriber_id,
+        &params.url,
+    )
+    .await
+    {
+        tracing::error!(
+            error.cause_chain = ?e,
+            error.message = %e,
+            "Failed to record a link click",
+        );
+    }
+
+    Ok((StatusCode::FOUND, [(header::LOCATION, target.to_string())]).into_response())
+}
+
+#[tracing::instrument(skip(pool))]
+async fn record_click(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    url: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        INSERT INTO issue_link_clicks (
+            newsletter_issue_id,
+            subscriber_id,
+            url,
+            click_count,
+            first_clicked_at,
+            last_clicked_at
+        )
+        VALUES ($1, $2, $3, 1, now(), now())
+        ON CONFLICT (newsletter_issue_id, subscriber_id, url) DO UPDATE
+        SET
+            click_count = issue_link_clicks.click_count + 1,
+            last_clicked_at = now()
+        "#,
+        newsletter_issue_id,
+        subscriber_id,
+        url
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
+
+#[tracing::instrument(skip(pool))]
+async fn record_open(
+    pool: &PgPool,
+    newsletter_issue_id: Uuid,
+    subscriber_id: Uuid,
+    user_agent: Option<String>,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE issue_deliveries d
+        SET
+            first_opened_at = COALESCE(d.first_opened_at, now()),
+            open_count = d.open_count + 1,
+            user_agent = $3
+        FROM newsletter_issues i, subscriptions s
+        WHERE
+            d.newsletter_issue_id = $1 AND
+            d.subscriber_id = $2 AND
+            i.newsletter_issue_id = d.newsletter_issue_id AND
+            s.id = d.subscriber_id AND
+            i.track_opens AND
+            s.track_opens
+        "#,
+        newsletter_issue_id,
+        subscriber_id,
+        user_agent
+    )
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
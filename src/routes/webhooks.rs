@@ -0,0 +1,273 @@
+use anyhow::Context;
+use axum::{
+    extract::State,
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use secrecy::ExposeSecret;
+use serde::Deserialize;
+use sqlx::{Postgres, Transaction};
+use uuid::Uuid;
+
+use crate::{
+    authentication::basic_authentication,
+    domain::{FeedbackKind, SuppressionTarget},
+    error::error_chain_fmt,
+    routes::suppress,
+    startup::AppState,
+};
+
+/// Postmark のバウンスと苦情の Webhook のペイロード
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+pub struct PostmarkEvent {
+    record_type: String,
+    #[serde(rename = "ID")]
+    id: i64,
+    #[serde(rename = "Type")]
+    bounce_type: Option<String>,
+    #[serde(rename = "MessageID")]
+    message_id: Option<String>,
+    email: String,
+    bounced_at: Option<DateTime<Utc>>,
+    description: Option<String>,
+}
+
+#[derive(thiserror::Error)]
+pub enum WebhookError {
+    #[error("{0}")]
+    ValidationError(String),
+    #[error("Authentication failed")]
+    AuthError(#[source] anyhow::Error),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+impl std::fmt::Debug for WebhookError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        error_chain_fmt(self, f)
+    }
+}
+
+impl IntoResponse for WebhookError {
+    fn into_response(self) -> Response {
+        match self {
+            WebhookError::ValidationError(_) => StatusCode::BAD_REQUEST.into_response(),
+            WebhookError::AuthError(_) => (
+                StatusCode::UNAUTHORIZED,
+                [(header::WWW_AUTHENTICATE, r#"Basic realm="webhooks""#)],
+            )
+                .into_response(),
+            WebhookError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+        }
+    }
+}
+
+/// Postmark から通知されたバウンスと苦情を記録し、購読の状態に反映する
+///
+/// ハードバウンスと苦情は即座に、ソフトバウンスは設定した回数に達した時点で
+/// 購読者を配信対象から除外し、メールアドレスを送信停止リストに登録する。
+/// 再送された通知は記録済みのため何もしない。
+#[tracing::instrument(
+    name = "Receive a Postmark webhook",
+    skip(headers, state, body),
+    fields(record_type = tracing::field::Empty, provider_event_id = tracing::field::Empty)
+)]
+pub async fn receive_postmark_webhook(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    body: String,
+) -> Result<StatusCode, WebhookError> {
+    authenticate(&headers, &state)?;
+
+    let event: PostmarkEvent = serde_json::from_str(&body)
+        .map_err(|e| WebhookError::ValidationError(format!("Invalid payload: {}", e)))?;
+    tracing::Span::current()
+        .record("record_type", tracing::field::display(&event.record_type))
+        .record("provider_event_id", event.id);
+    let kind = FeedbackKind::classify(&event.record_type, event.bounce_type.as_deref());
+
+    let mut transaction = state
+        .db_state
+        .db_pool
+        .begin()
+        .await
+        .context("Failed to acquire a Postgres connection from the pool")?;
+
+    let is_new = insert_email_event(&mut transaction, &event, kind)
+        .await
+        .context("Failed to record an email event")?;
+    if is_new {
+        // 購読の状態と送信停止リストに登録する理由
+        let outcome = match kind {
+            FeedbackKind::HardBounce => Some(("bounced", "bounce")),
+            FeedbackKind::SpamComplaint => Some(("complained", "complaint")),
+            FeedbackKind::SoftBounce => {
+                let n_soft_bounces = count_soft_bounces(&mut transaction, &event.email)
+                    .await
+                    .context("Failed to count soft bounces")?;
+                (n_soft_bounces >= i64::from(state.email_webhook.soft_bounce_threshold))
+                    .then_some(("bounced", "bounce"))
+            }
+            FeedbackKind::Other => None,
+        };
+
+        if let Some((status, reason)) = outcome {
+            update_subscription_status(&mut transaction, &event.email, status)
+                .await
+                .context("Failed to update the subscription status")?;
+
+            match SuppressionTarget::parse(&event.email) {
+                Ok(target @ SuppressionTarget::Address(_)) => {
+                    suppress(
+                        &mut transaction,
+                        &target,
+                        reason,
+                        event.description.as_deref(),
+                    )
+                    .await
+                    .context("Failed to add the address to the suppression list")?;
+                }
+                _ => tracing::warn!(
+                    email = %event.email,
+                    "Skipping the suppression of an invalid email address",
+                ),
+            }
+        }
+    }
+
+    transaction
+        .commit()
+        .await
+        .context("Failed to commit SQL transaction to store an email event")?;
+
+    Ok(StatusCode::OK)
+}
+
+/// 設定した認証情報と一致するか検証する
+///
+/// HMAC タグで比較することで、比較にかかる時間から正しい値を推測できないようにする。
+fn authenticate(headers: &HeaderMap, state: &AppState) -> Result<(), WebhookError> {
+    let credentials = basic_authentication(headers).map_err(WebhookError::AuthError)?;
+    let settings = &state.email_webhook;
+    let hmac_secret = &state.hmac_secret;
+
+    hmac_secret
+        .verify(&credentials.username, &hmac_secret.sign(&settings.username))
+        .and_then(|_| {
+            hmac_secret.verify(
+                credentials.password.expose_secret(),
+                &hmac_secret.sign(settings.password.expose_secret()),
+            )
+        })
+        .context("Invalid webhook credentials")
+        .map_err(WebhookError::AuthError)
+}
+
+/// 通知を記録し、初めて受け取った通知の場合は `true` を返却する
+#[tracing::instrument(skip_all)]
+async fn insert_email_event(
+    transaction: &mut Transaction<'_, Postgres>,
+    event: &PostmarkEvent,
+    kind: FeedbackKind,
+) -> Result<bool, sqlx::Error> {
+    let subscriber_id = match &event.message_id {
+        Some(message_id) => find_subscriber_by_message_id(transaction, message_id).await?,
+        None => None,
+    };
+
+    let result = sqlx::query!(
+        r#"
+        INSERT INTO email_events (
+            record_type,
+            provider_event_id,
+            kind,
+            email,
+            subscriber_id,
+            provider_message_id,
+            description,
+            occurred_at,
+            received_at
+        )
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, now())
+        ON CONFLICT DO NOTHING
+        "#,
+        event.record_type,
+        event.id,
+        kind.as_str(),
+        event.email,
+        subscriber_id,
+        event.message_id,
+        event.description,
+        event.bounced_at
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(result.rows_affected() > 0)
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn find_subscriber_by_message_id(
+    transaction: &mut Transaction<'_, Postgres>,
+    message_id: &str,
+) -> Result<Option<Uuid>, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT subscriber_id
+        FROM issue_deliveries
+        WHERE provider_message_id = $1
+        "#,
+        message_id
+    )
+    .fetch_optional(transaction)
+    .await?;
+
+    Ok(row.map(|r| r.subscriber_id))
+}
+
+#[tracing::instrument(skip(transaction))]
+async fn count_soft_bounces(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+) -> Result<i64, sqlx::Error> {
+    let row = sqlx::query!(
+        r#"
+        SELECT COUNT(*) AS "n_soft_bounces!"
+        FROM email_events
+        WHERE lower(email) = lower($1) AND kind = 'soft_bounce'
+        "#,
+        email
+    )
+    .fetch_one(transaction)
+    .await?;
+
+    Ok(row.n_soft_bounces)
+}
+
+/// 宛先のメールアドレスの全てのリストの購読を配信対象から除外する
+///
+/// 既に配信を停止している購読はそのままにする。
+#[tracing::instrument(skip(transaction))]
+async fn update_subscription_status(
+    transaction: &mut Transaction<'_, Postgres>,
+    email: &str,
+    status: &str,
+) -> Result<(), sqlx::Error> {
+    sqlx::query!(
+        r#"
+        UPDATE subscriptions
+        SET status = $2
+        WHERE
+            lower(email) = lower($1) AND
+            status IN ('pending_confirmation', 'confirmed')
+        "#,
+        email,
+        status
+    )
+    .execute(transaction)
+    .await?;
+
+    Ok(())
+}
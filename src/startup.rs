@@ -1,17 +1,27 @@
 use std::net::SocketAddr;
 
 use axum::{
-    extract::FromRef,
-    routing::{get, post},
+    extract::{DefaultBodyLimit, FromRef},
+    routing::{delete, get, post, put},
     Router,
 };
-use secrecy::Secret;
+use hmac::{Hmac, Mac};
+use secrecy::{ExposeSecret, Secret};
 use sqlx::{postgres::PgPoolOptions, PgPool};
 
 use crate::{
-    configuration::{DatabaseSettings, Settings},
-    email_client::EmailClient,
-    routes::{confirm, health_check, home, login, login_form, publish_subscriber, subscribe},
+    configuration::{DatabaseSettings, EmailWebhookSettings, Settings},
+    email_client::{DevMailbox, EmailClient, MAX_MESSAGE_SIZE},
+    routes::{
+        atom_feed, cancel_scheduled_issue, confirm, create_draft, create_mailing_list,
+        create_suppression, delete_draft, delete_suppression, delivery_report, dry_run_newsletter,
+        get_draft, health_check, home, list_drafts, list_mailbox, list_mailing_lists,
+        list_published_issues, list_scheduled_issues, list_suppressions, login, login_form,
+        publish_draft, publish_subscriber, receive_postmark_webhook, reschedule_issue, rss_feed,
+        show_mailbox_email, show_mailbox_email_html, show_published_issue, subscribe,
+        test_send_newsletter, track_click, track_open, unsubscribe, unsubscribe_form, update_draft,
+        update_subscriber_tags, update_subscriber_tracking,
+    },
 };
 
 #[derive(Clone)]
@@ -20,6 +30,8 @@ pub struct AppState {
     pub email_client: EmailClient,
     pub base_url: ApplicationBaseUrl,
     pub hmac_secret: HmacSecret,
+    pub idempotency_retention: chrono::Duration,
+    pub email_webhook: EmailWebhookSettings,
 }
 
 #[derive(Clone)]
@@ -33,18 +45,46 @@ pub struct ApplicationBaseUrl(pub String);
 #[derive(Clone)]
 pub struct HmacSecret(pub Secret<String>);
 
+impl HmacSecret {
+    /// メッセージの HMAC タグを16進数の文字列で返却する
+    pub fn sign(&self, message: &str) -> String {
+        let mut mac = self.mac();
+        mac.update(message.as_bytes());
+        format!("{:x}", mac.finalize().into_bytes())
+    }
+
+    /// 16進数の文字列で渡された HMAC タグがメッセージに対応しているか検証する
+    pub fn verify(&self, message: &str, tag: &str) -> Result<(), anyhow::Error> {
+        let tag = hex::decode(tag)?;
+
+        let mut mac = self.mac();
+        mac.update(message.as_bytes());
+        mac.verify_slice(&tag)?;
+
+        Ok(())
+    }
+
+    fn mac(&self) -> Hmac<sha2::Sha256> {
+        Hmac::<sha2::Sha256>::new_from_slice(self.0.expose_secret().as_bytes()).unwrap()
+    }
+}
+
 impl AppState {
     pub fn new(
         db_pool: PgPool,
         email_client: EmailClient,
         base_url: String,
         hmac_secret: Secret<String>,
+        idempotency_retention: chrono::Duration,
+        email_webhook: EmailWebhookSettings,
     ) -> Self {
         Self {
             db_state: DbState { db_pool },
             email_client,
             base_url: ApplicationBaseUrl(base_url),
             hmac_secret: HmacSecret(hmac_secret),
+            idempotency_retention,
+            email_webhook,
         }
     }
 }
@@ -61,44 +101,111 @@ impl FromRef<AppState> for EmailClient {
     }
 }
 
-pub fn create_app(state: AppState) -> Router {
-    Router::new()
+/// 添付ファイルを含む号を投稿できるように、既定の 2MB より大きくしたリクエストボディの上限
+const NEWSLETTER_BODY_LIMIT: usize = 2 * MAX_MESSAGE_SIZE;
+
+/// 開発用の受信箱がある場合は `/_dev/mailbox` で送信したメールを確認できる
+pub fn create_app(state: AppState, dev_mailbox: Option<DevMailbox>) -> Router {
+    let app = Router::new()
         .route("/health_check", get(health_check))
         .route("/subscriptions", post(subscribe))
         .route("/subscriptions/confirm", get(confirm))
-        .route("/newsletters", post(publish_subscriber))
+        .route(
+            "/subscriptions/unsubscribe",
+            get(unsubscribe_form).post(unsubscribe),
+        )
+        .route(
+            "/subscribers/:subscriber_id/tags",
+            put(update_subscriber_tags),
+        )
+        .route(
+            "/subscribers/:subscriber_id/tracking",
+            put(update_subscriber_tracking),
+        )
+        .route(
+            "/suppressions",
+            get(list_suppressions).post(create_suppression),
+        )
+        .route("/suppressions/:suppression_id", delete(delete_suppression))
+        .route(
+            "/mailing_lists",
+            get(list_mailing_lists).post(create_mailing_list),
+        )
+        .route(
+            "/newsletters",
+            post(publish_subscriber).layer(DefaultBodyLimit::max(NEWSLETTER_BODY_LIMIT)),
+        )
+        .route(
+            "/newsletters/test",
+            post(test_send_newsletter).layer(DefaultBodyLimit::max(NEWSLETTER_BODY_LIMIT)),
+        )
+        .route(
+            "/newsletters/dry-run",
+            post(dry_run_newsletter).layer(DefaultBodyLimit::max(NEWSLETTER_BODY_LIMIT)),
+        )
+        .route("/newsletters/scheduled", get(list_scheduled_issues))
+        .route(
+            "/newsletters/scheduled/:newsletter_issue_id",
+            put(reschedule_issue).delete(cancel_scheduled_issue),
+        )
+        .route("/newsletters/drafts", get(list_drafts).post(create_draft))
+        .route(
+            "/newsletters/drafts/:draft_id",
+            get(get_draft).put(update_draft).delete(delete_draft),
+        )
+        .route("/newsletters/drafts/:draft_id/publish", post(publish_draft))
+        .route(
+            "/newsletters/:newsletter_issue_id/report",
+            get(delivery_report),
+        )
+        .route("/issues", get(list_published_issues))
+        .route("/issues/:slug", get(show_published_issue))
+        .route("/feed.rss", get(rss_feed))
+        .route("/feed.atom", get(atom_feed))
+        .route("/tracking/open", get(track_open))
+        .route("/tracking/click", get(track_click))
+        .route("/webhooks/postmark", post(receive_postmark_webhook))
         .route("/", get(home))
         .route("/login", get(login_form))
         .route("/login", post(login))
-        .with_state(state)
+        .with_state(state);
+
+    match dev_mailbox {
+        Some(mailbox) => app.merge(
+            Router::new()
+                .route("/_dev/mailbox", get(list_mailbox))
+                .route("/_dev/mailbox/:email_id", get(show_mailbox_email))
+                .route("/_dev/mailbox/:email_id/html", get(show_mailbox_email_html))
+                .with_state(mailbox),
+        ),
+        None => app,
+    }
 }
 
 pub struct Application {
     addr: SocketAddr,
     app: Router,
+    email_client: EmailClient,
 }
 
 impl Application {
     pub fn build(configuration: Settings) -> Self {
         let connection_pool = get_connection_pool(&configuration.database);
 
-        let sender_email = configuration
+        let email_webhook = configuration.email_client.webhook.clone();
+        let dev_mailbox = configuration
             .email_client
-            .sender()
-            .expect("Invalid sender email address.");
-        let timeout = configuration.email_client.timeout();
-        let email_client = EmailClient::new(
-            configuration.email_client.base_url,
-            sender_email,
-            configuration.email_client.authorization_token,
-            timeout,
-        );
+            .dev_mailbox(configuration.application.environment);
+        let email_client = configuration.email_client.client(dev_mailbox.clone());
 
+        let idempotency_retention = configuration.application.idempotency_retention();
         let app_state = AppState::new(
             connection_pool,
-            email_client,
+            email_client.clone(),
             configuration.application.base_url,
             configuration.application.hmac_secret,
+            idempotency_retention,
+            email_webhook,
         );
 
         // 実行する
@@ -110,8 +217,9 @@ impl Application {
         .expect("SockerAddr is not valid");
 
         Self {
-            app: create_app(app_state),
+            app: create_app(app_state, dev_mailbox),
             addr,
+            email_client,
         }
     }
 
@@ -123,6 +231,11 @@ impl Application {
         self.app.clone()
     }
 
+    /// APIとワーカーで送信数の上限を共有するために同じクライアントを返却する
+    pub fn email_client(&self) -> EmailClient {
+        self.email_client.clone()
+    }
+
     pub async fn run_until_stopped(self) -> Result<(), hyper::Error> {
         axum::Server::bind(&self.addr)
             .serve(self.app.into_make_service())
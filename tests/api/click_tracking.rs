@@ -0,0 +1,193 @@
+use axum::{
+    body::Body,
+    http::{self, Request, StatusCode},
+};
+use tower::ServiceExt;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{setup_app, TestApp};
+
+/// 号を配信して、送信されたメールの HTML とテキストの本文を返却する
+async fn publish_and_dispatch(app: &mut TestApp, html: &str) -> (String, String) {
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::POST,
+            "/newsletters",
+            Some(serde_json::json!({
+                "title": "Newsletter title",
+                "content": {
+                    "text": "Read https://example.com/post",
+                    "html": html,
+                },
+            })),
+        )
+        .await;
+    assert_eq!(status_code, StatusCode::OK);
+    app.dispatch_all_pending_emails().await;
+
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let messages: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+
+    (
+        messages[0]["HtmlBody"].as_str().unwrap().to_owned(),
+        messages[0]["TextBody"].as_str().unwrap().to_owned(),
+    )
+}
+
+/// HTML の本文からクリックを記録するURLのパスとクエリを全て取り出す
+fn click_uris(html: &str) -> Vec<String> {
+    html.match_indices("/tracking/click?")
+        .map(|(start, _)| {
+            let end = start + html[start..].find('"').unwrap();
+            html[start..end].replace("&amp;", "&")
+        })
+        .collect()
+}
+
+async fn click(app: &TestApp, uri: &str) -> (StatusCode, Option<String>) {
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri(uri)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app
+        .app
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("Failed to execute request");
+
+    let location = response
+        .headers()
+        .get(http::header::LOCATION)
+        .map(|value| value.to_str().unwrap().to_owned());
+
+    (response.status(), location)
+}
+
+#[tokio::test]
+async fn links_in_the_html_part_are_rewritten_to_signed_redirects() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    // Act
+    let (html, text) = publish_and_dispatch(
+        &mut app,
+        r#"<p><a href="https://example.com/post?a=1&amp;b=2">Post</a> <a href="mailto:editor@example.com">Mail</a> <a href="{{ unsubscribe_url }}">Unsubscribe</a></p>"#,
+    )
+    .await;
+
+    // Assert
+    assert!(!html.contains(r#"href="https://example.com/post"#));
+    assert!(html.contains(r#"href="mailto:editor@example.com""#));
+    // 配信停止やブラウザで表示するためのリンクは書き換えない
+    assert!(html.contains(&format!(
+        r#"href="{}/subscriptions/unsubscribe?"#,
+        app.base_url.0
+    )));
+    assert!(html.contains(&format!(r#"href="{}/issues/"#, app.base_url.0)));
+    assert_eq!(click_uris(&html).len(), 1);
+    assert_eq!(
+        text.lines().next().unwrap(),
+        "Read https://example.com/post"
+    );
+}
+
+#[tokio::test]
+async fn clicks_are_recorded_before_redirecting_to_the_original_link() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let (html, _) = publish_and_dispatch(
+        &mut app,
+        r#"<a href="https://example.com/post?a=1&amp;b=2">Post</a>"#,
+    )
+    .await;
+    let uri = click_uris(&html)[0].clone();
+
+    // Act
+    let (status, location) = click(&app, &uri).await;
+    click(&app, &uri).await;
+
+    // Assert
+    assert_eq!(status, StatusCode::FOUND);
+    assert_eq!(
+        location.as_deref(),
+        Some("https://example.com/post?a=1&b=2")
+    );
+
+    let clicks = sqlx::query!("SELECT url, click_count FROM issue_link_clicks")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(clicks.len(), 1);
+    assert_eq!(clicks[0].url, "https://example.com/post?a=1&b=2");
+    assert_eq!(clicks[0].click_count, 2);
+
+    let newsletter_issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+    let (_, report) = app
+        .authenticated_request(
+            http::Method::GET,
+            &format!("/newsletters/{}/report", newsletter_issue_id),
+            None,
+        )
+        .await;
+    assert_eq!(
+        report["links"],
+        serde_json::json!([{
+            "url": "https://example.com/post?a=1&b=2",
+            "clicks": 2,
+            "subscribers": 1,
+        }])
+    );
+}
+
+#[tokio::test]
+async fn redirects_to_other_targets_are_rejected() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let (html, _) =
+        publish_and_dispatch(&mut app, r#"<a href="https://example.com/post">Post</a>"#).await;
+    let uri = click_uris(&html)[0].clone();
+    let tampered = uri.replace(
+        urlencoding::encode("https://example.com/post").as_ref(),
+        urlencoding::encode("https://evil.example.com").as_ref(),
+    );
+    assert_ne!(uri, tampered);
+
+    // Act
+    let (status, location) = click(&app, &tampered).await;
+
+    // Assert
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    assert_eq!(location, None);
+    let n_clicks = sqlx::query!("SELECT COUNT(*) AS n_clicks FROM issue_link_clicks")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n_clicks;
+    assert_eq!(n_clicks, Some(0));
+}
@@ -0,0 +1,237 @@
+use axum::http::{self, StatusCode};
+use uuid::Uuid;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{setup_app, TestApp};
+
+async fn get_report(app: &TestApp, newsletter_issue_id: Uuid, query: &str) -> serde_json::Value {
+    let (status_code, body) = app
+        .authenticated_request(
+            http::Method::GET,
+            &format!("/newsletters/{}/report{}", newsletter_issue_id, query),
+            None,
+        )
+        .await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    body
+}
+
+#[tokio::test]
+async fn successful_deliveries_are_recorded_with_the_provider_message_id() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                "MessageID": "0a129aee-e1cd-480d-b08d-4f48548ff48d",
+                "ErrorCode": 0,
+                "Message": "OK"
+            }])),
+        )
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_issue_id = app
+        .publish_newsletter("Newsletter title")
+        .await
+        .newsletter_issue_id;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let report = get_report(&app, newsletter_issue_id, "").await;
+    assert_eq!(report["counts"]["total"], 1);
+    assert_eq!(report["counts"]["sent"], 1);
+    assert_eq!(report["failed_recipients"].as_array().unwrap().len(), 0);
+
+    let delivery = sqlx::query!("SELECT n_attempts, provider_message_id FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.n_attempts, 1);
+    assert_eq!(
+        delivery.provider_message_id.as_deref(),
+        Some("0a129aee-e1cd-480d-b08d-4f48548ff48d")
+    );
+}
+
+#[tokio::test]
+async fn deliveries_waiting_for_a_retry_are_reported_as_queued() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_issue_id = app
+        .publish_newsletter("Newsletter title")
+        .await
+        .newsletter_issue_id;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let report = get_report(&app, newsletter_issue_id, "").await;
+    assert_eq!(report["counts"]["queued"], 1);
+
+    let delivery = sqlx::query!("SELECT n_attempts, last_error FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.n_attempts, 1);
+    assert!(delivery.last_error.is_some());
+}
+
+#[tokio::test]
+async fn deliveries_are_reported_as_failed_after_too_many_attempts() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_issue_id = app
+        .publish_newsletter("Newsletter title")
+        .await
+        .newsletter_issue_id;
+    for _ in 0..5 {
+        app.dispatch_all_pending_emails().await;
+        // 再試行の待ち時間を経過したことにする
+        sqlx::query!("UPDATE issue_delivery_queue SET execute_after = now()")
+            .execute(&app.db_pool)
+            .await
+            .unwrap();
+    }
+
+    // Assert
+    let report = get_report(&app, newsletter_issue_id, "").await;
+    assert_eq!(report["counts"]["total"], 2);
+    assert_eq!(report["counts"]["failed"], 2);
+
+    let failed_recipients = report["failed_recipients"].as_array().unwrap();
+    assert_eq!(failed_recipients.len(), 2);
+    assert_eq!(failed_recipients[0]["status"], "failed");
+    assert_eq!(failed_recipients[0]["n_attempts"], 5);
+
+    // メールアドレスで絞り込む
+    let email = failed_recipients[0]["subscriber_email"].as_str().unwrap();
+    let report = get_report(&app, newsletter_issue_id, &format!("?email={}", email)).await;
+    assert_eq!(report["failed_recipients"].as_array().unwrap().len(), 1);
+
+    // ワイルドカードは文字どおりに検索する
+    let report = get_report(&app, newsletter_issue_id, "?email=%25").await;
+    assert!(report["failed_recipients"].as_array().unwrap().is_empty());
+}
+
+#[tokio::test]
+async fn recipients_rejected_by_the_email_server_are_reported_as_failed() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                "ErrorCode": 406,
+                "Message": "You tried to send to a recipient that has been marked as inactive."
+            }])),
+        )
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_issue_id = app
+        .publish_newsletter("Newsletter title")
+        .await
+        .newsletter_issue_id;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    // 宛先ごとのエラーは再試行せずに失敗として記録する
+    let report = get_report(&app, newsletter_issue_id, "").await;
+    assert_eq!(report["counts"]["failed"], 1);
+
+    let failed_recipients = report["failed_recipients"].as_array().unwrap();
+    assert_eq!(failed_recipients[0]["n_attempts"], 1);
+    assert!(failed_recipients[0]["last_error"]
+        .as_str()
+        .unwrap()
+        .starts_with("ErrorCode 406"));
+
+    let n_tasks = sqlx::query!("SELECT COUNT(*) AS n_tasks FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n_tasks;
+    assert_eq!(n_tasks, Some(0));
+}
+
+#[tokio::test]
+async fn subscribers_with_invalid_emails_are_reported_as_skipped() {
+    // Arrange
+    let mut app = setup_app().await;
+
+    sqlx::query!(
+        "INSERT INTO subscriptions (id, email, name, subscribed_at, status, mailing_list_id)
+        SELECT $1, 'not-an-email', 'shimopino', now(), 'confirmed', id
+        FROM mailing_lists WHERE slug = 'default'",
+        Uuid::new_v4()
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    let newsletter_issue_id = app
+        .publish_newsletter("Newsletter title")
+        .await
+        .newsletter_issue_id;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let report = get_report(&app, newsletter_issue_id, "?status=skipped").await;
+    assert_eq!(report["counts"]["skipped"], 1);
+
+    let failed_recipients = report["failed_recipients"].as_array().unwrap();
+    assert_eq!(failed_recipients.len(), 1);
+    assert_eq!(failed_recipients[0]["subscriber_email"], "not-an-email");
+}
+
+#[tokio::test]
+async fn report_of_an_unknown_issue_is_not_found() {
+    // Arrange
+    let app = setup_app().await;
+
+    // Act
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::GET,
+            &format!("/newsletters/{}/report", Uuid::new_v4()),
+            None,
+        )
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,79 @@
+use axum::http::StatusCode;
+use zero2prod::email_client::EmailProvider;
+
+use crate::helpers::{setup_app, setup_app_with};
+
+#[tokio::test]
+async fn confirmation_emails_are_listed_in_the_dev_mailbox() {
+    // Arrange
+    let mut test_app = setup_app_with(|c| c.email_client.provider = EmailProvider::Mailbox).await;
+
+    // Act
+    let (status, _) = test_app
+        .post_subscription("name=shimopino&email=shimopino%40example.com".to_string())
+        .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    // Assert
+    let (status, body) = test_app.get_html("/_dev/mailbox").await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("Welcome!"));
+    assert!(body.contains("shimopino@example.com"));
+
+    // メール配信サービスには送信されない
+    let received_requests = test_app.email_server.received_requests().await.unwrap();
+    assert!(received_requests.is_empty());
+}
+
+#[tokio::test]
+async fn an_email_in_the_dev_mailbox_can_be_viewed() {
+    // Arrange
+    let mut test_app = setup_app_with(|c| c.email_client.provider = EmailProvider::Mailbox).await;
+    test_app
+        .post_subscription("name=shimopino&email=shimopino%40example.com".to_string())
+        .await;
+    let (_, body) = test_app.get_html("/_dev/mailbox").await;
+    let link = body
+        .split(r#"<a href=""#)
+        .nth(1)
+        .and_then(|rest| rest.split('"').next())
+        .unwrap()
+        .to_owned();
+
+    // Act
+    let (status, detail) = test_app.get_html(&link).await;
+    let (html_status, html) = test_app.get_html(&format!("{}/html", link)).await;
+
+    // Assert
+    assert_eq!(status, StatusCode::OK);
+    assert!(detail.contains("<h2>Text</h2>"));
+    assert!(detail.contains("/subscriptions/confirm?subscription_token="));
+    assert_eq!(html_status, StatusCode::OK);
+    assert!(html.contains("/subscriptions/confirm?subscription_token="));
+}
+
+#[tokio::test]
+async fn unknown_emails_in_the_dev_mailbox_return_404() {
+    // Arrange
+    let test_app = setup_app_with(|c| c.email_client.provider = EmailProvider::Mailbox).await;
+
+    // Act
+    let (status, _) = test_app
+        .get_html(&format!("/_dev/mailbox/{}", uuid::Uuid::new_v4()))
+        .await;
+
+    // Assert
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn the_dev_mailbox_is_not_available_with_other_providers() {
+    // Arrange
+    let test_app = setup_app().await;
+
+    // Act
+    let (status, _) = test_app.get_html("/_dev/mailbox").await;
+
+    // Assert
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
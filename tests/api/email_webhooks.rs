@@ -0,0 +1,241 @@
+use axum::{
+    body::Body,
+    http::{self, Request, StatusCode},
+};
+use secrecy::ExposeSecret;
+use tower::ServiceExt;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{basic_auth_value, setup_app, TestApp};
+
+const MESSAGE_ID: &str = "0a129aee-e1cd-480d-b08d-4f48548ff48d";
+
+async fn post_webhook(app: &TestApp, body: &serde_json::Value, password: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri("/webhooks/postmark")
+        .header(http::header::CONTENT_TYPE, "application/json")
+        .header(
+            http::header::AUTHORIZATION,
+            basic_auth_value(&app.email_webhook.username, &password.to_owned()),
+        )
+        .body(Body::from(body.to_string()))
+        .unwrap();
+
+    let response = app
+        .app
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("Failed to execute request");
+
+    response.status()
+}
+
+async fn post_authenticated_webhook(app: &TestApp, body: &serde_json::Value) -> StatusCode {
+    let password = app.email_webhook.password.expose_secret().clone();
+    post_webhook(app, body, &password).await
+}
+
+fn bounce(id: i64, bounce_type: &str, email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "Bounce",
+        "ID": id,
+        "Type": bounce_type,
+        "TypeCode": 1,
+        "MessageID": MESSAGE_ID,
+        "Email": email,
+        "BouncedAt": "2023-06-04T10:35:12.9070259Z",
+        "Description": "The server was unable to deliver your message",
+    })
+}
+
+fn spam_complaint(id: i64, email: &str) -> serde_json::Value {
+    serde_json::json!({
+        "RecordType": "SpamComplaint",
+        "ID": id,
+        "Type": "SpamComplaint",
+        "TypeCode": 512,
+        "MessageID": MESSAGE_ID,
+        "Email": email,
+        "BouncedAt": "2023-06-04T10:35:12Z",
+    })
+}
+
+/// 確認済みの購読者に号を配信し、購読者のメールアドレスを返却する
+async fn deliver_issue_to_subscriber(app: &mut TestApp) -> String {
+    app.create_confirmed_subscriber().await;
+
+    let _mock_guard = Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(serde_json::json!([{
+                "MessageID": MESSAGE_ID,
+                "ErrorCode": 0,
+                "Message": "OK"
+            }])),
+        )
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+    app.publish_newsletter("Newsletter title").await;
+    app.dispatch_all_pending_emails().await;
+
+    app.subscriber_email().await
+}
+
+#[tokio::test]
+async fn webhooks_without_valid_credentials_are_rejected() {
+    // Arrange
+    let mut app = setup_app().await;
+    let email = deliver_issue_to_subscriber(&mut app).await;
+
+    // Act
+    let status_code = post_webhook(&app, &bounce(1, "HardBounce", &email), "wrong-password").await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::UNAUTHORIZED);
+    assert_eq!(app.subscription_status().await, "confirmed");
+}
+
+#[tokio::test]
+async fn hard_bounces_mark_the_subscriber_as_bounced() {
+    // Arrange
+    let mut app = setup_app().await;
+    let email = deliver_issue_to_subscriber(&mut app).await;
+
+    // Act
+    let status_code = post_authenticated_webhook(&app, &bounce(1, "HardBounce", &email)).await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(app.subscription_status().await, "bounced");
+
+    let event = sqlx::query!(
+        r#"
+        SELECT e.kind, e.subscriber_id, e.occurred_at, s.id AS expected_subscriber_id
+        FROM email_events e, subscriptions s
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(event.kind, "hard_bounce");
+    assert_eq!(event.subscriber_id, Some(event.expected_subscriber_id));
+    assert!(event.occurred_at.is_some());
+
+    let suppression = sqlx::query!("SELECT kind, value, reason FROM suppressions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(suppression.kind, "address");
+    assert_eq!(suppression.value, email.to_lowercase());
+    assert_eq!(suppression.reason, "bounce");
+}
+
+#[tokio::test]
+async fn spam_complaints_mark_the_subscriber_as_complained() {
+    // Arrange
+    let mut app = setup_app().await;
+    let email = deliver_issue_to_subscriber(&mut app).await;
+
+    // Act
+    let status_code = post_authenticated_webhook(&app, &spam_complaint(1, &email)).await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(app.subscription_status().await, "complained");
+}
+
+#[tokio::test]
+async fn soft_bounces_mark_the_subscriber_as_bounced_after_the_threshold() {
+    // Arrange
+    let mut app = setup_app().await;
+    let email = deliver_issue_to_subscriber(&mut app).await;
+    let threshold = app.email_webhook.soft_bounce_threshold as i64;
+
+    for id in 1..threshold {
+        // Act
+        post_authenticated_webhook(&app, &bounce(id, "SoftBounce", &email)).await;
+
+        // Assert
+        assert_eq!(app.subscription_status().await, "confirmed");
+    }
+
+    // Act
+    post_authenticated_webhook(&app, &bounce(threshold, "SoftBounce", &email)).await;
+
+    // Assert
+    assert_eq!(app.subscription_status().await, "bounced");
+}
+
+#[tokio::test]
+async fn redelivered_webhooks_are_recorded_only_once() {
+    // Arrange
+    let mut app = setup_app().await;
+    let email = deliver_issue_to_subscriber(&mut app).await;
+    let threshold = app.email_webhook.soft_bounce_threshold;
+
+    // Act
+    for _ in 0..threshold {
+        let status_code = post_authenticated_webhook(&app, &bounce(1, "SoftBounce", &email)).await;
+        assert_eq!(status_code, StatusCode::OK);
+    }
+
+    // Assert
+    assert_eq!(app.subscription_status().await, "confirmed");
+    let n_events = sqlx::query!("SELECT COUNT(*) AS n_events FROM email_events")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n_events;
+    assert_eq!(n_events, Some(1));
+}
+
+#[tokio::test]
+async fn malformed_payloads_are_rejected() {
+    // Arrange
+    let app = setup_app().await;
+
+    // Act
+    let status_code =
+        post_authenticated_webhook(&app, &serde_json::json!({ "RecordType": "Bounce" })).await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_bounced_subscribers() {
+    // Arrange
+    let mut app = setup_app().await;
+    let email = deliver_issue_to_subscriber(&mut app).await;
+    post_authenticated_webhook(&app, &bounce(1, "HardBounce", &email)).await;
+
+    Mock::given(path("/email/batch"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.authenticated_request(
+        http::Method::POST,
+        "/newsletters",
+        Some(serde_json::json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+            },
+        })),
+    )
+    .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    // Mock verifies on Drop that we haven't sent the newsletter email
+}
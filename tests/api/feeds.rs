@@ -0,0 +1,137 @@
+use axum::{
+    body::Body,
+    http::{self, HeaderMap, Request, StatusCode},
+};
+use tower::ServiceExt;
+
+use crate::helpers::{setup_app, TestApp};
+
+async fn get_feed(
+    app: &TestApp,
+    uri: &str,
+    headers: &[(http::HeaderName, &str)],
+) -> (StatusCode, HeaderMap, String) {
+    let mut request = Request::builder().method(http::Method::GET).uri(uri);
+    for (name, value) in headers {
+        request = request.header(name, *value);
+    }
+
+    let response = app
+        .app
+        .clone()
+        .oneshot(request.body(Body::empty()).unwrap())
+        .await
+        .expect("Failed to execute request");
+
+    let status = response.status();
+    let headers = response.headers().to_owned();
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    (status, headers, String::from_utf8(bytes.to_vec()).unwrap())
+}
+
+#[tokio::test]
+async fn rss_feed_contains_published_issues_with_absolute_links() {
+    // Arrange
+    let mut app = setup_app().await;
+    let slug = app.publish_newsletter("Weekly <update>").await.slug;
+
+    // Act
+    let (status_code, headers, body) = get_feed(&app, "/feed.rss", &[]).await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(
+        headers[http::header::CONTENT_TYPE],
+        "application/rss+xml; charset=utf-8"
+    );
+    assert!(body.contains(r#"<rss version="2.0""#));
+    assert!(body.contains("<title>Weekly &lt;update&gt;</title>"));
+    assert!(body.contains(&format!("<link>{}/issues/{}</link>", app.base_url.0, slug)));
+    assert!(body.contains("&lt;p&gt;Newsletter body as HTML for reader&lt;/p&gt;"));
+}
+
+#[tokio::test]
+async fn atom_feed_contains_published_issues_with_absolute_links() {
+    // Arrange
+    let mut app = setup_app().await;
+    let slug = app.publish_newsletter("Weekly update").await.slug;
+
+    // Act
+    let (status_code, headers, body) = get_feed(&app, "/feed.atom", &[]).await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(
+        headers[http::header::CONTENT_TYPE],
+        "application/atom+xml; charset=utf-8"
+    );
+    assert!(body.contains(r#"<feed xmlns="http://www.w3.org/2005/Atom">"#));
+    assert!(body.contains(&format!(r#"href="{}/issues/{}""#, app.base_url.0, slug)));
+    assert!(body.contains(&format!(r#"href="{}/feed.atom""#, app.base_url.0)));
+}
+
+#[tokio::test]
+async fn unchanged_feeds_are_not_sent_again_for_a_matching_etag() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.publish_newsletter("Weekly update").await;
+
+    for uri in ["/feed.rss", "/feed.atom"] {
+        let (_, headers, _) = get_feed(&app, uri, &[]).await;
+        let etag = headers[http::header::ETAG].to_str().unwrap().to_owned();
+
+        // Act
+        let (status_code, _, body) =
+            get_feed(&app, uri, &[(http::header::IF_NONE_MATCH, &etag)]).await;
+
+        // Assert
+        assert_eq!(status_code, StatusCode::NOT_MODIFIED, "{}", uri);
+        assert!(body.is_empty());
+    }
+}
+
+#[tokio::test]
+async fn unchanged_feeds_are_not_sent_again_since_last_modified() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.publish_newsletter("Weekly update").await;
+
+    let (_, headers, _) = get_feed(&app, "/feed.rss", &[]).await;
+    let last_modified = headers[http::header::LAST_MODIFIED]
+        .to_str()
+        .unwrap()
+        .to_owned();
+
+    // Act
+    let (status_code, _, _) = get_feed(
+        &app,
+        "/feed.rss",
+        &[(http::header::IF_MODIFIED_SINCE, &last_modified)],
+    )
+    .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::NOT_MODIFIED);
+}
+
+#[tokio::test]
+async fn feeds_are_sent_again_once_a_new_issue_is_published() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.publish_newsletter("First issue").await;
+
+    let (_, headers, _) = get_feed(&app, "/feed.atom", &[]).await;
+    let etag = headers[http::header::ETAG].to_str().unwrap().to_owned();
+
+    app.publish_newsletter("Second issue").await;
+
+    // Act
+    let (status_code, headers, body) =
+        get_feed(&app, "/feed.atom", &[(http::header::IF_NONE_MATCH, &etag)]).await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_ne!(headers[http::header::ETAG], etag.as_str());
+    assert!(body.contains("Second issue"));
+}
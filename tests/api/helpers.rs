@@ -6,16 +6,26 @@ use axum::{
     http::{self, HeaderValue, Request},
     Router,
 };
+use fake::{
+    faker::{internet::en::SafeEmail, name::en::FirstName},
+    Fake,
+};
 use hyper::HeaderMap;
 use once_cell::sync::Lazy;
 use reqwest::Url;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use tower::{Service, ServiceExt};
 use uuid::Uuid;
-use wiremock::MockServer;
+use wiremock::{
+    matchers::{method, path},
+    Mock, MockServer, ResponseTemplate,
+};
 use zero2prod::{
-    configuration::{get_configuration, DatabaseSettings},
-    startup::{get_connection_pool, Application},
+    configuration::{get_configuration, DatabaseSettings, EmailWebhookSettings, Settings},
+    email_client::{EmailClient, EmailProvider, RetryPolicy},
+    issue_delivery_worker::{try_execute_task, ExecutionOutcome},
+    issue_scheduler::try_release_scheduled_issue,
+    startup::{get_connection_pool, Application, ApplicationBaseUrl, HmacSecret},
     telemetry::{get_subscriber, init_subscriber},
 };
 
@@ -38,14 +48,84 @@ pub struct ConfirmationLinks {
     pub plain_text: reqwest::Url,
 }
 
+/// 公開した号を後から参照するための識別子
+pub struct PublishedIssue {
+    pub newsletter_issue_id: Uuid,
+    pub slug: String,
+}
+
 pub struct TestApp {
     pub app: Router,
     pub db_pool: PgPool,
     pub email_server: MockServer,
     pub test_user: TestUser,
+    pub email_client: EmailClient,
+    pub base_url: ApplicationBaseUrl,
+    pub hmac_secret: HmacSecret,
+    pub email_webhook: EmailWebhookSettings,
 }
 
 impl TestApp {
+    pub async fn create_unconfirmed_subscriber(&mut self) -> ConfirmationLinks {
+        // 複数の購読者を作成できるようにランダムな値を使用する
+        let name: String = FirstName().fake();
+        let email: String = SafeEmail().fake();
+        let body = format!(
+            "name={}&email={}",
+            urlencoding::encode(&name),
+            urlencoding::encode(&email)
+        );
+
+        let _mock_guard = Mock::given(path("/email"))
+            .and(method("POST"))
+            .respond_with(ResponseTemplate::new(200))
+            .named("Create unconfirmed subscriber")
+            .expect(1)
+            .mount_as_scoped(&self.email_server)
+            .await;
+
+        self.post_subscription(body).await;
+
+        // Emailサーバーに送信されたメールから本文を抽出する
+        let email_request = &self
+            .email_server
+            .received_requests()
+            .await
+            .unwrap()
+            .pop()
+            .unwrap();
+
+        // メール本文からURLリンクを抽出する
+        self.get_confirmation_links(email_request)
+    }
+
+    pub async fn create_confirmed_subscriber(&mut self) {
+        // リンクからトークンを抽出して送信することで、確認済みデータを作成する
+        let confirmation_links = self.create_unconfirmed_subscriber().await;
+
+        let query_params = extract_query_params(&confirmation_links.html);
+        let token = query_params.get("subscription_token").unwrap().to_owned();
+
+        self.confirm_link(token).await;
+    }
+
+    /// キューに登録されている配信タスクを全て実行する
+    pub async fn dispatch_all_pending_emails(&self) {
+        loop {
+            if let ExecutionOutcome::EmptyQueue = try_execute_task(
+                &self.db_pool,
+                &self.email_client,
+                &self.base_url,
+                &self.hmac_secret,
+            )
+            .await
+            .unwrap()
+            {
+                break;
+            }
+        }
+    }
+
     pub async fn post_subscription(&mut self, body: String) -> (axum::http::StatusCode, String) {
         let request = Request::builder()
             .method(http::Method::POST)
@@ -124,17 +204,10 @@ impl TestApp {
         body: serde_json::Value,
         with_auth_header: bool,
     ) -> (axum::http::StatusCode, HeaderMap) {
-        let mut request = Request::builder()
-            .method(http::Method::POST)
-            .uri("/newsletters")
-            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
-            .body(Body::from(serde_json::to_vec(&body).unwrap()))
-            .unwrap();
-
-        if with_auth_header {
-            let auth_value = basic_auth_value(&self.test_user.username, &self.test_user.password);
+        let mut request = self.newsletters_request(&body);
 
-            request.headers_mut().insert("Authorization", auth_value);
+        if !with_auth_header {
+            request.headers_mut().remove("Authorization");
         }
 
         let response = self
@@ -148,6 +221,159 @@ impl TestApp {
 
         (response.status(), response.headers().to_owned())
     }
+
+    /// 指定したタイトルの号を公開して、配信キューに登録する
+    pub async fn publish_newsletter(&mut self, title: &str) -> PublishedIssue {
+        let (status_code, _) = self
+            .post_newsletters(
+                serde_json::json!({
+                    "title": title,
+                    "content": {
+                        "text": "Newsletter body as plain text",
+                        "html": "<p>Newsletter body as HTML for {{ name }}</p>",
+                    }
+                }),
+                true,
+            )
+            .await;
+        assert_eq!(status_code, http::StatusCode::OK);
+
+        sqlx::query_as!(
+            PublishedIssue,
+            "SELECT newsletter_issue_id, slug FROM newsletter_issues WHERE title = $1",
+            title
+        )
+        .fetch_one(&self.db_pool)
+        .await
+        .unwrap()
+    }
+
+    /// 唯一の購読者のメールアドレスを返却する
+    pub async fn subscriber_email(&self) -> String {
+        sqlx::query!("SELECT email FROM subscriptions")
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+            .email
+    }
+
+    /// 唯一の購読者の状態を返却する
+    pub async fn subscription_status(&self) -> String {
+        sqlx::query!("SELECT status FROM subscriptions")
+            .fetch_one(&self.db_pool)
+            .await
+            .unwrap()
+            .status
+    }
+
+    /// 配信予定日時を過ぎた予約配信を全て配信キューに登録する
+    pub async fn release_scheduled_issues(&self) {
+        loop {
+            if let ExecutionOutcome::EmptyQueue =
+                try_release_scheduled_issue(&self.db_pool).await.unwrap()
+            {
+                break;
+            }
+        }
+    }
+
+    /// 認証なしで GET リクエストを送信し、HTMLのレスポンスを返却する
+    pub async fn get_html(&self, uri: &str) -> (axum::http::StatusCode, String) {
+        let request = Request::builder()
+            .method(http::Method::GET)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap();
+
+        let response = self
+            .app
+            .clone()
+            .oneshot(request)
+            .await
+            .expect("Failed to execute request");
+
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = std::str::from_utf8(&bytes).unwrap();
+
+        (status, String::from(body))
+    }
+
+    /// Basic認証のヘッダーを付与してリクエストし、JSONのレスポンスを返却する
+    pub async fn authenticated_request(
+        &self,
+        method: http::Method,
+        uri: &str,
+        body: Option<serde_json::Value>,
+    ) -> (axum::http::StatusCode, serde_json::Value) {
+        let mut request = Request::builder().method(method).uri(uri).header(
+            http::header::AUTHORIZATION,
+            basic_auth_value(&self.test_user.username, &self.test_user.password),
+        );
+
+        let body = match body {
+            Some(body) => {
+                request =
+                    request.header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref());
+                Body::from(serde_json::to_vec(&body).unwrap())
+            }
+            None => Body::empty(),
+        };
+
+        let response = self
+            .app
+            .clone()
+            .oneshot(request.body(body).unwrap())
+            .await
+            .expect("Failed to execute request");
+
+        let status = response.status();
+        let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        let body = if bytes.is_empty() {
+            serde_json::Value::Null
+        } else {
+            serde_json::from_slice(&bytes).unwrap()
+        };
+
+        (status, body)
+    }
+
+    /// Idempotency-Key ヘッダーを付与してニュースレターを配信する
+    ///
+    /// 同時実行のテストでも利用できるように、ルーターを複製してリクエストする
+    pub async fn post_newsletters_with_idempotency_key(
+        &self,
+        body: &serde_json::Value,
+        idempotency_key: &str,
+    ) -> (axum::http::StatusCode, HeaderMap) {
+        let mut request = self.newsletters_request(body);
+        request
+            .headers_mut()
+            .insert("Idempotency-Key", idempotency_key.parse().unwrap());
+
+        let response = self
+            .app
+            .clone()
+            .oneshot(request)
+            .await
+            .expect("Failed to execute request");
+
+        (response.status(), response.headers().to_owned())
+    }
+
+    fn newsletters_request(&self, body: &serde_json::Value) -> Request<Body> {
+        let mut request = Request::builder()
+            .method(http::Method::POST)
+            .uri("/newsletters")
+            .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+            .body(Body::from(serde_json::to_vec(body).unwrap()))
+            .unwrap();
+
+        let auth_value = basic_auth_value(&self.test_user.username, &self.test_user.password);
+        request.headers_mut().insert("Authorization", auth_value);
+
+        request
+    }
 }
 
 pub struct TestUser {
@@ -190,6 +416,11 @@ impl TestUser {
 }
 
 pub async fn setup_app() -> TestApp {
+    setup_app_with(|_| {}).await
+}
+
+/// テストごとに設定を変更してアプリケーションを起動する
+pub async fn setup_app_with(customize: impl FnOnce(&mut Settings)) -> TestApp {
     Lazy::force(&TRACING);
 
     // テスト用のEmailモックサーバー
@@ -199,7 +430,12 @@ pub async fn setup_app() -> TestApp {
         let mut c = get_configuration().expect("Failed to read configuration.");
         c.database.database_name = Uuid::new_v4().to_string();
         c.application.port = 0;
+        // ローカル環境の設定に関わらずモックサーバーにメールを送信する
+        c.email_client.provider = EmailProvider::Postmark;
         c.email_client.base_url = email_server.uri();
+        // メールサーバーへのリクエスト回数を検証できるように、クライアントでは再試行しない
+        c.email_client.retry_policy = RetryPolicy::none();
+        customize(&mut c);
         c
     };
 
@@ -212,6 +448,10 @@ pub async fn setup_app() -> TestApp {
         db_pool: get_connection_pool(&configuration.database),
         email_server,
         test_user: TestUser::generate(),
+        email_client: application.email_client(),
+        base_url: ApplicationBaseUrl(configuration.application.base_url.clone()),
+        hmac_secret: HmacSecret(configuration.application.hmac_secret.clone()),
+        email_webhook: configuration.email_client.webhook.clone(),
     };
 
     test_app.test_user.store(&test_app.db_pool).await;
@@ -0,0 +1,186 @@
+use axum::http::{self, StatusCode};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::setup_app;
+
+#[tokio::test]
+async fn published_issues_are_listed_in_the_archive() {
+    // Arrange
+    let mut app = setup_app().await;
+    let slug = app.publish_newsletter("Release <notes>").await.slug;
+
+    // 予約配信はアーカイブに表示しない
+    app.post_newsletters(
+        serde_json::json!({
+            "title": "Scheduled issue",
+            "content": {"text": "text", "html": "<p>html</p>"},
+            "send_at": chrono::Utc::now() + chrono::Duration::days(1),
+        }),
+        true,
+    )
+    .await;
+
+    // Act
+    let (status_code, html) = app.get_html("/issues").await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(html.contains(&format!(r#"href="/issues/{}""#, slug)));
+    assert!(html.contains("Release &lt;notes&gt;"));
+    assert!(!html.contains("Scheduled issue"));
+}
+
+#[tokio::test]
+async fn the_archive_is_paginated() {
+    // Arrange
+    let mut app = setup_app().await;
+    for i in 0..11 {
+        app.publish_newsletter(&format!("Issue {}", i)).await;
+    }
+
+    // Act
+    let (_, first_page) = app.get_html("/issues").await;
+    let (_, second_page) = app.get_html("/issues?page=2").await;
+
+    // Assert
+    assert_eq!(first_page.matches("<li>").count(), 10);
+    assert!(first_page.contains(r#"href="/issues?page=2""#));
+    assert!(!first_page.contains("Newer issues"));
+
+    assert_eq!(second_page.matches("<li>").count(), 1);
+    assert!(second_page.contains(r#"href="/issues?page=1""#));
+    assert!(!second_page.contains("Older issues"));
+}
+
+#[tokio::test]
+async fn huge_page_numbers_show_an_empty_page() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.publish_newsletter("Weekly update").await;
+
+    // Act
+    let (status_code, html) = app.get_html(&format!("/issues?page={}", i64::MAX)).await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(html.contains("No issues have been published yet."));
+    assert!(!html.contains("Older issues"));
+}
+
+#[tokio::test]
+async fn a_published_issue_is_shown_in_the_site_layout() {
+    // Arrange
+    let mut app = setup_app().await;
+    let slug = app.publish_newsletter("Weekly update").await.slug;
+
+    // Act
+    let (status_code, html) = app.get_html(&format!("/issues/{}", slug)).await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Weekly update</title>"));
+    assert!(html.contains("<p>Newsletter body as HTML for reader</p>"));
+}
+
+#[tokio::test]
+async fn unknown_issues_are_not_found() {
+    // Arrange
+    let app = setup_app().await;
+
+    // Act
+    let (status_code, _) = app.get_html("/issues/unknown-issue").await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn issues_sent_to_a_segment_or_another_list_are_not_public() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.authenticated_request(
+        http::Method::POST,
+        "/mailing_lists",
+        Some(serde_json::json!({ "slug": "rust-weekly", "name": "Rust Weekly" })),
+    )
+    .await;
+    for body in [
+        serde_json::json!({
+            "title": "Beta issue",
+            "content": {"text": "text", "html": "<p>html</p>"},
+            "segment": r#"tags include "beta""#,
+        }),
+        serde_json::json!({
+            "title": "Rust Weekly issue",
+            "content": {"text": "text", "html": "<p>html</p>"},
+            "list": "rust-weekly",
+        }),
+    ] {
+        let (status_code, _) = app.post_newsletters(body, true).await;
+        assert_eq!(status_code, StatusCode::OK);
+    }
+    let slugs: Vec<String> = sqlx::query_scalar!("SELECT slug FROM newsletter_issues")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let (_, archive) = app.get_html("/issues").await;
+    let (_, rss) = app.get_html("/feed.rss").await;
+    let (_, atom) = app.get_html("/feed.atom").await;
+
+    // Assert
+    for body in [archive, rss, atom] {
+        assert!(!body.contains("Beta issue"));
+        assert!(!body.contains("Rust Weekly issue"));
+    }
+    for slug in slugs {
+        let (status_code, _) = app.get_html(&format!("/issues/{}", slug)).await;
+        assert_eq!(status_code, StatusCode::NOT_FOUND);
+    }
+}
+
+#[tokio::test]
+async fn emails_link_to_the_web_version_of_the_issue() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let slug = app.publish_newsletter("Weekly update").await.slug;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let messages: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    let web_version_url = format!("{}/issues/{}", app.base_url.0, slug);
+    // このサービス自身へのリンクのため、クリックを記録するURLを経由しない
+    assert!(messages[0]["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains(&format!(r#"href="{}""#, web_version_url)));
+    assert!(messages[0]["TextBody"]
+        .as_str()
+        .unwrap()
+        .ends_with(&web_version_url));
+
+    let (status_code, _) = app.get_html(&format!("/issues/{}", slug)).await;
+    assert_eq!(status_code, StatusCode::OK);
+}
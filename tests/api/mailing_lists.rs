@@ -0,0 +1,253 @@
+use axum::http::{self, StatusCode};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{extract_query_params, setup_app, TestApp};
+
+async fn create_mailing_list(app: &TestApp, slug: &str, name: &str) -> StatusCode {
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::POST,
+            "/mailing_lists",
+            Some(serde_json::json!({ "slug": slug, "name": name })),
+        )
+        .await;
+
+    status_code
+}
+
+/// 指定したリストを購読し、確認メールに記載されたトークンを返却する
+async fn subscribe_to_list(app: &mut TestApp, email: &str, list: &str) -> String {
+    let _mock_guard = Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    let (status, _) = app
+        .post_subscription(format!(
+            "name=shimopino&email={}&list={}",
+            urlencoding::encode(email),
+            list
+        ))
+        .await;
+    assert_eq!(status, StatusCode::CREATED);
+
+    let email_request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let confirmation_links = app.get_confirmation_links(&email_request);
+
+    extract_query_params(&confirmation_links.html)
+        .get("subscription_token")
+        .unwrap()
+        .to_owned()
+}
+
+#[tokio::test]
+async fn existing_subscriptions_belong_to_the_default_list() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    // Act
+    let (status_code, body) = app
+        .authenticated_request(http::Method::GET, "/mailing_lists", None)
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body[0]["slug"], "default");
+
+    let subscription = sqlx::query!(
+        r#"
+        SELECT l.slug
+        FROM subscriptions s
+        JOIN mailing_lists l ON l.id = s.mailing_list_id
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(subscription.slug, "default");
+}
+
+#[tokio::test]
+async fn mailing_lists_can_be_created_and_listed() {
+    // Arrange
+    let app = setup_app().await;
+
+    // Act
+    let status_code = create_mailing_list(&app, "rust-weekly", "Rust Weekly").await;
+    let (_, body) = app
+        .authenticated_request(http::Method::GET, "/mailing_lists", None)
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::CREATED);
+    let slugs: Vec<_> = body
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|list| list["slug"].as_str().unwrap())
+        .collect();
+    assert_eq!(slugs, vec!["default", "rust-weekly"]);
+}
+
+#[tokio::test]
+async fn invalid_or_duplicate_mailing_lists_are_rejected() {
+    // Arrange
+    let app = setup_app().await;
+    let test_cases = vec![
+        ("Rust Weekly", "Rust Weekly", "an invalid identifier"),
+        ("rust-weekly", "  ", "an empty name"),
+        ("default", "Another default", "an existing identifier"),
+    ];
+
+    for (slug, name, description) in test_cases {
+        // Act
+        let status_code = create_mailing_list(&app, slug, name).await;
+
+        // Assert
+        assert_eq!(
+            status_code,
+            StatusCode::BAD_REQUEST,
+            "The API did not fail with 400 Bad Request when the payload had {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn an_email_can_subscribe_to_several_lists_with_separate_confirmation() {
+    // Arrange
+    let mut app = setup_app().await;
+    create_mailing_list(&app, "rust-weekly", "Rust Weekly").await;
+    let email = "shimopino@example.com";
+
+    // Act
+    let default_token = subscribe_to_list(&mut app, email, "default").await;
+    subscribe_to_list(&mut app, email, "rust-weekly").await;
+    app.confirm_link(default_token).await;
+
+    // Assert
+    let subscriptions = sqlx::query!(
+        r#"
+        SELECT l.slug, s.status
+        FROM subscriptions s
+        JOIN mailing_lists l ON l.id = s.mailing_list_id
+        WHERE s.email = $1
+        ORDER BY l.slug
+        "#,
+        email
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(subscriptions.len(), 2);
+    assert_eq!(subscriptions[0].slug, "default");
+    assert_eq!(subscriptions[0].status, "confirmed");
+    assert_eq!(subscriptions[1].slug, "rust-weekly");
+    assert_eq!(subscriptions[1].status, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn subscribing_to_an_unknown_list_returns_400() {
+    // Arrange
+    let mut app = setup_app().await;
+
+    // Act
+    let (status, _) = app
+        .post_subscription("name=shimopino&email=shimopino%40example.com&list=unknown".into())
+        .await;
+
+    // Assert
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+#[tokio::test]
+async fn newsletters_are_delivered_only_to_the_given_list() {
+    // Arrange
+    let mut app = setup_app().await;
+    create_mailing_list(&app, "rust-weekly", "Rust Weekly").await;
+    app.create_confirmed_subscriber().await;
+    let token = subscribe_to_list(&mut app, "rustacean@example.com", "rust-weekly").await;
+    app.confirm_link(token).await;
+    // 確認されていない購読者には配信しない
+    subscribe_to_list(&mut app, "pending@example.com", "rust-weekly").await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::POST,
+            "/newsletters",
+            Some(serde_json::json!({
+                "title": "Newsletter title",
+                "content": {
+                    "text": "Newsletter body as plain text",
+                    "html": "<p>Newsletter body as HTML</p>",
+                },
+                "list": "rust-weekly",
+            })),
+        )
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let messages: Vec<serde_json::Value> = serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0]["To"], "rustacean@example.com");
+}
+
+#[tokio::test]
+async fn publishing_to_an_unknown_list_returns_400() {
+    // Arrange
+    let app = setup_app().await;
+
+    // Act
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::POST,
+            "/newsletters",
+            Some(serde_json::json!({
+                "title": "Newsletter title",
+                "content": {
+                    "text": "Newsletter body as plain text",
+                    "html": "<p>Newsletter body as HTML</p>",
+                },
+                "list": "unknown",
+            })),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+    let n_issues = sqlx::query!("SELECT COUNT(*) AS n_issues FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n_issues;
+    assert_eq!(n_issues, Some(0));
+}
@@ -1,8 +1,23 @@
 // main.rsを配置して単一バイナリとしてテストを実行する
 // これでファイルを分割しても、そえぞれのテストをコンパイルするのではなく
 // テスト全体を1つのファイルとして実行することが可能となる
+mod click_tracking;
+mod delivery_report;
+mod dev_mailbox;
+mod email_webhooks;
+mod feeds;
 mod health_check;
 mod helpers;
+mod issue_archive;
+mod mailing_lists;
 mod newsletter;
+mod newsletter_drafts;
+mod newsletter_personalization;
+mod open_tracking;
+mod scheduled_newsletter;
+mod segments;
 mod subscription;
 mod subscription_confirm;
+mod suppressions;
+mod test_send;
+mod unsubscribe;
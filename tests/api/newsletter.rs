@@ -11,15 +11,13 @@ use wiremock::{
     Mock, ResponseTemplate,
 };
 
-use crate::helpers::{
-    basic_auth_value, extract_query_params, setup_app, ConfirmationLinks, TestApp,
-};
+use crate::helpers::{basic_auth_value, setup_app};
 
 #[tokio::test]
 async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
     // Arrange
     let mut app = setup_app().await;
-    create_unconfirmed_subscriber(&mut app).await;
+    app.create_unconfirmed_subscriber().await;
 
     Mock::given(any())
         .respond_with(ResponseTemplate::new(200))
@@ -39,15 +37,16 @@ async fn newsletters_are_not_delivered_to_unconfirmed_subscribers() {
     let (status_code, _) = app.post_newsletters(newsletter_request_body, true).await;
 
     assert_eq!(status_code, StatusCode::OK);
+    app.dispatch_all_pending_emails().await;
 }
 
 #[tokio::test]
 async fn newsletters_are_delivered_to_confirmed_subscribers() {
     // Arrange
     let mut app = setup_app().await;
-    create_confirmed_subscriber(&mut app).await;
+    app.create_confirmed_subscriber().await;
 
-    Mock::given(path("/email"))
+    Mock::given(path("/email/batch"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
         .expect(1)
@@ -65,6 +64,7 @@ async fn newsletters_are_delivered_to_confirmed_subscribers() {
     let (status_code, _) = app.post_newsletters(newsletter_request_body, true).await;
 
     assert_eq!(status_code, StatusCode::OK);
+    app.dispatch_all_pending_emails().await;
 }
 
 #[tokio::test]
@@ -85,6 +85,13 @@ async fn newsletters_returns_400_for_invalid_data() {
             serde_json::json!({"title": "Newsletter!"}),
             "missing content",
         ),
+        (
+            serde_json::json!({
+                "title": "Newsletter!",
+                "content": {"text": "Newsletter body as plain text"}
+            }),
+            "missing html content",
+        ),
     ];
 
     for (invalid_body, error_message) in test_cases {
@@ -100,6 +107,181 @@ async fn newsletters_returns_400_for_invalid_data() {
     }
 }
 
+#[tokio::test]
+async fn markdown_content_is_delivered_as_html_and_text() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "markdown": "Hello **{{ name }}**!\n\nRead the [release notes](https://example.com/notes).\n\n<script>alert('xss')</script>",
+        }
+    });
+    let (status_code, _) = app.post_newsletters(newsletter_request_body, true).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+
+    let issue = sqlx::query!("SELECT html_content, text_content FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(issue.html_content.contains("<strong>{{ name }}</strong>"));
+    assert!(issue
+        .html_content
+        .contains(r#"<a href="https://example.com/notes""#));
+    assert!(!issue.html_content.contains("<script>"));
+    assert_eq!(
+        issue.text_content,
+        "Hello {{ name }}!\n\nRead the release notes[1].\n\n[1] https://example.com/notes"
+    );
+
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let messages: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    assert!(!messages[0]["HtmlBody"]
+        .as_str()
+        .unwrap()
+        .contains("{{ name }}"));
+}
+
+#[tokio::test]
+async fn attachments_are_delivered_with_the_issue() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let newsletter_request_body = serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p><img src=\"cid:logo\">",
+        },
+        "attachments": [
+            {
+                "name": "agenda.pdf",
+                "content_type": "application/pdf",
+                "content": "JVBERi0xLjQK",
+            },
+            {
+                "name": "logo.png",
+                "content_type": "image/png",
+                "content": "iVBORw0KGgo=",
+                "content_id": "cid:logo",
+            }
+        ]
+    });
+    let (status_code, _) = app.post_newsletters(newsletter_request_body, true).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let messages: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(
+        messages[0]["Attachments"],
+        json!([
+            {
+                "Name": "agenda.pdf",
+                "ContentType": "application/pdf",
+                "Content": "JVBERi0xLjQK",
+            },
+            {
+                "Name": "logo.png",
+                "ContentType": "image/png",
+                "Content": "iVBORw0KGgo=",
+                "ContentID": "cid:logo",
+            }
+        ])
+    );
+}
+
+#[tokio::test]
+async fn newsletters_with_invalid_attachments_are_rejected() {
+    // Arrange
+    let mut app = setup_app().await;
+    let attachment = |name: &str, content_type: &str, content: &str| {
+        json!({
+            "title": "Newsletter title",
+            "content": {
+                "text": "Newsletter body as plain text",
+                "html": "<p>Newsletter body as HTML</p>",
+            },
+            "attachments": [{
+                "name": name,
+                "content_type": content_type,
+                "content": content,
+            }]
+        })
+    };
+    let test_cases = vec![
+        (
+            attachment("", "application/pdf", "JVBERi0xLjQK"),
+            "empty name",
+        ),
+        (
+            attachment("agenda.pdf", "pdf", "JVBERi0xLjQK"),
+            "invalid content type",
+        ),
+        (
+            attachment("agenda.pdf", "application/pdf", "not base64!"),
+            "invalid base64 content",
+        ),
+        (
+            attachment(
+                "agenda.pdf",
+                "application/pdf",
+                &"A".repeat(10 * 1024 * 1024),
+            ),
+            "too large attachment",
+        ),
+    ];
+
+    for (invalid_body, error_message) in test_cases {
+        // Act
+        let (status_code, _) = app.post_newsletters(invalid_body, true).await;
+
+        // Assert
+        assert_eq!(
+            status_code,
+            StatusCode::BAD_REQUEST,
+            "The API did not fail with 400 Bad Request when the payload had {}.",
+            error_message
+        );
+    }
+}
+
 #[tokio::test]
 async fn requests_missing_authorization_are_rejected() {
     // Arrange
@@ -210,39 +392,263 @@ async fn invalid_password_is_rejected() {
     )
 }
 
-async fn create_unconfirmed_subscriber(app: &mut TestApp) -> ConfirmationLinks {
-    let body = "name=shimopino&email=shimopino%40example.com";
+#[tokio::test]
+async fn newsletters_are_enqueued_without_calling_the_email_server() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
 
-    let _mock_guard = Mock::given(path("/email"))
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (status_code, _) = app.post_newsletters(newsletter_request_body(), true).await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+
+    let n_tasks = sqlx::query!("SELECT COUNT(*) AS n_tasks FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n_tasks;
+    assert_eq!(n_tasks, Some(1));
+}
+
+#[tokio::test]
+async fn confirmed_subscribers_of_an_issue_are_delivered_in_a_single_batch() {
+    // Arrange
+    let mut app = setup_app().await;
+    for _ in 0..3 {
+        app.create_confirmed_subscriber().await;
+    }
+
+    Mock::given(path("/email/batch"))
         .and(method("POST"))
         .respond_with(ResponseTemplate::new(200))
-        .named("Create unconfirmed subscriber")
         .expect(1)
-        .mount_as_scoped(&app.email_server)
+        .mount(&app.email_server)
         .await;
 
-    app.post_subscription(body.into()).await;
+    // Act
+    let (status_code, _) = app.post_newsletters(newsletter_request_body(), true).await;
+    app.dispatch_all_pending_emails().await;
 
-    // Emailサーバーに送信されたメールから本文を抽出する
-    let email_request = &app
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+
+    let batch_request = app
         .email_server
         .received_requests()
         .await
         .unwrap()
         .pop()
         .unwrap();
+    let messages: serde_json::Value = serde_json::from_slice(&batch_request.body).unwrap();
+    assert_eq!(messages.as_array().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_kept_in_the_queue_for_a_retry() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (status_code, _) = app.post_newsletters(newsletter_request_body(), true).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
 
-    // メール本文からURLリンクを抽出する
-    app.get_confirmation_links(email_request)
+    let task = sqlx::query!("SELECT n_retries, execute_after FROM issue_delivery_queue")
+        .fetch_one(&app.db_pool)
+        .await
+        .expect("The failed delivery task was removed from the queue.");
+    assert_eq!(task.n_retries, 1);
+    assert!(task.execute_after > chrono::Utc::now());
 }
 
-async fn create_confirmed_subscriber(app: &mut TestApp) {
-    // リンクからトークンを抽出して送信することで、確認済みデータを作成する
-    let confirmation_links = create_unconfirmed_subscriber(app).await;
+#[tokio::test]
+async fn permanently_failed_deliveries_are_not_retried() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
 
-    let query_params = extract_query_params(&confirmation_links.html);
-    let token = query_params.get("subscription_token").unwrap().to_owned();
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(422))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
 
     // Act
-    app.confirm_link(token).await;
+    let (status_code, _) = app.post_newsletters(newsletter_request_body(), true).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+
+    let n_tasks = sqlx::query_scalar!(r#"SELECT count(*) AS "count!" FROM issue_delivery_queue"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(n_tasks, 0);
+
+    let delivery = sqlx::query!("SELECT status, n_attempts FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "failed");
+    assert_eq!(delivery.n_attempts, 1);
+}
+
+#[tokio::test]
+async fn each_confirmed_subscriber_receives_the_issue_only_once() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (status_code, _) = app.post_newsletters(newsletter_request_body(), true).await;
+    // ワーカーを再起動した場合を想定して、キューを複数回処理する
+    app.dispatch_all_pending_emails().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn newsletter_creation_is_idempotent() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let idempotency_key = Uuid::new_v4().to_string();
+
+    // Act - 1回目のリクエスト
+    let (status_code, _) = app
+        .post_newsletters_with_idempotency_key(&newsletter_request_body(), &idempotency_key)
+        .await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    // Act - 同じキーで再送する
+    let (status_code, _) = app
+        .post_newsletters_with_idempotency_key(&newsletter_request_body(), &idempotency_key)
+        .await;
+    assert_eq!(status_code, StatusCode::OK);
+
+    app.dispatch_all_pending_emails().await;
+    // Assert
+    // ドロップ時にメールが1回のみ送信されたことを検証する
+}
+
+#[tokio::test]
+async fn concurrent_form_submission_is_handled_gracefully() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let body = newsletter_request_body();
+    let idempotency_key = Uuid::new_v4().to_string();
+
+    // Act - 同じキーで2つのリクエストを同時に送信する
+    let response1 = app.post_newsletters_with_idempotency_key(&body, &idempotency_key);
+    let response2 = app.post_newsletters_with_idempotency_key(&body, &idempotency_key);
+    let ((status1, _), (status2, _)) = tokio::join!(response1, response2);
+
+    // Assert
+    assert_eq!(status1, StatusCode::OK);
+    assert_eq!(status2, StatusCode::OK);
+
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn expired_idempotency_keys_are_processed_again() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(2)
+        .mount(&app.email_server)
+        .await;
+
+    let idempotency_key = Uuid::new_v4().to_string();
+    app.post_newsletters_with_idempotency_key(&newsletter_request_body(), &idempotency_key)
+        .await;
+
+    // 保存期間を過ぎたことにする
+    sqlx::query!("UPDATE idempotency SET created_at = now() - interval '30 days'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let (status_code, _) = app
+        .post_newsletters_with_idempotency_key(&newsletter_request_body(), &idempotency_key)
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    app.dispatch_all_pending_emails().await;
+}
+
+#[tokio::test]
+async fn invalid_idempotency_keys_are_rejected() {
+    // Arrange
+    let app = setup_app().await;
+
+    // Act
+    let (status_code, _) = app
+        .post_newsletters_with_idempotency_key(&newsletter_request_body(), &"a".repeat(100))
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
+
+fn newsletter_request_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    })
 }
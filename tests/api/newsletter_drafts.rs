@@ -0,0 +1,254 @@
+use axum::{
+    body::Body,
+    http::{self, Request, StatusCode},
+};
+use tower::ServiceExt;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{basic_auth_value, setup_app, TestApp};
+
+fn draft_request_body(title: &str) -> serde_json::Value {
+    serde_json::json!({
+        "title": title,
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        }
+    })
+}
+
+async fn create_draft(app: &TestApp, title: &str) -> String {
+    let (status_code, body) = app
+        .authenticated_request(
+            http::Method::POST,
+            "/newsletters/drafts",
+            Some(draft_request_body(title)),
+        )
+        .await;
+    assert_eq!(status_code, StatusCode::CREATED);
+
+    body["draft_id"].as_str().unwrap().to_owned()
+}
+
+#[tokio::test]
+async fn created_drafts_can_be_fetched() {
+    // Arrange
+    let app = setup_app().await;
+    let draft_id = create_draft(&app, "Draft title").await;
+
+    // Act
+    let (status_code, body) = app
+        .authenticated_request(
+            http::Method::GET,
+            &format!("/newsletters/drafts/{}", draft_id),
+            None,
+        )
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body["title"], "Draft title");
+    assert_eq!(body["content"]["text"], "Newsletter body as plain text");
+    assert_eq!(body["content"]["html"], "<p>Newsletter body as HTML</p>");
+    assert_eq!(body["author_id"], app.test_user.user_id.to_string());
+}
+
+#[tokio::test]
+async fn drafts_are_listed() {
+    // Arrange
+    let app = setup_app().await;
+    create_draft(&app, "First draft").await;
+    create_draft(&app, "Second draft").await;
+
+    // Act
+    let (status_code, body) = app
+        .authenticated_request(http::Method::GET, "/newsletters/drafts", None)
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body.as_array().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn drafts_can_be_updated() {
+    // Arrange
+    let app = setup_app().await;
+    let draft_id = create_draft(&app, "Draft title").await;
+
+    // Act
+    let (status_code, body) = app
+        .authenticated_request(
+            http::Method::PUT,
+            &format!("/newsletters/drafts/{}", draft_id),
+            Some(draft_request_body("Updated title")),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body["title"], "Updated title");
+}
+
+#[tokio::test]
+async fn deleted_drafts_are_not_found() {
+    // Arrange
+    let app = setup_app().await;
+    let draft_id = create_draft(&app, "Draft title").await;
+    let uri = format!("/newsletters/drafts/{}", draft_id);
+
+    // Act
+    let (status_code, _) = app
+        .authenticated_request(http::Method::DELETE, &uri, None)
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::NO_CONTENT);
+
+    let (status_code, _) = app
+        .authenticated_request(http::Method::GET, &uri, None)
+        .await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn published_drafts_are_delivered_to_confirmed_subscribers() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let draft_id = create_draft(&app, "Draft title").await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act - 本文なしで配信する
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::POST,
+            &format!("/newsletters/drafts/{}/publish", draft_id),
+            None,
+        )
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+
+    let issue = sqlx::query!("SELECT title FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.title, "Draft title");
+
+    // 配信済みの下書きは削除される
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::GET,
+            &format!("/newsletters/drafts/{}", draft_id),
+            None,
+        )
+        .await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn drafts_cannot_be_published_twice() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let draft_id = create_draft(&app, "Draft title").await;
+    let uri = format!("/newsletters/drafts/{}/publish", draft_id);
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let first = app.authenticated_request(http::Method::POST, &uri, None);
+    let second = app.authenticated_request(http::Method::POST, &uri, None);
+    let ((status1, _), (status2, _)) = tokio::join!(first, second);
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let mut statuses = vec![status1, status2];
+    statuses.sort();
+    assert_eq!(statuses, vec![StatusCode::OK, StatusCode::NOT_FOUND]);
+}
+
+#[tokio::test]
+async fn drafts_are_not_published_with_an_invalid_body() {
+    // Arrange
+    let app = setup_app().await;
+    let draft_id = create_draft(&app, "Draft title").await;
+    let test_cases = vec![
+        (r#"{"send_at": "tomorrow"}"#, "invalid send_at"),
+        (r#"{"sendat": "2030-01-01T00:00:00Z"}"#, "misspelled field"),
+        (r#"{"segment": "#, "malformed JSON"),
+    ];
+
+    for (body, description) in test_cases {
+        // Act
+        let response = app
+            .app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .method(http::Method::POST)
+                    .uri(format!("/newsletters/drafts/{}/publish", draft_id))
+                    .header(
+                        http::header::AUTHORIZATION,
+                        basic_auth_value(&app.test_user.username, &app.test_user.password),
+                    )
+                    .header(http::header::CONTENT_TYPE, mime::APPLICATION_JSON.as_ref())
+                    .body(Body::from(body))
+                    .unwrap(),
+            )
+            .await
+            .expect("Failed to execute request");
+
+        // Assert
+        assert!(
+            response.status().is_client_error(),
+            "The API did not reject the body with {}",
+            description
+        );
+    }
+
+    let issues = sqlx::query!("SELECT title FROM newsletter_issues")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap();
+    assert!(issues.is_empty());
+}
+
+#[tokio::test]
+async fn drafts_require_authentication() {
+    // Arrange
+    let app = setup_app().await;
+
+    // Act
+    let response = app
+        .app
+        .oneshot(
+            Request::builder()
+                .method(http::Method::GET)
+                .uri("/newsletters/drafts")
+                .body(Body::empty())
+                .unwrap(),
+        )
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
@@ -0,0 +1,177 @@
+use axum::http::{self, StatusCode};
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{setup_app, TestApp};
+
+fn personalized_request_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Hi {{ name }}, you subscribed as {{ email }}. Unsubscribe: {{ unsubscribe_url }}",
+            "html": "<p>Hi {{ name }}</p><a href=\"{{ unsubscribe_url }}\">Unsubscribe</a>",
+        }
+    })
+}
+
+async fn stored_subscriber(app: &TestApp) -> (uuid::Uuid, String, String) {
+    let subscriber = sqlx::query!("SELECT id, name, email FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+    (subscriber.id, subscriber.name, subscriber.email)
+}
+
+#[tokio::test]
+async fn placeholders_are_rendered_for_each_recipient() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let (subscriber_id, name, email) = stored_subscriber(&app).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (status_code, _) = app
+        .post_newsletters(personalized_request_body(), true)
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let messages: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    let text_body = messages[0]["TextBody"].as_str().unwrap();
+    let html_body = messages[0]["HtmlBody"].as_str().unwrap();
+
+    assert!(text_body.starts_with(&format!("Hi {}, you subscribed as {}.", name, email)));
+    assert!(html_body.starts_with(&format!("<p>Hi {}</p>", htmlescape::encode_minimal(&name))));
+
+    // 配信停止用のリンクは購読者IDを署名したものになる
+    let unsubscribe_url = linkify::LinkFinder::new()
+        .links(text_body)
+        .filter(|l| *l.kind() == linkify::LinkKind::Url)
+        .map(|l| reqwest::Url::parse(l.as_str()).unwrap())
+        .find(|url| url.path() == "/subscriptions/unsubscribe")
+        .unwrap();
+
+    let query_string = format!("subscriber_id={}", subscriber_id);
+    let tag = unsubscribe_url
+        .query_pairs()
+        .find(|(key, _)| key == "tag")
+        .map(|(_, value)| value.into_owned())
+        .unwrap();
+    assert!(app.hmac_secret.verify(&query_string, &tag).is_ok());
+
+    // 本文の配信停止用のリンクから配信停止の画面を開ける
+    let (status_code, _) = app
+        .get_html(&format!(
+            "{}?{}",
+            unsubscribe_url.path(),
+            unsubscribe_url.query().unwrap()
+        ))
+        .await;
+    assert_eq!(status_code, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn issues_with_unknown_variables_are_rejected() {
+    // Arrange
+    let mut app = setup_app().await;
+    let test_cases = vec![
+        (
+            serde_json::json!({"text": "Hi {{ first_name }}", "html": "<p>Hi</p>"}),
+            "unknown variable in the text content",
+        ),
+        (
+            serde_json::json!({"text": "Hi", "html": "<p>Hi {{ age }}</p>"}),
+            "unknown variable in the html content",
+        ),
+    ];
+
+    for (content, error_message) in test_cases {
+        // Act
+        let (status_code, _) = app
+            .post_newsletters(
+                serde_json::json!({
+                    "title": "Newsletter title",
+                    "content": content,
+                }),
+                true,
+            )
+            .await;
+
+        // Assert
+        assert_eq!(
+            status_code,
+            StatusCode::BAD_REQUEST,
+            "The API did not fail with 400 Bad Request when the payload was {}.",
+            error_message
+        );
+    }
+
+    let n_issues = sqlx::query!("SELECT COUNT(*) AS n_issues FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n_issues;
+    assert_eq!(n_issues, Some(0));
+}
+
+#[tokio::test]
+async fn drafts_with_unknown_variables_cannot_be_published() {
+    // Arrange
+    let app = setup_app().await;
+    let (status_code, body) = app
+        .authenticated_request(
+            http::Method::POST,
+            "/newsletters/drafts",
+            Some(serde_json::json!({
+                "title": "Newsletter title",
+                "content": {
+                    "text": "Hi {{ nickname }}",
+                    "html": "<p>Hi {{ name }}</p>",
+                }
+            })),
+        )
+        .await;
+    // 下書きは編集途中の内容を保存できる
+    assert_eq!(status_code, StatusCode::CREATED);
+    let draft_id = body["draft_id"].as_str().unwrap();
+
+    // Act
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::POST,
+            &format!("/newsletters/drafts/{}/publish", draft_id),
+            None,
+        )
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::GET,
+            &format!("/newsletters/drafts/{}", draft_id),
+            None,
+        )
+        .await;
+    assert_eq!(status_code, StatusCode::OK);
+}
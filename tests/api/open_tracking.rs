@@ -0,0 +1,229 @@
+use axum::{
+    body::Body,
+    http::{self, Request, StatusCode},
+};
+use tower::ServiceExt;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{setup_app, TestApp};
+
+/// 号を配信して、送信されたメールの HTML の本文を返却する
+async fn publish_and_dispatch(app: &mut TestApp, track_opens: Option<bool>) -> String {
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let mut body = serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        },
+    });
+    if let Some(track_opens) = track_opens {
+        body["track_opens"] = serde_json::json!(track_opens);
+    }
+    let (status_code, _) = app
+        .authenticated_request(http::Method::POST, "/newsletters", Some(body))
+        .await;
+    assert_eq!(status_code, StatusCode::OK);
+    app.dispatch_all_pending_emails().await;
+
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let messages: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    messages[0]["HtmlBody"].as_str().unwrap().to_owned()
+}
+
+/// HTML の本文から開封を記録するURLのパスとクエリを取り出す
+fn tracking_uri(html: &str) -> Option<String> {
+    let start = html.find("/tracking/open?")?;
+    let end = start + html[start..].find('"')?;
+
+    Some(html[start..end].replace("&amp;", "&"))
+}
+
+async fn open(app: &TestApp, uri: &str, user_agent: &str) -> (StatusCode, Option<String>, Vec<u8>) {
+    let request = Request::builder()
+        .method(http::Method::GET)
+        .uri(uri)
+        .header(http::header::USER_AGENT, user_agent)
+        .body(Body::empty())
+        .unwrap();
+
+    let response = app
+        .app
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("Failed to execute request");
+
+    let status = response.status();
+    let content_type = response
+        .headers()
+        .get(http::header::CONTENT_TYPE)
+        .map(|value| value.to_str().unwrap().to_owned());
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+    (status, content_type, bytes.to_vec())
+}
+
+#[tokio::test]
+async fn opens_are_recorded_through_the_tracking_pixel() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let html = publish_and_dispatch(&mut app, None).await;
+    let uri = tracking_uri(&html).expect("The tracking pixel was not injected");
+
+    // Act
+    let (status, content_type, body) = open(&app, &uri, "Mail/1.0").await;
+    let first_open = sqlx::query!("SELECT first_opened_at FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .first_opened_at;
+    open(&app, &uri, "Mail/2.0").await;
+
+    // Assert
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type.as_deref(), Some("image/gif"));
+    assert!(body.starts_with(b"GIF89a"));
+
+    let delivery =
+        sqlx::query!("SELECT first_opened_at, open_count, user_agent FROM issue_deliveries")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert!(first_open.is_some());
+    assert_eq!(delivery.first_opened_at, first_open);
+    assert_eq!(delivery.open_count, 2);
+    assert_eq!(delivery.user_agent.as_deref(), Some("Mail/2.0"));
+}
+
+#[tokio::test]
+async fn opens_are_counted_in_the_delivery_report() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let html = publish_and_dispatch(&mut app, None).await;
+    let newsletter_issue_id = sqlx::query!("SELECT newsletter_issue_id FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .newsletter_issue_id;
+
+    // Act
+    open(&app, &tracking_uri(&html).unwrap(), "Mail/1.0").await;
+
+    // Assert
+    let (_, report) = app
+        .authenticated_request(
+            http::Method::GET,
+            &format!("/newsletters/{}/report", newsletter_issue_id),
+            None,
+        )
+        .await;
+    assert_eq!(report["counts"]["opened"], 1);
+}
+
+#[tokio::test]
+async fn tampered_tracking_links_are_rejected() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let html = publish_and_dispatch(&mut app, None).await;
+    let uri = tracking_uri(&html).unwrap();
+    let tampered = format!(
+        "{}&subscriber_id={}",
+        &uri[..uri.find("&subscriber_id=").unwrap()],
+        uuid::Uuid::new_v4()
+    ) + &uri[uri.find("&tag=").unwrap()..];
+
+    // Act
+    let (status, _, _) = open(&app, &tampered, "Mail/1.0").await;
+
+    // Assert
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+    let delivery = sqlx::query!("SELECT open_count FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.open_count, 0);
+}
+
+#[tokio::test]
+async fn issues_can_opt_out_of_open_tracking() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    // Act
+    let html = publish_and_dispatch(&mut app, Some(false)).await;
+
+    // Assert
+    assert_eq!(tracking_uri(&html), None);
+}
+
+#[tokio::test]
+async fn subscribers_can_opt_out_of_open_tracking() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+
+    // Act
+    let (status_code, body) = app
+        .authenticated_request(
+            http::Method::PUT,
+            &format!("/subscribers/{}/tracking", subscriber_id),
+            Some(serde_json::json!({ "track_opens": false })),
+        )
+        .await;
+    let html = publish_and_dispatch(&mut app, None).await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body["track_opens"], false);
+    assert_eq!(tracking_uri(&html), None);
+}
+
+#[tokio::test]
+async fn opens_are_not_recorded_after_the_subscriber_opted_out() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let html = publish_and_dispatch(&mut app, None).await;
+    sqlx::query!("UPDATE subscriptions SET track_opens = false")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let (status, content_type, _) = open(&app, &tracking_uri(&html).unwrap(), "Mail/1.0").await;
+
+    // Assert
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(content_type.as_deref(), Some("image/gif"));
+    let delivery = sqlx::query!("SELECT open_count, first_opened_at FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.open_count, 0);
+    assert!(delivery.first_opened_at.is_none());
+}
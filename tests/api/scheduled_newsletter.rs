@@ -0,0 +1,197 @@
+use axum::http::{self, StatusCode};
+use chrono::{Duration, Utc};
+use wiremock::{
+    matchers::{any, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{setup_app, TestApp};
+
+fn scheduled_newsletter_request_body(send_at: chrono::DateTime<Utc>) -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        },
+        "send_at": send_at,
+    })
+}
+
+async fn schedule_newsletter(app: &TestApp, send_at: chrono::DateTime<Utc>) -> String {
+    let (status_code, body) = app
+        .authenticated_request(
+            http::Method::POST,
+            "/newsletters",
+            Some(scheduled_newsletter_request_body(send_at)),
+        )
+        .await;
+    assert_eq!(status_code, StatusCode::ACCEPTED);
+
+    body["newsletter_issue_id"].as_str().unwrap().to_owned()
+}
+
+#[tokio::test]
+async fn scheduled_newsletters_are_not_delivered_before_send_at() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    schedule_newsletter(&app, Utc::now() + Duration::hours(1)).await;
+    app.release_scheduled_issues().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    // ドロップ時にメールが送信されていないことを検証する
+}
+
+#[tokio::test]
+async fn scheduled_newsletters_are_delivered_once_send_at_has_passed() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    schedule_newsletter(&app, Utc::now() + Duration::hours(1)).await;
+
+    // 配信予定日時を過ぎたことにする
+    sqlx::query!("UPDATE newsletter_issues SET send_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    app.release_scheduled_issues().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let issue = sqlx::query!("SELECT status, published_at FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(issue.status, "published");
+    assert!(issue.published_at.is_some());
+}
+
+#[tokio::test]
+async fn scheduled_newsletters_are_listed() {
+    // Arrange
+    let app = setup_app().await;
+    let newsletter_issue_id = schedule_newsletter(&app, Utc::now() + Duration::hours(1)).await;
+
+    // Act
+    let (status_code, body) = app
+        .authenticated_request(http::Method::GET, "/newsletters/scheduled", None)
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    let issues = body.as_array().unwrap();
+    assert_eq!(issues.len(), 1);
+    assert_eq!(issues[0]["newsletter_issue_id"], newsletter_issue_id);
+    assert_eq!(issues[0]["title"], "Newsletter title");
+}
+
+#[tokio::test]
+async fn scheduled_newsletters_can_be_rescheduled() {
+    // Arrange
+    let app = setup_app().await;
+    let newsletter_issue_id = schedule_newsletter(&app, Utc::now() + Duration::hours(1)).await;
+    let send_at = Utc::now() + Duration::days(1);
+
+    // Act
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::PUT,
+            &format!("/newsletters/scheduled/{}", newsletter_issue_id),
+            Some(serde_json::json!({ "send_at": send_at })),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+
+    let saved = sqlx::query!(r#"SELECT send_at as "send_at!" FROM newsletter_issues"#)
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.send_at.timestamp(), send_at.timestamp());
+}
+
+#[tokio::test]
+async fn cancelled_newsletters_are_never_delivered() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let newsletter_issue_id = schedule_newsletter(&app, Utc::now() + Duration::hours(1)).await;
+    let uri = format!("/newsletters/scheduled/{}", newsletter_issue_id);
+
+    // Act
+    let (status_code, _) = app
+        .authenticated_request(http::Method::DELETE, &uri, None)
+        .await;
+    assert_eq!(status_code, StatusCode::NO_CONTENT);
+
+    sqlx::query!("UPDATE newsletter_issues SET send_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    app.release_scheduled_issues().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    // 取り消し済みの予約配信は再度取り消せない
+    let (status_code, _) = app
+        .authenticated_request(http::Method::DELETE, &uri, None)
+        .await;
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn send_at_in_the_past_is_delivered_immediately() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::POST,
+            "/newsletters",
+            Some(scheduled_newsletter_request_body(
+                Utc::now() - Duration::hours(1),
+            )),
+        )
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+}
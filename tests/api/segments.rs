@@ -0,0 +1,343 @@
+use std::collections::HashSet;
+
+use axum::http::{self, StatusCode};
+use chrono::{Duration, Utc};
+use wiremock::{
+    matchers::{any, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{setup_app, TestApp};
+
+fn newsletter_request_body(segment: &str) -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        },
+        "segment": segment,
+    })
+}
+
+/// 確認済みの購読者を作成してタグを付与し、購読者ごとのメールアドレスを返却する
+async fn create_tagged_subscribers(app: &mut TestApp, tags: &[&[&str]]) -> Vec<String> {
+    let mut emails = Vec::new();
+    for subscriber_tags in tags {
+        app.create_confirmed_subscriber().await;
+        let subscriber = sqlx::query!(
+            "SELECT id, email FROM subscriptions ORDER BY subscribed_at DESC, email LIMIT 1"
+        )
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+
+        let (status_code, _) = app
+            .authenticated_request(
+                http::Method::PUT,
+                &format!("/subscribers/{}/tags", subscriber.id),
+                Some(serde_json::json!({ "tags": subscriber_tags })),
+            )
+            .await;
+        assert_eq!(status_code, StatusCode::OK);
+
+        emails.push(subscriber.email);
+    }
+
+    emails
+}
+
+/// バッチ送信されたメールの宛先を全て取り出す
+async fn delivered_recipients(app: &TestApp) -> HashSet<String> {
+    app.email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .filter(|request| request.url.path() == "/email/batch")
+        .flat_map(|request| {
+            let messages: Vec<serde_json::Value> = serde_json::from_slice(&request.body).unwrap();
+            messages
+                .into_iter()
+                .map(|message| message["To"].as_str().unwrap().to_owned())
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn subscriber_tags_are_replaced_normalized_and_deduplicated() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    let uri = format!("/subscribers/{}/tags", subscriber_id);
+
+    // Act
+    app.authenticated_request(
+        http::Method::PUT,
+        &uri,
+        Some(serde_json::json!({ "tags": ["old"] })),
+    )
+    .await;
+    let (status_code, body) = app
+        .authenticated_request(
+            http::Method::PUT,
+            &uri,
+            Some(serde_json::json!({ "tags": ["beta", " early-access ", "beta"] })),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body["tags"], serde_json::json!(["beta", "early-access"]));
+
+    let saved = sqlx::query!("SELECT tags FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(saved.tags, vec!["beta", "early-access"]);
+}
+
+#[tokio::test]
+async fn invalid_subscriber_tags_are_rejected() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let subscriber_id = sqlx::query!("SELECT id FROM subscriptions")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .id;
+    let test_cases = vec![
+        (
+            serde_json::json!({ "tags": ["beta", "  "] }),
+            "an empty tag",
+        ),
+        (
+            serde_json::json!({ "tags": ["a".repeat(65)] }),
+            "a tag that is too long",
+        ),
+    ];
+
+    for (body, description) in test_cases {
+        // Act
+        let (status_code, _) = app
+            .authenticated_request(
+                http::Method::PUT,
+                &format!("/subscribers/{}/tags", subscriber_id),
+                Some(body),
+            )
+            .await;
+
+        // Assert
+        assert_eq!(
+            status_code,
+            StatusCode::BAD_REQUEST,
+            "The API did not fail with 400 Bad Request when the payload was {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn tagging_an_unknown_subscriber_returns_404() {
+    // Arrange
+    let app = setup_app().await;
+
+    // Act
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::PUT,
+            &format!("/subscribers/{}/tags", uuid::Uuid::new_v4()),
+            Some(serde_json::json!({ "tags": ["beta"] })),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn newsletters_are_delivered_only_to_subscribers_matching_the_segment() {
+    // Arrange
+    let mut app = setup_app().await;
+    let emails = create_tagged_subscribers(
+        &mut app,
+        &[&["beta"], &["beta", "churned"], &[], &["early-access"]],
+    )
+    .await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::POST,
+            "/newsletters",
+            Some(newsletter_request_body(
+                r#"tags include "beta" and not "churned" or "early-access""#,
+            )),
+        )
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(
+        delivered_recipients(&app).await,
+        HashSet::from([emails[0].clone(), emails[3].clone()])
+    );
+
+    let n_deliveries = sqlx::query!("SELECT COUNT(*) AS n_deliveries FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n_deliveries;
+    assert_eq!(n_deliveries, Some(2));
+}
+
+#[tokio::test]
+async fn scheduled_newsletters_are_delivered_to_the_segment_at_release_time() {
+    // Arrange
+    let mut app = setup_app().await;
+    let emails = create_tagged_subscribers(&mut app, &[&["beta"], &[]]).await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    let mut body = newsletter_request_body(r#""beta""#);
+    body["send_at"] = serde_json::json!(Utc::now() + Duration::hours(1));
+    let (status_code, _) = app
+        .authenticated_request(http::Method::POST, "/newsletters", Some(body))
+        .await;
+    assert_eq!(status_code, StatusCode::ACCEPTED);
+
+    // 予約後にタグを付与した購読者も配信対象となる
+    let late_subscriber = sqlx::query!("SELECT id FROM subscriptions WHERE email = $1", emails[1])
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    app.authenticated_request(
+        http::Method::PUT,
+        &format!("/subscribers/{}/tags", late_subscriber.id),
+        Some(serde_json::json!({ "tags": ["beta"] })),
+    )
+    .await;
+
+    sqlx::query!("UPDATE newsletter_issues SET send_at = now() - interval '1 minute'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    app.release_scheduled_issues().await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(delivered_recipients(&app).await, HashSet::from_iter(emails));
+}
+
+#[tokio::test]
+async fn invalid_segments_are_rejected() {
+    // Arrange
+    let app = setup_app().await;
+    let test_cases = vec![
+        ("beta", "an unquoted tag"),
+        (r#""beta" and"#, "an incomplete expression"),
+        (r#"("beta""#, "an unclosed parenthesis"),
+    ];
+
+    for (segment, description) in test_cases {
+        // Act
+        let (status_code, _) = app
+            .authenticated_request(
+                http::Method::POST,
+                "/newsletters",
+                Some(newsletter_request_body(segment)),
+            )
+            .await;
+
+        // Assert
+        assert_eq!(
+            status_code,
+            StatusCode::BAD_REQUEST,
+            "The API did not fail with 400 Bad Request when the segment was {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn dry_run_returns_the_recipient_count_without_publishing() {
+    // Arrange
+    let mut app = setup_app().await;
+    create_tagged_subscribers(&mut app, &[&["beta"], &["beta", "churned"], &[]]).await;
+    app.create_unconfirmed_subscriber().await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    let test_cases = vec![
+        (Some(r#"tags include "beta" and not "churned""#), 1),
+        (Some(r#""beta""#), 2),
+        (Some(r#"not "beta""#), 1),
+        (None, 3),
+    ];
+
+    for (segment, expected) in test_cases {
+        let mut body = newsletter_request_body("");
+        body["segment"] = serde_json::json!(segment);
+
+        // Act
+        let (status_code, body) = app
+            .authenticated_request(http::Method::POST, "/newsletters/dry-run", Some(body))
+            .await;
+
+        // Assert
+        assert_eq!(status_code, StatusCode::OK);
+        assert_eq!(body["recipients"], expected, "segment: {:?}", segment);
+    }
+
+    app.dispatch_all_pending_emails().await;
+    let n_issues = sqlx::query!("SELECT COUNT(*) AS n_issues FROM newsletter_issues")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap()
+        .n_issues;
+    assert_eq!(n_issues, Some(0));
+}
+
+#[tokio::test]
+async fn dry_run_rejects_invalid_segments() {
+    // Arrange
+    let app = setup_app().await;
+
+    // Act
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::POST,
+            "/newsletters/dry-run",
+            Some(newsletter_request_body("tags beta")),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::BAD_REQUEST);
+}
@@ -1,9 +1,10 @@
-use crate::helpers::setup_app;
+use crate::helpers::{setup_app, setup_app_with};
 use axum::http::StatusCode;
 use wiremock::{
     matchers::{method, path},
     Mock, ResponseTemplate,
 };
+use zero2prod::email_client::EmailProvider;
 
 #[tokio::test]
 async fn subscribe_returns_200_for_valid_from_data() {
@@ -172,3 +173,28 @@ async fn subscribe_fails_if_there_is_a_fatal_database_error() {
     // Assert
     assert_eq!(status, StatusCode::INTERNAL_SERVER_ERROR);
 }
+
+#[tokio::test]
+async fn subscribe_fails_over_to_the_next_provider_when_the_email_server_is_down() {
+    // Arrange
+    let mut test_app = setup_app_with(|c| {
+        c.email_client.fallback_providers = vec![EmailProvider::Mailbox];
+    })
+    .await;
+    let body = "name=shimopino&email=shimopino%40example.com";
+
+    Mock::given(path("/email"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(503))
+        .expect(1)
+        .mount(&test_app.email_server)
+        .await;
+
+    // Act
+    let (status, _) = test_app.post_subscription(body.into()).await;
+
+    // Assert
+    assert_eq!(status, StatusCode::CREATED);
+    let (_, mailbox) = test_app.get_html("/_dev/mailbox").await;
+    assert!(mailbox.contains("shimopino@example.com"));
+}
@@ -0,0 +1,212 @@
+use axum::http::{self, StatusCode};
+use wiremock::{
+    matchers::{any, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{setup_app, TestApp};
+
+async fn suppress(app: &TestApp, value: &str) -> (StatusCode, serde_json::Value) {
+    app.authenticated_request(
+        http::Method::POST,
+        "/suppressions",
+        Some(serde_json::json!({ "value": value, "reason": "legal", "note": "Request #42" })),
+    )
+    .await
+}
+
+fn newsletter_body() -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Newsletter body as plain text",
+            "html": "<p>Newsletter body as HTML</p>",
+        },
+    })
+}
+
+#[tokio::test]
+async fn suppressions_can_be_added_listed_and_deleted() {
+    // Arrange
+    let app = setup_app().await;
+
+    // Act
+    let (status_code, created) = suppress(&app, "Someone@Example.com").await;
+    let (_, duplicate) = suppress(&app, "someone@example.com").await;
+    suppress(&app, "@example.org").await;
+    let (_, listed) = app
+        .authenticated_request(http::Method::GET, "/suppressions", None)
+        .await;
+    let (delete_status, _) = app
+        .authenticated_request(
+            http::Method::DELETE,
+            &format!("/suppressions/{}", created["id"].as_str().unwrap()),
+            None,
+        )
+        .await;
+    let (_, remaining) = app
+        .authenticated_request(http::Method::GET, "/suppressions", None)
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::CREATED);
+    assert_eq!(created["kind"], "address");
+    assert_eq!(created["value"], "someone@example.com");
+    assert_eq!(created["reason"], "legal");
+    assert_eq!(duplicate["id"], created["id"]);
+
+    let values: Vec<_> = listed
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|suppression| suppression["value"].as_str().unwrap())
+        .collect();
+    assert_eq!(values, vec!["someone@example.com", "example.org"]);
+
+    assert_eq!(delete_status, StatusCode::NO_CONTENT);
+    assert_eq!(remaining.as_array().unwrap().len(), 1);
+    assert_eq!(remaining[0]["kind"], "domain");
+}
+
+#[tokio::test]
+async fn invalid_suppressions_are_rejected() {
+    // Arrange
+    let app = setup_app().await;
+    let test_cases = vec![
+        (
+            serde_json::json!({ "value": "localhost" }),
+            "an invalid domain",
+        ),
+        (
+            serde_json::json!({ "value": "someone@example.com", "reason": "bounce" }),
+            "a reason reserved for webhooks",
+        ),
+    ];
+
+    for (body, description) in test_cases {
+        // Act
+        let (status_code, _) = app
+            .authenticated_request(http::Method::POST, "/suppressions", Some(body))
+            .await;
+
+        // Assert
+        assert_eq!(
+            status_code,
+            StatusCode::BAD_REQUEST,
+            "The API did not fail with 400 Bad Request when the payload had {}.",
+            description
+        );
+    }
+}
+
+#[tokio::test]
+async fn subscribing_with_a_suppressed_address_is_accepted_without_sending_an_email() {
+    // Arrange
+    let mut app = setup_app().await;
+    suppress(&app, "suppressed@example.com").await;
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (status, _) = app
+        .post_subscription("name=shimopino&email=Suppressed%40example.com".into())
+        .await;
+
+    // Assert
+    assert_eq!(status, StatusCode::CREATED);
+    assert_eq!(app.subscription_status().await, "pending_confirmation");
+}
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_suppressed_domains() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let email = app.subscriber_email().await;
+    let domain = email.split('@').nth(1).unwrap();
+    suppress(&app, domain).await;
+
+    Mock::given(path("/email/batch"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (_, dry_run) = app
+        .authenticated_request(
+            http::Method::POST,
+            "/newsletters/dry-run",
+            Some(newsletter_body()),
+        )
+        .await;
+    app.publish_newsletter("Newsletter title").await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(dry_run["recipients"], 0);
+}
+
+#[tokio::test]
+async fn queued_deliveries_are_skipped_after_the_address_is_suppressed() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    app.publish_newsletter("Newsletter title").await;
+
+    // Act
+    suppress(&app, &app.subscriber_email().await).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let delivery = sqlx::query!("SELECT status, last_error FROM issue_deliveries")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(delivery.status, "skipped");
+    assert_eq!(
+        delivery.last_error.as_deref(),
+        Some("The address is suppressed")
+    );
+}
+
+#[tokio::test]
+async fn test_sends_are_not_delivered_to_suppressed_addresses() {
+    // Arrange
+    let app = setup_app().await;
+    suppress(&app, "suppressed@example.com").await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+    let mut body = newsletter_body();
+    body["recipients"] = serde_json::json!(["editor@example.com", "suppressed@example.com"]);
+
+    // Act
+    let (status_code, body) = app
+        .authenticated_request(http::Method::POST, "/newsletters/test", Some(body))
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(body["sent"], serde_json::json!(["editor@example.com"]));
+    assert_eq!(body["failed"][0]["email"], "suppressed@example.com");
+
+    let request = &app.email_server.received_requests().await.unwrap()[0];
+    let messages: Vec<serde_json::Value> = serde_json::from_slice(&request.body).unwrap();
+    assert_eq!(messages.len(), 1);
+    assert_eq!(messages[0]["To"], "editor@example.com");
+}
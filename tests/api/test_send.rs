@@ -0,0 +1,185 @@
+use axum::{
+    body::Body,
+    http::{self, Request, StatusCode},
+};
+use tower::ServiceExt;
+use wiremock::{
+    matchers::{any, method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::setup_app;
+
+fn test_send_body(recipients: serde_json::Value) -> serde_json::Value {
+    serde_json::json!({
+        "title": "Newsletter title",
+        "content": {
+            "text": "Hi {{ name }}, this goes to {{ email }}",
+            "html": "<p>Hi {{ name }}</p>",
+        },
+        "recipients": recipients,
+    })
+}
+
+#[tokio::test]
+async fn test_sends_are_delivered_only_to_the_given_addresses() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (status_code, body) = app
+        .authenticated_request(
+            http::Method::POST,
+            "/newsletters/test",
+            Some(test_send_body(serde_json::json!([
+                "editor@example.com",
+                "reviewer@example.com",
+                "editor@example.com"
+            ]))),
+        )
+        .await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+    assert_eq!(
+        body["sent"],
+        serde_json::json!(["editor@example.com", "reviewer@example.com"])
+    );
+
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let messages: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    let messages = messages.as_array().unwrap();
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0]["To"], "editor@example.com");
+    assert_eq!(messages[0]["Subject"], "[TEST] Newsletter title");
+    assert!(messages[0]["TextBody"]
+        .as_str()
+        .unwrap()
+        .starts_with("Hi Test subscriber, this goes to editor@example.com"));
+}
+
+#[tokio::test]
+async fn test_sends_do_not_touch_subscribers_or_delivery_records() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+
+    Mock::given(path("/email/batch"))
+        .respond_with(ResponseTemplate::new(200))
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    let (status_code, _) = app
+        .authenticated_request(
+            http::Method::POST,
+            "/newsletters/test",
+            Some(test_send_body(serde_json::json!(["editor@example.com"]))),
+        )
+        .await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    assert_eq!(status_code, StatusCode::OK);
+
+    let counts = sqlx::query!(
+        r#"
+        SELECT
+            (SELECT COUNT(*) FROM newsletter_issues) as "issues!",
+            (SELECT COUNT(*) FROM issue_delivery_queue) as "tasks!",
+            (SELECT COUNT(*) FROM issue_deliveries) as "deliveries!",
+            (SELECT COUNT(*) FROM subscriptions) as "subscribers!"
+        "#
+    )
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(counts.issues, 0);
+    assert_eq!(counts.tasks, 0);
+    assert_eq!(counts.deliveries, 0);
+    assert_eq!(counts.subscribers, 1);
+}
+
+#[tokio::test]
+async fn test_sends_with_invalid_recipients_are_rejected() {
+    // Arrange
+    let app = setup_app().await;
+    let test_cases = vec![
+        (serde_json::json!([]), "no recipients"),
+        (serde_json::json!(["not-an-email"]), "an invalid email"),
+        (
+            serde_json::json!([
+                "a@example.com",
+                "b@example.com",
+                "c@example.com",
+                "d@example.com",
+                "e@example.com",
+                "f@example.com"
+            ]),
+            "too many recipients",
+        ),
+    ];
+
+    Mock::given(any())
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    for (recipients, error_message) in test_cases {
+        // Act
+        let (status_code, _) = app
+            .authenticated_request(
+                http::Method::POST,
+                "/newsletters/test",
+                Some(test_send_body(recipients)),
+            )
+            .await;
+
+        // Assert
+        assert_eq!(
+            status_code,
+            StatusCode::BAD_REQUEST,
+            "The API did not fail with 400 Bad Request when the payload had {}.",
+            error_message
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_sends_require_authentication() {
+    // Arrange
+    let app = setup_app().await;
+    let body = test_send_body(serde_json::json!(["editor@example.com"]));
+
+    // Act
+    let response = app
+        .app
+        .oneshot(
+            Request::builder()
+                .method(http::Method::POST)
+                .uri("/newsletters/test")
+                .header(http::header::CONTENT_TYPE, "application/json")
+                .body(Body::from(serde_json::to_vec(&body).unwrap()))
+                .unwrap(),
+        )
+        .await
+        .expect("Failed to execute request");
+
+    // Assert
+    assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+}
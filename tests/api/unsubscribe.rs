@@ -0,0 +1,173 @@
+use axum::{
+    body::Body,
+    http::{self, Request, StatusCode},
+};
+use tower::ServiceExt;
+use wiremock::{
+    matchers::{method, path},
+    Mock, ResponseTemplate,
+};
+
+use crate::helpers::{setup_app, TestApp};
+
+/// 号を配信して、送信されたメールの List-Unsubscribe ヘッダーからURLのパスとクエリを取り出す
+async fn publish_and_get_unsubscribe_uri(app: &mut TestApp) -> String {
+    let _mock_guard = Mock::given(path("/email/batch"))
+        .and(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount_as_scoped(&app.email_server)
+        .await;
+
+    app.publish_newsletter("Newsletter title").await;
+    app.dispatch_all_pending_emails().await;
+
+    let request = app
+        .email_server
+        .received_requests()
+        .await
+        .unwrap()
+        .pop()
+        .unwrap();
+    let messages: serde_json::Value = serde_json::from_slice(&request.body).unwrap();
+    let headers = messages[0]["Headers"].as_array().unwrap();
+    assert_eq!(headers[1]["Name"], "List-Unsubscribe-Post");
+    assert_eq!(headers[1]["Value"], "List-Unsubscribe=One-Click");
+
+    assert_eq!(headers[0]["Name"], "List-Unsubscribe");
+    let header = headers[0]["Value"].as_str().unwrap();
+    let url = header
+        .strip_prefix('<')
+        .and_then(|url| url.strip_suffix('>'))
+        .unwrap();
+    let start = url.find("/subscriptions/unsubscribe?").unwrap();
+
+    url[start..].to_owned()
+}
+
+async fn post_one_click_unsubscribe(app: &TestApp, uri: &str) -> StatusCode {
+    let request = Request::builder()
+        .method(http::Method::POST)
+        .uri(uri)
+        .header(
+            http::header::CONTENT_TYPE,
+            "application/x-www-form-urlencoded",
+        )
+        .body(Body::from("List-Unsubscribe=One-Click"))
+        .unwrap();
+
+    let response = app
+        .app
+        .clone()
+        .oneshot(request)
+        .await
+        .expect("Failed to execute request");
+
+    response.status()
+}
+
+#[tokio::test]
+async fn the_unsubscribe_link_shows_a_confirmation_page_without_unsubscribing() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let uri = publish_and_get_unsubscribe_uri(&mut app).await;
+
+    // Act
+    let (status, html) = app.get_html(&uri).await;
+
+    // Assert
+    assert_eq!(status, StatusCode::OK);
+    assert!(html.contains(r#"method="post""#));
+    assert!(html.contains(&uri.replace('&', "&amp;")));
+    assert_eq!(app.subscription_status().await, "confirmed");
+}
+
+#[tokio::test]
+async fn one_click_unsubscribe_marks_the_subscription_as_unsubscribed() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let uri = publish_and_get_unsubscribe_uri(&mut app).await;
+
+    // Act
+    let status = post_one_click_unsubscribe(&app, &uri).await;
+
+    // Assert
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(app.subscription_status().await, "unsubscribed");
+}
+
+#[tokio::test]
+async fn tampered_unsubscribe_links_are_rejected() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let uri = publish_and_get_unsubscribe_uri(&mut app).await;
+    let tampered = format!(
+        "/subscriptions/unsubscribe?subscriber_id={}{}",
+        uuid::Uuid::new_v4(),
+        &uri[uri.find("&tag=").unwrap()..]
+    );
+
+    // Act
+    let (get_status, _) = app.get_html(&tampered).await;
+    let post_status = post_one_click_unsubscribe(&app, &tampered).await;
+
+    // Assert
+    assert_eq!(get_status, StatusCode::UNAUTHORIZED);
+    assert_eq!(post_status, StatusCode::UNAUTHORIZED);
+    assert_eq!(app.subscription_status().await, "confirmed");
+}
+
+#[tokio::test]
+async fn newsletters_are_not_delivered_to_unsubscribed_subscribers() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let uri = publish_and_get_unsubscribe_uri(&mut app).await;
+    post_one_click_unsubscribe(&app, &uri).await;
+
+    Mock::given(path("/email/batch"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+
+    // Act
+    app.publish_newsletter("Newsletter title").await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    // Mock verifies on Drop that we haven't sent the newsletter email
+}
+
+#[tokio::test]
+async fn queued_deliveries_are_skipped_after_unsubscribing() {
+    // Arrange
+    let mut app = setup_app().await;
+    app.create_confirmed_subscriber().await;
+    let uri = publish_and_get_unsubscribe_uri(&mut app).await;
+
+    Mock::given(path("/email/batch"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(0)
+        .mount(&app.email_server)
+        .await;
+    app.publish_newsletter("Newsletter title").await;
+
+    // Act
+    post_one_click_unsubscribe(&app, &uri).await;
+    app.dispatch_all_pending_emails().await;
+
+    // Assert
+    let statuses: Vec<_> = sqlx::query!("SELECT status FROM issue_deliveries ORDER BY status")
+        .fetch_all(&app.db_pool)
+        .await
+        .unwrap()
+        .into_iter()
+        .map(|delivery| delivery.status)
+        .collect();
+    // 最初の号は送信済み、配信停止後に処理した号は送信せずに記録する
+    assert_eq!(statuses, vec!["sent", "skipped"]);
+}